@@ -0,0 +1,901 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign};
+use std::str::FromStr;
+
+/*
+    The integer types above stop at 128 bits. `BigInt` keeps its magnitude as a little-endian vector of 32-bit limbs
+    plus a sign flag, so it can grow as large as memory allows. Zero is always stored as an empty magnitude with a positive sign.
+*/
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+// Below this many limbs the schoolbook product is faster than splitting the operands again.
+const KARATSUBA_THRESHOLD: usize = 32;
+
+const LIMB_BITS: u32 = 32;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseBigIntError {
+    Empty,
+    InvalidDigit { index: usize, found: char },
+    UnsupportedRadix(u32),
+}
+
+impl fmt::Display for ParseBigIntError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseBigIntError::Empty => write!(f, "cannot parse integer from empty string"),
+            ParseBigIntError::InvalidDigit { index, found } => {
+                write!(f, "invalid digit {:?} at byte {}", found, index)
+            }
+            ParseBigIntError::UnsupportedRadix(radix) => {
+                write!(f, "radix {} is not supported (use 2, 10 or 16)", radix)
+            }
+        }
+    }
+}
+
+impl Error for ParseBigIntError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TryFromBigIntError(());
+
+impl fmt::Display for TryFromBigIntError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "big integer out of range for the target integer type")
+    }
+}
+
+impl Error for TryFromBigIntError {}
+
+impl BigInt {
+    pub fn zero() -> BigInt {
+        BigInt { negative: false, magnitude: Vec::new() }
+    }
+
+    pub fn one() -> BigInt {
+        BigInt { negative: false, magnitude: vec![1] }
+    }
+
+    fn from_parts(negative: bool, mut magnitude: Vec<u32>) -> BigInt {
+        trim(&mut magnitude);
+        let negative = negative && !magnitude.is_empty();
+        BigInt { negative, magnitude }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn signum(&self) -> i32 {
+        if self.negative {
+            -1
+        } else if self.is_zero() {
+            0
+        } else {
+            1
+        }
+    }
+
+    pub fn abs(&self) -> BigInt {
+        BigInt { negative: false, magnitude: self.magnitude.clone() }
+    }
+
+    // Number of bits needed to represent the magnitude; zero needs none.
+    pub fn bits(&self) -> u64 {
+        match self.magnitude.last() {
+            None => 0,
+            Some(top) => {
+                (self.magnitude.len() as u64) * u64::from(LIMB_BITS) - u64::from(top.leading_zeros())
+            }
+        }
+    }
+
+    pub fn pow(&self, mut exp: u32) -> BigInt {
+        let mut base = self.clone();
+        let mut result = BigInt::one();
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = &result * &base;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = &base * &base;
+            }
+        }
+
+        result
+    }
+
+    /*
+        Division truncates toward zero and the remainder takes the sign of the dividend,
+        exactly like `/` and `%` on the primitive integer types.
+    */
+    pub fn checked_div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let (quotient, remainder) = div_rem_magnitude(&self.magnitude, &other.magnitude);
+
+        Some((
+            BigInt::from_parts(self.negative != other.negative, quotient),
+            BigInt::from_parts(self.negative, remainder),
+        ))
+    }
+
+    pub fn div_rem(&self, other: &BigInt) -> (BigInt, BigInt) {
+        self.checked_div_rem(other).expect("attempt to divide by zero")
+    }
+
+    // Floored division: the remainder always has the sign of the divisor, like Python's `divmod`.
+    pub fn div_mod_floor(&self, other: &BigInt) -> (BigInt, BigInt) {
+        let (quotient, remainder) = self.div_rem(other);
+
+        if !remainder.is_zero() && remainder.negative != other.negative {
+            (quotient - BigInt::one(), remainder + other)
+        } else {
+            (quotient, remainder)
+        }
+    }
+
+    pub fn from_str_radix(src: &str, radix: u32) -> Result<BigInt, ParseBigIntError> {
+        let chunk_digits = match radix {
+            2 => 31,
+            10 => 9,
+            16 => 7,
+            _ => return Err(ParseBigIntError::UnsupportedRadix(radix)),
+        };
+
+        let (negative, digits, offset) = match src.as_bytes().first() {
+            Some(b'-') => (true, &src[1..], 1),
+            Some(b'+') => (false, &src[1..], 1),
+            _ => (false, src, 0),
+        };
+
+        if digits.is_empty() {
+            return Err(ParseBigIntError::Empty);
+        }
+
+        let mut values = Vec::with_capacity(digits.len());
+        for (index, ch) in digits.char_indices() {
+            match ch.to_digit(radix) {
+                Some(value) => values.push(value),
+                None => return Err(ParseBigIntError::InvalidDigit { index: index + offset, found: ch }),
+            }
+        }
+
+        /*
+            Instead of multiplying the whole number by the radix once per digit, we fold as many digits as fit in a limb
+            into a single value first, then multiply by radix^chunk_len.
+        */
+        let mut magnitude = Vec::new();
+        let head = values.len() % chunk_digits;
+        let chunks = if head == 0 {
+            values.chunks(chunk_digits).collect::<Vec<_>>()
+        } else {
+            let mut chunks = vec![&values[..head]];
+            chunks.extend(values[head..].chunks(chunk_digits));
+            chunks
+        };
+
+        for chunk in chunks {
+            let mut value = 0u32;
+            let mut scale = 1u32;
+            for &digit in chunk {
+                value = value * radix + digit;
+                scale *= radix;
+            }
+            mul_small_in_place(&mut magnitude, scale);
+            add_small_in_place(&mut magnitude, value);
+        }
+
+        Ok(BigInt::from_parts(negative, magnitude))
+    }
+
+    // `None` for the radixes `from_str_radix` doesn't accept either.
+    pub fn to_str_radix(&self, radix: u32) -> Option<String> {
+        let digits = match radix {
+            2 => self.magnitude_to_power_of_two_radix(1),
+            16 => self.magnitude_to_power_of_two_radix(4),
+            10 => self.magnitude_to_decimal(),
+            _ => return None,
+        };
+
+        if self.negative {
+            Some(format!("-{}", digits))
+        } else {
+            Some(digits)
+        }
+    }
+
+    fn magnitude_to_power_of_two_radix(&self, bits_per_digit: u32) -> String {
+        if self.is_zero() {
+            return String::from("0");
+        }
+
+        let mask = (1u32 << bits_per_digit) - 1;
+        let digits_per_limb = LIMB_BITS / bits_per_digit;
+        let mut out = Vec::with_capacity(self.magnitude.len() * digits_per_limb as usize);
+
+        for &limb in &self.magnitude {
+            for i in 0..digits_per_limb {
+                let digit = (limb >> (i * bits_per_digit)) & mask;
+                out.push(std::char::from_digit(digit, 1 << bits_per_digit).unwrap());
+            }
+        }
+
+        while out.len() > 1 && out.last() == Some(&'0') {
+            out.pop();
+        }
+
+        out.iter().rev().collect()
+    }
+
+    fn magnitude_to_decimal(&self) -> String {
+        if self.is_zero() {
+            return String::from("0");
+        }
+
+        // Peel off nine decimal digits at a time; 10^9 is the largest power of ten that fits in a limb.
+        let mut chunks = Vec::new();
+        let mut rest = self.magnitude.clone();
+        while !rest.is_empty() {
+            let (quotient, remainder) = div_rem_small(&rest, 1_000_000_000);
+            chunks.push(remainder);
+            rest = quotient;
+        }
+
+        let mut out = chunks.last().unwrap().to_string();
+        for chunk in chunks.iter().rev().skip(1) {
+            out.push_str(&format!("{:09}", chunk));
+        }
+        out
+    }
+
+    fn from_u128(value: u128) -> BigInt {
+        let mut magnitude = Vec::with_capacity(4);
+        let mut rest = value;
+        while rest != 0 {
+            magnitude.push(rest as u32);
+            rest >>= LIMB_BITS;
+        }
+        BigInt { negative: false, magnitude }
+    }
+
+    fn from_i128(value: i128) -> BigInt {
+        let mut result = BigInt::from_u128(value.unsigned_abs());
+        result.negative = value < 0;
+        result
+    }
+
+    fn magnitude_to_u128(&self) -> Option<u128> {
+        if self.magnitude.len() > 4 {
+            return None;
+        }
+        Some(self.magnitude.iter().rev().fold(0u128, |acc, &limb| (acc << LIMB_BITS) | u128::from(limb)))
+    }
+
+    fn to_i128(&self) -> Option<i128> {
+        let magnitude = self.magnitude_to_u128()?;
+        if self.negative {
+            if magnitude == 1u128 << 127 {
+                Some(i128::MIN)
+            } else {
+                i128::try_from(magnitude).ok().map(|value| -value)
+            }
+        } else {
+            i128::try_from(magnitude).ok()
+        }
+    }
+
+    fn to_u128(&self) -> Option<u128> {
+        if self.negative {
+            None
+        } else {
+            self.magnitude_to_u128()
+        }
+    }
+}
+
+fn trim(magnitude: &mut Vec<u32>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut out = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+
+    for (i, &limb) in long.iter().enumerate() {
+        let sum = u64::from(limb) + u64::from(*short.get(i).unwrap_or(&0)) + carry;
+        out.push(sum as u32);
+        carry = sum >> LIMB_BITS;
+    }
+    if carry != 0 {
+        out.push(carry as u32);
+    }
+
+    out
+}
+
+// Requires `a >= b`.
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+
+    for (i, &limb) in a.iter().enumerate() {
+        let mut diff = i64::from(limb) - i64::from(*b.get(i).unwrap_or(&0)) - borrow;
+        borrow = if diff < 0 {
+            diff += 1 << LIMB_BITS;
+            1
+        } else {
+            0
+        };
+        out.push(diff as u32);
+    }
+    debug_assert_eq!(borrow, 0);
+
+    trim(&mut out);
+    out
+}
+
+// Adds `value` into `target` starting at limb `offset`, growing `target` as needed.
+fn add_shifted_in_place(target: &mut Vec<u32>, value: &[u32], offset: usize) {
+    if target.len() < offset + value.len() {
+        target.resize(offset + value.len(), 0);
+    }
+
+    let mut carry = 0u64;
+    let mut i = 0;
+    while i < value.len() || carry != 0 {
+        if offset + i == target.len() {
+            target.push(0);
+        }
+        let sum = u64::from(target[offset + i]) + u64::from(*value.get(i).unwrap_or(&0)) + carry;
+        target[offset + i] = sum as u32;
+        carry = sum >> LIMB_BITS;
+        i += 1;
+    }
+}
+
+fn schoolbook_mul(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = vec![0u32; a.len() + b.len()];
+
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &y) in b.iter().enumerate() {
+            let product = u64::from(x) * u64::from(y) + u64::from(out[i + j]) + carry;
+            out[i + j] = product as u32;
+            carry = product >> LIMB_BITS;
+        }
+        out[i + b.len()] = carry as u32;
+    }
+
+    trim(&mut out);
+    out
+}
+
+/*
+    Karatsuba splits each operand at `half` limbs, a = a1·B^half + a0, and gets away with three half-size products:
+    z2 = a1·b1, z0 = a0·b0, z1 = (a0 + a1)(b0 + b1) - z2 - z0.
+*/
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    if a.len().min(b.len()) < KARATSUBA_THRESHOLD {
+        return schoolbook_mul(a, b);
+    }
+
+    let half = a.len().max(b.len()) / 2;
+    let split = |x: &[u32]| -> (Vec<u32>, Vec<u32>) {
+        if x.len() <= half {
+            (x.to_vec(), Vec::new())
+        } else {
+            let mut low = x[..half].to_vec();
+            trim(&mut low);
+            (low, x[half..].to_vec())
+        }
+    };
+    let (a0, a1) = split(a);
+    let (b0, b1) = split(b);
+
+    let z0 = mul_magnitude(&a0, &b0);
+    let z2 = mul_magnitude(&a1, &b1);
+    let z1 = mul_magnitude(&add_magnitude(&a0, &a1), &add_magnitude(&b0, &b1));
+    let z1 = sub_magnitude(&sub_magnitude(&z1, &z0), &z2);
+
+    let mut out = z0;
+    add_shifted_in_place(&mut out, &z1, half);
+    add_shifted_in_place(&mut out, &z2, 2 * half);
+    trim(&mut out);
+    out
+}
+
+fn mul_small_in_place(magnitude: &mut Vec<u32>, factor: u32) {
+    let mut carry = 0u64;
+    for limb in magnitude.iter_mut() {
+        let product = u64::from(*limb) * u64::from(factor) + carry;
+        *limb = product as u32;
+        carry = product >> LIMB_BITS;
+    }
+    if carry != 0 {
+        magnitude.push(carry as u32);
+    }
+    trim(magnitude);
+}
+
+fn add_small_in_place(magnitude: &mut Vec<u32>, value: u32) {
+    add_shifted_in_place(magnitude, &[value], 0);
+    trim(magnitude);
+}
+
+fn div_rem_small(a: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder = 0u64;
+
+    for i in (0..a.len()).rev() {
+        let current = (remainder << LIMB_BITS) | u64::from(a[i]);
+        quotient[i] = (current / u64::from(divisor)) as u32;
+        remainder = current % u64::from(divisor);
+    }
+
+    trim(&mut quotient);
+    (quotient, remainder as u32)
+}
+
+fn shl_bits(a: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return a.to_vec();
+    }
+    let mut out = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0u32;
+    for &limb in a {
+        out.push((limb << shift) | carry);
+        carry = limb >> (LIMB_BITS - shift);
+    }
+    out.push(carry);
+    out
+}
+
+fn shr_bits(a: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return a.to_vec();
+    }
+    let mut out = vec![0u32; a.len()];
+    for i in 0..a.len() {
+        let high = if i + 1 < a.len() { a[i + 1] << (LIMB_BITS - shift) } else { 0 };
+        out[i] = (a[i] >> shift) | high;
+    }
+    out
+}
+
+/*
+    Long division on limbs (Knuth, TAOCP vol. 2, algorithm D). Both operands are shifted left until the divisor's top bit is set,
+    which guarantees each estimated quotient limb is at most two too large.
+*/
+fn div_rem_magnitude(u: &[u32], v: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_magnitude(u, v) == Ordering::Less {
+        return (Vec::new(), u.to_vec());
+    }
+    if v.len() == 1 {
+        let (quotient, remainder) = div_rem_small(u, v[0]);
+        let remainder = if remainder == 0 { Vec::new() } else { vec![remainder] };
+        return (quotient, remainder);
+    }
+
+    let base = 1u64 << LIMB_BITS;
+    let shift = v[v.len() - 1].leading_zeros();
+    let v = shl_bits(v, shift);
+    let v = &v[..v.len() - if shift == 0 { 0 } else { 1 }];
+    let mut u = shl_bits(u, shift);
+    if shift == 0 {
+        u.push(0);
+    }
+
+    let n = v.len();
+    let m = u.len() - n;
+    let mut quotient = vec![0u32; m];
+
+    for j in (0..m).rev() {
+        let numerator = (u64::from(u[j + n]) << LIMB_BITS) | u64::from(u[j + n - 1]);
+        let mut q_hat = numerator / u64::from(v[n - 1]);
+        let mut r_hat = numerator % u64::from(v[n - 1]);
+
+        while q_hat >= base || q_hat * u64::from(v[n - 2]) > ((r_hat << LIMB_BITS) | u64::from(u[j + n - 2])) {
+            q_hat -= 1;
+            r_hat += u64::from(v[n - 1]);
+            if r_hat >= base {
+                break;
+            }
+        }
+
+        let mut borrow = 0i64;
+        for i in 0..n {
+            let product = q_hat * u64::from(v[i]);
+            let diff = i64::from(u[i + j]) - borrow - (product & 0xFFFF_FFFF) as i64;
+            u[i + j] = diff as u32;
+            borrow = (product >> LIMB_BITS) as i64 - (diff >> LIMB_BITS);
+        }
+        let diff = i64::from(u[j + n]) - borrow;
+        u[j + n] = diff as u32;
+
+        // The estimate was one too large: add the divisor back once.
+        if diff < 0 {
+            q_hat -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let sum = u64::from(u[i + j]) + u64::from(v[i]) + carry;
+                u[i + j] = sum as u32;
+                carry = sum >> LIMB_BITS;
+            }
+            u[j + n] = u[j + n].wrapping_add(carry as u32);
+        }
+
+        quotient[j] = q_hat as u32;
+    }
+
+    let mut remainder = shr_bits(&u[..n], shift);
+    trim(&mut quotient);
+    trim(&mut remainder);
+    (quotient, remainder)
+}
+
+fn add_signed(a_negative: bool, a: &[u32], b_negative: bool, b: &[u32]) -> BigInt {
+    if a_negative == b_negative {
+        return BigInt::from_parts(a_negative, add_magnitude(a, b));
+    }
+
+    match cmp_magnitude(a, b) {
+        Ordering::Equal => BigInt::zero(),
+        Ordering::Greater => BigInt::from_parts(a_negative, sub_magnitude(a, b)),
+        Ordering::Less => BigInt::from_parts(b_negative, sub_magnitude(b, a)),
+    }
+}
+
+impl Default for BigInt {
+    fn default() -> BigInt {
+        BigInt::zero()
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.magnitude)
+    }
+}
+
+impl Add<&BigInt> for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        add_signed(self.negative, &self.magnitude, other.negative, &other.magnitude)
+    }
+}
+
+impl Sub<&BigInt> for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        add_signed(self.negative, &self.magnitude, !other.negative, &other.magnitude)
+    }
+}
+
+impl Mul<&BigInt> for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::from_parts(self.negative != other.negative, mul_magnitude(&self.magnitude, &other.magnitude))
+    }
+}
+
+impl Div<&BigInt> for &BigInt {
+    type Output = BigInt;
+
+    fn div(self, other: &BigInt) -> BigInt {
+        self.div_rem(other).0
+    }
+}
+
+impl Rem<&BigInt> for &BigInt {
+    type Output = BigInt;
+
+    fn rem(self, other: &BigInt) -> BigInt {
+        self.div_rem(other).1
+    }
+}
+
+// The by-value and mixed forms all forward to the reference implementations above.
+macro_rules! forward_binop {
+    ($($trait:ident, $method:ident, $assign_trait:ident, $assign_method:ident;)*) => {$(
+        impl $trait<BigInt> for BigInt {
+            type Output = BigInt;
+
+            fn $method(self, other: BigInt) -> BigInt {
+                (&self).$method(&other)
+            }
+        }
+
+        impl<'a> $trait<&'a BigInt> for BigInt {
+            type Output = BigInt;
+
+            fn $method(self, other: &BigInt) -> BigInt {
+                (&self).$method(other)
+            }
+        }
+
+        impl<'a> $trait<BigInt> for &'a BigInt {
+            type Output = BigInt;
+
+            fn $method(self, other: BigInt) -> BigInt {
+                self.$method(&other)
+            }
+        }
+
+        impl $assign_trait<BigInt> for BigInt {
+            fn $assign_method(&mut self, other: BigInt) {
+                *self = (&*self).$method(&other);
+            }
+        }
+
+        impl<'a> $assign_trait<&'a BigInt> for BigInt {
+            fn $assign_method(&mut self, other: &BigInt) {
+                *self = (&*self).$method(other);
+            }
+        }
+    )*};
+}
+
+forward_binop! {
+    Add, add, AddAssign, add_assign;
+    Sub, sub, SubAssign, sub_assign;
+    Mul, mul, MulAssign, mul_assign;
+    Div, div, DivAssign, div_assign;
+    Rem, rem, RemAssign, rem_assign;
+}
+
+// Interoperability with the primitive integer types: conversions both ways, mixed arithmetic and comparison.
+macro_rules! impl_primitive {
+    ($via:ident, $from:ident, $to:ident; $($t:ty)*) => {$(
+        impl From<$t> for BigInt {
+            fn from(value: $t) -> BigInt {
+                BigInt::$from(value as $via)
+            }
+        }
+
+        impl<'a> TryFrom<&'a BigInt> for $t {
+            type Error = TryFromBigIntError;
+
+            fn try_from(value: &BigInt) -> Result<$t, TryFromBigIntError> {
+                value.$to()
+                    .and_then(|wide| <$t>::try_from(wide).ok())
+                    .ok_or(TryFromBigIntError(()))
+            }
+        }
+
+        impl TryFrom<BigInt> for $t {
+            type Error = TryFromBigIntError;
+
+            fn try_from(value: BigInt) -> Result<$t, TryFromBigIntError> {
+                <$t>::try_from(&value)
+            }
+        }
+
+        impl PartialEq<$t> for BigInt {
+            fn eq(&self, other: &$t) -> bool {
+                self.$to() == Some(*other as $via)
+            }
+        }
+
+        // A value that does not fit the widest primitive lies beyond every primitive on the side of its sign.
+        impl PartialOrd<$t> for BigInt {
+            fn partial_cmp(&self, other: &$t) -> Option<Ordering> {
+                match self.$to() {
+                    Some(value) => value.partial_cmp(&(*other as $via)),
+                    None if self.negative => Some(Ordering::Less),
+                    None => Some(Ordering::Greater),
+                }
+            }
+        }
+
+        impl Add<$t> for BigInt {
+            type Output = BigInt;
+
+            fn add(self, other: $t) -> BigInt {
+                self + BigInt::from(other)
+            }
+        }
+
+        impl Sub<$t> for BigInt {
+            type Output = BigInt;
+
+            fn sub(self, other: $t) -> BigInt {
+                self - BigInt::from(other)
+            }
+        }
+
+        impl Mul<$t> for BigInt {
+            type Output = BigInt;
+
+            fn mul(self, other: $t) -> BigInt {
+                self * BigInt::from(other)
+            }
+        }
+
+        impl Div<$t> for BigInt {
+            type Output = BigInt;
+
+            fn div(self, other: $t) -> BigInt {
+                self / BigInt::from(other)
+            }
+        }
+
+        impl Rem<$t> for BigInt {
+            type Output = BigInt;
+
+            fn rem(self, other: $t) -> BigInt {
+                self % BigInt::from(other)
+            }
+        }
+    )*};
+}
+
+impl_primitive!(i128, from_i128, to_i128; i8 i16 i32 i64 i128 isize);
+impl_primitive!(u128, from_u128, to_u128; u8 u16 u32 u64 u128 usize);
+
+impl FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    fn from_str(src: &str) -> Result<BigInt, ParseBigIntError> {
+        BigInt::from_str_radix(src, 10)
+    }
+}
+
+// `pad_integral` takes care of width, fill, `+` and the `#` prefix flags for us.
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad_integral(!self.negative, "", &self.magnitude_to_decimal())
+    }
+}
+
+impl fmt::LowerHex for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad_integral(!self.negative, "0x", &self.magnitude_to_power_of_two_radix(4))
+    }
+}
+
+impl fmt::UpperHex for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad_integral(!self.negative, "0x", &self.magnitude_to_power_of_two_radix(4).to_uppercase())
+    }
+}
+
+impl fmt::Binary for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad_integral(!self.negative, "0b", &self.magnitude_to_power_of_two_radix(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic limbs with the top one non-zero, so the number really has `len` limbs.
+    fn limbs(len: usize, seed: u32) -> Vec<u32> {
+        let mut state = seed;
+        let mut out: Vec<u32> = (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                state
+            })
+            .collect();
+        if let Some(top) = out.last_mut() {
+            *top |= 1;
+        }
+        out
+    }
+
+    #[test]
+    fn div_rem_matches_primitive_signs() {
+        for &(a, b) in &[(7i128, 2i128), (-7, 2), (7, -2), (-7, -2), (6, 3), (-6, 3), (0, -5), (1, 1 << 100)] {
+            let (quotient, remainder) = BigInt::from(a).div_rem(&BigInt::from(b));
+            assert_eq!(quotient, BigInt::from(a / b), "{} / {}", a, b);
+            assert_eq!(remainder, BigInt::from(a % b), "{} % {}", a, b);
+        }
+        assert_eq!(BigInt::from(5).checked_div_rem(&BigInt::zero()), None);
+    }
+
+    #[test]
+    fn div_mod_floor_takes_divisor_sign() {
+        for &(a, b, q, r) in &[(7i128, 2i128, 3i128, 1i128), (-7, 2, -4, 1), (7, -2, -4, -1), (-7, -2, 3, -1), (-6, 3, -2, 0)] {
+            assert_eq!(BigInt::from(a).div_mod_floor(&BigInt::from(b)), (BigInt::from(q), BigInt::from(r)));
+        }
+    }
+
+    #[test]
+    fn multi_limb_division_remainder() {
+        for &(u_len, v_len) in &[(8, 3), (40, 17), (5, 5), (64, 1), (3, 7)] {
+            let u = BigInt::from_parts(true, limbs(u_len, u_len as u32));
+            let v = BigInt::from_parts(false, limbs(v_len, 99 + v_len as u32));
+            let (quotient, remainder) = u.div_rem(&v);
+            assert_eq!(&quotient * &v + &remainder, u);
+            assert!(cmp_magnitude(&remainder.magnitude, &v.magnitude) == Ordering::Less);
+            assert!(remainder.is_zero() || remainder.is_negative());
+        }
+    }
+
+    #[test]
+    fn karatsuba_matches_schoolbook_around_threshold() {
+        for &len in &[KARATSUBA_THRESHOLD - 1, KARATSUBA_THRESHOLD, KARATSUBA_THRESHOLD + 1, 2 * KARATSUBA_THRESHOLD + 3] {
+            for &other in &[len, KARATSUBA_THRESHOLD, 3 * len] {
+                let (a, b) = (limbs(len, 1), limbs(other, 2));
+                let mut expected = schoolbook_mul(&a, &b);
+                trim(&mut expected);
+                assert_eq!(mul_magnitude(&a, &b), expected, "{} x {} limbs", len, other);
+            }
+        }
+    }
+
+    #[test]
+    fn product_signs() {
+        let a = BigInt::from_parts(false, limbs(KARATSUBA_THRESHOLD, 3));
+        assert!((-&a * &a).is_negative());
+        assert!(!(-&a * -&a).is_negative());
+        assert!((-&a * BigInt::zero()).is_zero() && !(-&a * BigInt::zero()).is_negative());
+    }
+
+    #[test]
+    fn radix_round_trips_and_rejects_the_same_radixes() {
+        let n = BigInt::from(-0x1234_5678_9abc_def0_i128);
+        for &radix in &[2, 10, 16] {
+            assert_eq!(BigInt::from_str_radix(&n.to_str_radix(radix).unwrap(), radix), Ok(n.clone()));
+        }
+        assert_eq!(n.to_str_radix(8), None);
+        assert_eq!(BigInt::from_str_radix("17", 8), Err(ParseBigIntError::UnsupportedRadix(8)));
+    }
+}
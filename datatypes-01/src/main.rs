@@ -1,3 +1,9 @@
+use std::convert::TryFrom;
+
+mod bigint;
+
+use bigint::BigInt;
+
 fn main() {
     /*
         data types
@@ -37,4 +43,36 @@ fn main() {
     // ... that is allowed to grow or shrink in size.
     let arr = [1, 2, 3];
     println!("arr[0]: {}", arr[0]);
+
+    /*
+        Every fixed-width integer eventually overflows: `i128::MAX + 1` panics in debug builds and wraps around in release builds.
+        When a value can grow without bound, such as a factorial or a binomial coefficient, we need an arbitrary-precision integer instead.
+        `BigInt` stores as many 32-bit limbs as the value needs and converts to and from the primitive types.
+    */
+    println!("i128::MAX.checked_add(1): {:?}", i128::MAX.checked_add(1));
+
+    let mut factorial = BigInt::one();
+    for n in 1..=50u32 {
+        factorial *= BigInt::from(n);
+    }
+    println!("50! = {} ({} bits)", factorial, factorial.bits());
+
+    // C(100, 50) = 100! / (50! * 50!), computed one factor at a time so every intermediate division is exact
+    let mut binomial = BigInt::one();
+    for k in 1..=50u32 {
+        binomial = binomial * (50 + k) / k;
+    }
+    println!("C(100, 50) = {}", binomial);
+
+    let big: BigInt = "-123456789012345678901234567890".parse().unwrap();
+    let (quotient, remainder) = big.div_rem(&BigInt::from(11));
+    let (floor_quotient, modulus) = big.div_mod_floor(&BigInt::from(11));
+    println!("{} / 11 = {} rem {} (floored: {} mod {})", big, quotient, remainder, floor_quotient, modulus);
+    println!("sign: {}, negative: {}, |big| = {}", big.signum(), big.is_negative(), big.abs());
+
+    let mersenne = BigInt::from(2).pow(127) - 1;
+    println!("2^127 - 1 = {:#x} = {:#b}", mersenne, BigInt::from(0b1011));
+    println!("fits in i128: {:?}, in i64: {:?}", i128::try_from(&mersenne).is_ok(), i64::try_from(&mersenne).is_ok());
+    println!("round-trips through hex: {}", BigInt::from_str_radix(&mersenne.to_str_radix(16).unwrap(), 16).unwrap() == mersenne);
+    println!("2^127 - 1 - i128::MAX is zero: {}", (&mersenne - BigInt::from(i128::MAX)).is_zero());
 }
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use us_state::UsState;
+use Coin;
+
+/*
+    A collector's album has one slot per state quarter. Because `Quarter` carries its state, a `match` can pull the state
+    straight out of the coin; pennies, nickels and dimes have no slot and are turned away.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Album {
+    // How many of each state's quarter the collector has; only states with at least one are present.
+    owned: BTreeMap<UsState, u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Added {
+    New(UsState),
+    // Already in the album; kept as a spare to trade.
+    Duplicate(UsState),
+    NotAStateQuarter(Coin),
+}
+
+impl Album {
+    pub fn new() -> Album {
+        Album::default()
+    }
+
+    pub fn add(&mut self, coin: Coin) -> Added {
+        match coin {
+            Coin::Quarter(state) => {
+                let count = self.owned.entry(state).or_insert(0);
+                *count += 1;
+                if *count == 1 {
+                    Added::New(state)
+                } else {
+                    Added::Duplicate(state)
+                }
+            }
+            other => Added::NotAStateQuarter(other),
+        }
+    }
+
+    pub fn has(&self, state: UsState) -> bool {
+        self.owned.contains_key(&state)
+    }
+
+    pub fn owned_count(&self) -> usize {
+        self.owned.len()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.owned.len() == UsState::ALL.len()
+    }
+
+    // The states still to find, in release order.
+    pub fn missing(&self) -> Vec<UsState> {
+        UsState::ALL.iter().cloned().filter(|state| !self.has(*state)).collect()
+    }
+
+    // Quarters owned more than once, with how many spares there are of each.
+    pub fn spares(&self) -> Vec<(UsState, u32)> {
+        self.owned.iter().filter(|&(_, &count)| count > 1).map(|(&state, &count)| (state, count - 1)).collect()
+    }
+}
+
+// One line per release year, with owned states marked `[x]` and missing ones `[ ]`.
+impl fmt::Display for Album {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} of {} state quarters", self.owned_count(), UsState::ALL.len())?;
+        for year_states in UsState::ALL.chunks(5) {
+            write!(f, "{}:", year_states[0].release_year())?;
+            for &state in year_states {
+                let mark = if self.has(state) { 'x' } else { ' ' };
+                write!(f, " [{}] {}", mark, state.abbreviation())?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DE: Coin = Coin::Quarter(UsState::Delaware);
+
+    #[test]
+    fn sorts_a_pocket_of_change() {
+        let mut album = Album::new();
+        let pocket = [
+            DE, Coin::Dime, Coin::Quarter(UsState::Pennsylvania), DE, Coin::Quarter(UsState::Hawaii), Coin::Penny,
+            Coin::Quarter(UsState::NewJersey), Coin::Quarter(UsState::Georgia), DE, Coin::Quarter(UsState::Texas),
+        ];
+        let added: Vec<Added> = pocket.iter().map(|&coin| album.add(coin)).collect();
+        assert_eq!(&added[..4], &[
+            Added::New(UsState::Delaware),
+            Added::NotAStateQuarter(Coin::Dime),
+            Added::New(UsState::Pennsylvania),
+            Added::Duplicate(UsState::Delaware),
+        ]);
+        assert_eq!(added[5], Added::NotAStateQuarter(Coin::Penny));
+
+        assert_eq!(album.owned_count(), 6);
+        assert_eq!(album.spares(), vec![(UsState::Delaware, 2)]);
+        assert!(!album.is_complete());
+        let missing = album.missing();
+        assert_eq!(missing.len(), 44);
+        assert_eq!(&missing[..2], &[UsState::Connecticut, UsState::Massachusetts]);
+        assert!(!missing.contains(&UsState::Texas) && missing.contains(&UsState::Alaska));
+    }
+
+    #[test]
+    fn a_full_album_is_complete() {
+        let mut album = Album::new();
+        assert_eq!(album.missing().len(), 50);
+        for &state in UsState::ALL.iter() {
+            assert_eq!(album.add(Coin::Quarter(state)), Added::New(state));
+        }
+        assert!(album.is_complete());
+        assert!(album.missing().is_empty() && album.spares().is_empty());
+        assert_eq!(album.add(DE), Added::Duplicate(UsState::Delaware));
+        assert_eq!(album.spares(), vec![(UsState::Delaware, 1)]);
+    }
+
+    #[test]
+    fn display_marks_owned_states_by_year() {
+        let mut album = Album::new();
+        album.add(DE);
+        album.add(Coin::Quarter(UsState::Hawaii));
+        let text = album.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 11);
+        assert_eq!(lines[0], "2 of 50 state quarters");
+        assert_eq!(lines[1], "1999: [x] DE [ ] PA [ ] NJ [ ] GA [ ] CT");
+        assert_eq!(lines[10], "2008: [ ] OK [ ] NM [ ] AZ [ ] AK [x] HI");
+    }
+}
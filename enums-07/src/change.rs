@@ -0,0 +1,489 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+use Coin;
+
+/*
+    Making change means paying an amount with as few coins as possible. The obvious way, "greedy", takes the largest coin
+    that still fits again and again. For US coins that is always optimal, but not for every set of coins: with coins of
+    1, 3 and 4, greedy pays 6 as 4 + 1 + 1 while 3 + 3 needs one coin fewer. Coin systems where greedy is always
+    optimal are called canonical.
+
+    `CoinSystem` checks once, when it is built, whether its coins are canonical, and then makes change greedily if they are
+    and with dynamic programming if they are not. The coins can be anything with a value, from `Coin` itself to plain numbers.
+
+    Different coins may have the same value, like quarters from different states. A coin system only needs one of them,
+    and an inventory pays with whichever ones it has.
+*/
+pub trait Valued: Copy + Ord {
+    // The value in the currency's smallest unit, e.g. cents.
+    fn value(&self) -> u32;
+}
+
+impl Valued for Coin {
+    fn value(&self) -> u32 {
+        self.cents()
+    }
+}
+
+impl Valued for u32 {
+    fn value(&self) -> u32 {
+        *self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChangeError {
+    NoCoins,
+    ZeroValue,
+    DuplicateValue(u32),
+    // No combination of the system's coins adds up to the amount, however many of each there are.
+    Impossible { amount: u32 },
+    // The amount could be paid with enough coins, but not with the ones in the inventory.
+    NotEnoughCoins { amount: u32 },
+    // Past `MAX_TABLE_AMOUNT` where dynamic programming is needed, or past `u32::MAX` for amounts kept in a `u64`.
+    TooLarge { amount: u64 },
+}
+
+impl fmt::Display for ChangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ChangeError::NoCoins => write!(f, "a coin system needs at least one coin"),
+            ChangeError::ZeroValue => write!(f, "coins must be worth more than nothing"),
+            ChangeError::DuplicateValue(value) => write!(f, "two coins are both worth {}", value),
+            ChangeError::Impossible { amount } => write!(f, "{} can't be paid with these coins", amount),
+            ChangeError::NotEnoughCoins { amount } => write!(f, "not enough coins left to pay {}", amount),
+            ChangeError::TooLarge { amount } => write!(f, "{} is too large to make change for", amount),
+        }
+    }
+}
+
+impl Error for ChangeError {}
+
+// How many of each coin to hand out, largest coin first. Coins not needed are left out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change<T> {
+    pub coins: Vec<(T, u32)>,
+}
+
+impl<T: Valued> Change<T> {
+    // In a `u64`, since a few billion coins of any value already add up to more than a `u32` holds.
+    pub fn total(&self) -> u64 {
+        self.coins.iter().map(|&(coin, count)| u64::from(coin.value()) * u64::from(count)).sum()
+    }
+
+    pub fn coin_count(&self) -> u32 {
+        self.coins.iter().map(|&(_, count)| count).sum()
+    }
+
+    // Every coin on its own, e.g. `[Quarter, Quarter, Dime]`.
+    pub fn to_vec(&self) -> Vec<T> {
+        self.coins.iter().flat_map(|&(coin, count)| (0..count).map(move |_| coin)).collect()
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Change<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.coins.is_empty() {
+            return f.write_str("nothing");
+        }
+        for (i, &(ref coin, count)) in self.coins.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" + ")?;
+            }
+            write!(f, "{} x {}", count, coin)?;
+        }
+        Ok(())
+    }
+}
+
+// The coins on hand, e.g. in a cash drawer. Kept in order, so which coins are handed out is always the same.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Inventory<T: Valued> {
+    counts: BTreeMap<T, u32>,
+}
+
+impl<T: Valued> Default for Inventory<T> {
+    fn default() -> Inventory<T> {
+        Inventory { counts: BTreeMap::new() }
+    }
+}
+
+impl<T: Valued> Inventory<T> {
+    pub fn new() -> Inventory<T> {
+        Inventory::default()
+    }
+
+    pub fn add(&mut self, coin: T, count: u32) {
+        *self.counts.entry(coin).or_insert(0) += count;
+    }
+
+    pub fn count(&self, coin: T) -> u32 {
+        self.counts.get(&coin).cloned().unwrap_or(0)
+    }
+
+    // How many coins of any kind worth exactly `value` there are.
+    pub fn count_worth(&self, value: u32) -> u32 {
+        self.counts.iter().filter(|&(coin, _)| coin.value() == value).map(|(_, &count)| count).sum()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().map(|(coin, &count)| u64::from(coin.value()) * u64::from(count)).sum()
+    }
+
+    /*
+        Takes out coins worth what each coin of `change` is worth, as many as it says, and returns the coins actually taken.
+        They can differ from the ones asked for when several kinds of coin have the same value.
+        If there aren't enough, nothing is taken and the result is `None`.
+    */
+    pub fn take(&mut self, change: &Change<T>) -> Option<Change<T>> {
+        if change.coins.iter().any(|&(coin, count)| self.count_worth(coin.value()) < count) {
+            return None;
+        }
+
+        let mut taken = Vec::new();
+        for &(wanted, count) in &change.coins {
+            let mut left = count;
+            for (&coin, available) in self.counts.iter_mut().filter(|&(coin, _)| coin.value() == wanted.value()) {
+                let used = left.min(*available);
+                if used > 0 {
+                    *available -= used;
+                    left -= used;
+                    taken.push((coin, used));
+                }
+            }
+        }
+        self.counts.retain(|_, &mut count| count > 0);
+        Some(Change { coins: taken })
+    }
+}
+
+/*
+    Dynamic programming keeps a table entry for every amount from 0 up to the one being paid, so it is only used up to
+    this amount, about 8 MB of table. Canonical systems pay larger amounts greedily, which needs no table at all.
+*/
+pub const MAX_TABLE_AMOUNT: u32 = 1_000_000;
+
+#[derive(Clone, Debug)]
+pub struct CoinSystem<T> {
+    // Largest first.
+    coins: Vec<T>,
+    canonical: bool,
+}
+
+impl<T: Valued> CoinSystem<T> {
+    pub fn new(coins: &[T]) -> Result<CoinSystem<T>, ChangeError> {
+        if coins.is_empty() {
+            return Err(ChangeError::NoCoins);
+        }
+        let mut coins = coins.to_vec();
+        coins.sort_by_key(|coin| Reverse(coin.value()));
+        if coins.last().unwrap().value() == 0 {
+            return Err(ChangeError::ZeroValue);
+        }
+        if let Some(pair) = coins.windows(2).find(|pair| pair[0].value() == pair[1].value()) {
+            return Err(ChangeError::DuplicateValue(pair[0].value()));
+        }
+
+        let mut system = CoinSystem { coins, canonical: false };
+        system.canonical = system.check_canonical();
+        Ok(system)
+    }
+
+    pub fn coins(&self) -> &[T] {
+        &self.coins
+    }
+
+    pub fn is_canonical(&self) -> bool {
+        self.canonical
+    }
+
+    /*
+        Kozen and Zaks showed that if greedy is ever worse than optimal, it already is for some amount below the sum of
+        the two largest coins. So comparing the two on every amount up to there settles it for all amounts.
+        Systems whose two largest coins add up to more than `MAX_TABLE_AMOUNT` are too big to check and count as not canonical.
+    */
+    fn check_canonical(&self) -> bool {
+        let limit = match self.coins.len() {
+            1 => return true,
+            _ => u64::from(self.coins[0].value()) + u64::from(self.coins[1].value()),
+        };
+        if limit > u64::from(MAX_TABLE_AMOUNT) {
+            return false;
+        }
+        let limit = limit as u32;
+        let fewest = self.fewest_coins(limit);
+        (1..limit as usize).all(|amount| {
+            let greedy = self.greedy(amount as u32).map(|change| change.coin_count());
+            greedy == fewest[amount]
+        })
+    }
+
+    // Largest coin first, as many as fit. `None` if that gets stuck, which never happens in a system with a coin worth 1.
+    pub fn greedy(&self, amount: u32) -> Option<Change<T>> {
+        let mut left = amount;
+        let mut coins = Vec::new();
+        for &coin in &self.coins {
+            let count = left / coin.value();
+            if count > 0 {
+                coins.push((coin, count));
+                left -= count * coin.value();
+            }
+        }
+        if left == 0 {
+            Some(Change { coins })
+        } else {
+            None
+        }
+    }
+
+    // `fewest[a]` is the smallest number of coins adding up to `a`, for every `a` up to `amount`, which callers keep
+    // within `MAX_TABLE_AMOUNT`.
+    fn fewest_coins(&self, amount: u32) -> Vec<Option<u32>> {
+        let mut fewest = vec![None; amount as usize + 1];
+        fewest[0] = Some(0);
+        for a in 1..=amount as usize {
+            fewest[a] = self.coins
+                .iter()
+                .filter(|coin| coin.value() as usize <= a)
+                .filter_map(|coin| fewest[a - coin.value() as usize])
+                .min()
+                .map(|count| count + 1);
+        }
+        fewest
+    }
+
+    // The fewest coins for `amount`, found by dynamic programming, which works for any coin system.
+    pub fn optimal(&self, amount: u32) -> Result<Change<T>, ChangeError> {
+        if amount > MAX_TABLE_AMOUNT {
+            return Err(ChangeError::TooLarge { amount: u64::from(amount) });
+        }
+        let fewest = self.fewest_coins(amount);
+        if fewest[amount as usize].is_none() {
+            return Err(ChangeError::Impossible { amount });
+        }
+
+        let mut counts: Vec<u32> = vec![0; self.coins.len()];
+        let mut left = amount as usize;
+        while left > 0 {
+            // Some coin leads from `left` to an amount needing one coin fewer; take it and continue from there.
+            let target = fewest[left].unwrap() - 1;
+            let i = (0..self.coins.len())
+                .find(|&i| {
+                    let value = self.coins[i].value() as usize;
+                    value <= left && fewest[left - value] == Some(target)
+                })
+                .unwrap();
+            counts[i] += 1;
+            left -= self.coins[i].value() as usize;
+        }
+        Ok(self.change_from_counts(&counts))
+    }
+
+    fn change_from_counts(&self, counts: &[u32]) -> Change<T> {
+        Change {
+            coins: self.coins.iter().cloned().zip(counts.iter().cloned()).filter(|&(_, count)| count > 0).collect(),
+        }
+    }
+
+    // Change for `amount` from an unlimited supply of every coin.
+    pub fn make_change(&self, amount: u32) -> Result<Change<T>, ChangeError> {
+        if self.canonical {
+            self.greedy(amount).ok_or(ChangeError::Impossible { amount })
+        } else {
+            self.optimal(amount)
+        }
+    }
+
+    /*
+        Change for `amount` using only the coins in `inventory`, which is left untouched; call `Inventory::take` to hand them out.
+        Coins in the inventory count as whichever coin of the system has the same value.
+
+        Greedy isn't safe here even for canonical systems: with one quarter and three dimes, greedy pays 30 as a quarter and
+        then has no nickel for the rest, although three dimes would do. So this always works it out with dynamic programming,
+        going through the coins one at a time and finding the best count of each that the inventory allows.
+
+        Trying every count for every amount would take about `amount² / value` steps per coin, far too many for pennies.
+        But the amounts reachable from `a` with one more coin are `a + value`, `a + 2 * value`, ..., so each remainder
+        modulo `value` forms its own chain, and along a chain the best count is the minimum over a sliding window
+        `available` long. A queue that drops any candidate no better than a newer one finds that minimum in constant time
+        per amount, making each coin linear in `amount`.
+    */
+    pub fn make_change_from(&self, amount: u32, inventory: &Inventory<T>) -> Result<Change<T>, ChangeError> {
+        if amount > MAX_TABLE_AMOUNT {
+            return Err(ChangeError::TooLarge { amount: u64::from(amount) });
+        }
+        let size = amount as usize + 1;
+        // `fewest[a]` after handling some coins: the fewest of those coins adding up to `a`.
+        let mut fewest: Vec<Option<u32>> = vec![None; size];
+        fewest[0] = Some(0);
+        // `used[i][a]`: how many of coin `i` the best way to reach `a` with the first `i + 1` coins uses.
+        let mut used: Vec<Vec<u32>> = Vec::with_capacity(self.coins.len());
+
+        for &coin in &self.coins {
+            let value = coin.value() as usize;
+            let available = inventory.count_worth(coin.value()) as usize;
+            let mut next = vec![None; size];
+            let mut count_used = vec![0; size];
+            for remainder in 0..value.min(size) {
+                /*
+                    Along the chain `remainder + j * value`, reaching step `j` from step `k` takes `j - k` coins, so the best
+                    start is the `k` within `available` steps back with the smallest `fewest - k`. The queue holds candidate
+                    `(k, fewest - k)` pairs with increasing keys; on ties the newer one wins, which uses fewer of this coin.
+                */
+                let mut window: VecDeque<(usize, i64)> = VecDeque::new();
+                for (j, a) in (remainder..size).step_by(value).enumerate() {
+                    if let Some(before) = fewest[a] {
+                        let key = i64::from(before) - j as i64;
+                        while window.back().is_some_and(|&(_, back)| back >= key) {
+                            window.pop_back();
+                        }
+                        window.push_back((j, key));
+                    }
+                    while window.front().is_some_and(|&(k, _)| k + available < j) {
+                        window.pop_front();
+                    }
+                    if let Some(&(k, key)) = window.front() {
+                        next[a] = Some((key + j as i64) as u32);
+                        count_used[a] = (j - k) as u32;
+                    }
+                }
+            }
+            fewest = next;
+            used.push(count_used);
+        }
+
+        if fewest[amount as usize].is_none() {
+            return Err(match self.optimal(amount) {
+                Ok(_) => ChangeError::NotEnoughCoins { amount },
+                Err(error) => error,
+            });
+        }
+
+        let mut counts = vec![0; self.coins.len()];
+        let mut left = amount as usize;
+        for i in (0..self.coins.len()).rev() {
+            counts[i] = used[i][left];
+            left -= counts[i] as usize * self.coins[i].value() as usize;
+        }
+        Ok(self.change_from_counts(&counts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system(values: &[u32]) -> CoinSystem<u32> {
+        CoinSystem::new(values).unwrap()
+    }
+
+    #[test]
+    fn detects_canonical_systems() {
+        assert!(system(&[1, 5, 10, 25]).is_canonical());
+        assert!(system(&[1, 2, 5, 10, 20, 50, 100, 200]).is_canonical());
+        assert!(system(&[10, 50, 100, 500]).is_canonical());
+        assert!(system(&[7]).is_canonical());
+    }
+
+    #[test]
+    fn detects_non_canonical_systems() {
+        for values in &[&[1, 3, 4][..], &[1, 7, 10], &[1, 10, 25], &[1, 5, 12, 20]] {
+            let coins = system(values);
+            assert!(!coins.is_canonical(), "{:?}", values);
+            // Non-canonical means some amount where greedy needs more coins than necessary.
+            let beaten = (1..100).any(|amount| {
+                coins.greedy(amount).map(|change| change.coin_count()) > coins.optimal(amount).ok().map(|change| change.coin_count())
+            });
+            assert!(beaten, "{:?}", values);
+        }
+        assert_eq!(system(&[1, 3, 4]).make_change(6).unwrap().coins, vec![(3, 2)]);
+    }
+
+    #[test]
+    fn canonical_check_agrees_with_brute_force() {
+        for a in 2..12 {
+            for b in (a + 1)..25 {
+                let coins = system(&[1, a, b]);
+                let greedy_always_optimal = (1..200).all(|amount| {
+                    coins.greedy(amount).map(|change| change.coin_count()) == coins.optimal(amount).ok().map(|change| change.coin_count())
+                });
+                assert_eq!(coins.is_canonical(), greedy_always_optimal, "1, {}, {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn large_amounts_are_refused_instead_of_allocating() {
+        let odd = system(&[1, 3, 4]);
+        assert_eq!(odd.make_change(u32::MAX), Err(ChangeError::TooLarge { amount: u64::from(u32::MAX) }));
+        assert!(odd.make_change(MAX_TABLE_AMOUNT).is_ok());
+        assert_eq!(system(&[1, 5, 10, 25]).make_change(u32::MAX).unwrap().coin_count(), 171_798_693);
+
+        let mut drawer = Inventory::new();
+        drawer.add(25u32, 4);
+        assert_eq!(system(&[1, 25]).make_change_from(u32::MAX, &drawer), Err(ChangeError::TooLarge { amount: u64::from(u32::MAX) }));
+        assert!(!system(&[1, u32::MAX / 2, u32::MAX]).is_canonical());
+    }
+
+    // The fewest coins for `amount` from `inventory`, by trying every combination.
+    fn brute_force(values: &[u32], counts: &[u32], amount: u32) -> Option<u32> {
+        match (values.split_first(), counts.split_first()) {
+            (Some((&value, values)), Some((&available, counts))) => (0..=available)
+                .take_while(|&count| count * value <= amount)
+                .filter_map(|count| brute_force(values, counts, amount - count * value).map(|rest| rest + count))
+                .min(),
+            _ => if amount == 0 { Some(0) } else { None },
+        }
+    }
+
+    #[test]
+    fn change_from_an_inventory_agrees_with_brute_force() {
+        let values = [25, 12, 10, 5, 1];
+        for &counts in &[[1, 0, 3, 0, 0], [2, 3, 1, 1, 4], [0, 5, 5, 0, 2], [4, 1, 2, 3, 0]] {
+            let coins = system(&values);
+            let mut inventory = Inventory::new();
+            for (&value, &count) in values.iter().zip(&counts) {
+                inventory.add(value, count);
+            }
+            for amount in 0..=140 {
+                let change = coins.make_change_from(amount, &inventory);
+                match brute_force(&values, &counts, amount) {
+                    Some(fewest) => {
+                        let change = change.unwrap();
+                        assert_eq!((change.total(), change.coin_count()), (u64::from(amount), fewest), "{} from {:?}", amount, counts);
+                        assert!(inventory.clone().take(&change).is_some());
+                    }
+                    None => assert!(change.is_err(), "{} from {:?}", amount, counts),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn change_from_many_small_coins_is_fast() {
+        let coins = system(&[1, 3, 4]);
+        let mut inventory = Inventory::new();
+        inventory.add(1u32, MAX_TABLE_AMOUNT);
+        inventory.add(3u32, 2);
+        let change = coins.make_change_from(MAX_TABLE_AMOUNT, &inventory).unwrap();
+        assert_eq!(change.coins, vec![(3, 2), (1, MAX_TABLE_AMOUNT - 6)]);
+
+        inventory.add(4u32, 250_000);
+        let change = coins.make_change_from(MAX_TABLE_AMOUNT - 1, &inventory).unwrap();
+        assert_eq!(change.coins, vec![(4, 249_999), (3, 1)]);
+        assert_eq!(coins.make_change_from(MAX_TABLE_AMOUNT, &Inventory::new()), Err(ChangeError::NotEnoughCoins { amount: MAX_TABLE_AMOUNT }));
+    }
+
+    #[test]
+    fn totals_do_not_overflow() {
+        let mut inventory = Inventory::new();
+        inventory.add(u32::MAX, 3);
+        inventory.add(1u32, u32::MAX);
+        assert_eq!(inventory.total(), 4 * u64::from(u32::MAX));
+        let change = Change { coins: vec![(100u32, u32::MAX), (1, 1)] };
+        assert_eq!(change.total(), 100 * u64::from(u32::MAX) + 1);
+    }
+}
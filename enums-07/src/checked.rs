@@ -0,0 +1,199 @@
+use std::error::Error;
+use std::fmt;
+
+/*
+    `plus_one` computes `value + 1`, which panics in a debug build when `value` is already the largest `i32` and
+    silently wraps around in a release build. The integer types' `checked_*` methods return `None` instead, and
+    the traits here build on them so that whole calculations can be written as a chain:
+
+        Some(price).times(count).plus(shipping).divided_by(people)
+
+    The first step that overflows or divides by zero turns the rest of the chain into `None`. Chaining on a
+    `Result` works the same way but keeps an `ArithmeticError` saying what went wrong.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Operation::Add => "addition",
+            Operation::Subtract => "subtraction",
+            Operation::Multiply => "multiplication",
+            Operation::Divide => "division",
+        };
+        f.pad(name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ArithmeticError {
+    // The result doesn't fit in the type, e.g. `i32::MAX + 1`, `0u8 - 1` or `i32::MIN / -1`.
+    Overflow(Operation),
+    DivideByZero,
+}
+
+impl fmt::Display for ArithmeticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ArithmeticError::Overflow(operation) => write!(f, "{} overflowed", operation),
+            ArithmeticError::DivideByZero => write!(f, "division by zero"),
+        }
+    }
+}
+
+impl Error for ArithmeticError {}
+
+// Implemented below for every built-in integer type.
+pub trait Integer: Copy + PartialEq + fmt::Debug + fmt::Display {
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn try_add(self, rhs: Self) -> Result<Self, ArithmeticError>;
+    fn try_sub(self, rhs: Self) -> Result<Self, ArithmeticError>;
+    fn try_mul(self, rhs: Self) -> Result<Self, ArithmeticError>;
+    // Rounds towards zero, like `/`.
+    fn try_div(self, rhs: Self) -> Result<Self, ArithmeticError>;
+}
+
+macro_rules! impl_integer {
+    ($($int:ty)*) => {
+        $(
+            impl Integer for $int {
+                const ZERO: $int = 0;
+                const ONE: $int = 1;
+
+                fn try_add(self, rhs: $int) -> Result<$int, ArithmeticError> {
+                    self.checked_add(rhs).ok_or(ArithmeticError::Overflow(Operation::Add))
+                }
+
+                fn try_sub(self, rhs: $int) -> Result<$int, ArithmeticError> {
+                    self.checked_sub(rhs).ok_or(ArithmeticError::Overflow(Operation::Subtract))
+                }
+
+                fn try_mul(self, rhs: $int) -> Result<$int, ArithmeticError> {
+                    self.checked_mul(rhs).ok_or(ArithmeticError::Overflow(Operation::Multiply))
+                }
+
+                fn try_div(self, rhs: $int) -> Result<$int, ArithmeticError> {
+                    if rhs == 0 {
+                        return Err(ArithmeticError::DivideByZero);
+                    }
+                    self.checked_div(rhs).ok_or(ArithmeticError::Overflow(Operation::Divide))
+                }
+            }
+        )*
+    };
+}
+
+impl_integer! { i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize }
+
+// Arithmetic that carries on from an earlier step that may have failed; once a step fails, every later one does too.
+pub trait Checked<T: Integer>: Sized {
+    fn plus(self, rhs: T) -> Self;
+    fn minus(self, rhs: T) -> Self;
+    fn times(self, rhs: T) -> Self;
+    fn divided_by(self, rhs: T) -> Self;
+}
+
+impl<T: Integer> Checked<T> for Option<T> {
+    fn plus(self, rhs: T) -> Option<T> {
+        self.and_then(|value| value.try_add(rhs).ok())
+    }
+
+    fn minus(self, rhs: T) -> Option<T> {
+        self.and_then(|value| value.try_sub(rhs).ok())
+    }
+
+    fn times(self, rhs: T) -> Option<T> {
+        self.and_then(|value| value.try_mul(rhs).ok())
+    }
+
+    fn divided_by(self, rhs: T) -> Option<T> {
+        self.and_then(|value| value.try_div(rhs).ok())
+    }
+}
+
+impl<T: Integer> Checked<T> for Result<T, ArithmeticError> {
+    fn plus(self, rhs: T) -> Result<T, ArithmeticError> {
+        self.and_then(|value| value.try_add(rhs))
+    }
+
+    fn minus(self, rhs: T) -> Result<T, ArithmeticError> {
+        self.and_then(|value| value.try_sub(rhs))
+    }
+
+    fn times(self, rhs: T) -> Result<T, ArithmeticError> {
+        self.and_then(|value| value.try_mul(rhs))
+    }
+
+    fn divided_by(self, rhs: T) -> Result<T, ArithmeticError> {
+        self.and_then(|value| value.try_div(rhs))
+    }
+}
+
+// `plus_one` for any integer type, giving `None` instead of overflowing.
+pub fn plus_one<T: Integer>(x: Option<T>) -> Option<T> {
+    x.plus(T::ONE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plus_one_stops_at_the_largest_value() {
+        assert_eq!(plus_one(Some(i32::MAX)), None);
+        assert_eq!(plus_one(Some(254u8)), Some(255));
+        assert_eq!(plus_one(plus_one(Some(u64::MAX - 1))), None);
+        assert_eq!(plus_one(None::<i32>), None);
+        assert_eq!(plus_one(Some(-1i64)), Some(0));
+    }
+
+    #[test]
+    fn division_fails_on_zero_and_on_overflow() {
+        assert_eq!(i32::MIN.try_div(-1), Err(ArithmeticError::Overflow(Operation::Divide)));
+        assert_eq!(i8::MIN.try_div(-1), Err(ArithmeticError::Overflow(Operation::Divide)));
+        assert_eq!(i32::MIN.try_div(1), Ok(i32::MIN));
+        assert_eq!(10usize.try_div(0), Err(ArithmeticError::DivideByZero));
+        assert_eq!(0i32.try_div(0), Err(ArithmeticError::DivideByZero));
+        // Rounds towards zero, like `/`.
+        assert_eq!((-7i16).try_div(2), Ok(-3));
+        assert_eq!(Ok(7i16).divided_by(2), Ok::<i16, ArithmeticError>(3));
+    }
+
+    #[test]
+    fn each_operation_reports_its_own_overflow() {
+        assert_eq!(u8::MAX.try_add(1), Err(ArithmeticError::Overflow(Operation::Add)));
+        assert_eq!(usize::ZERO.try_sub(1), Err(ArithmeticError::Overflow(Operation::Subtract)));
+        assert_eq!(i64::MIN.try_mul(-1), Err(ArithmeticError::Overflow(Operation::Multiply)));
+        assert_eq!(ArithmeticError::Overflow(Operation::Subtract).to_string(), "subtraction overflowed");
+        assert_eq!(ArithmeticError::DivideByZero.to_string(), "division by zero");
+    }
+
+    #[test]
+    fn an_option_chain_stays_none_after_a_failure() {
+        let (price, count, shipping, people): (u32, u32, u32, u32) = (1_999, 3, 500, 2);
+        assert_eq!(Some(price).times(count).plus(shipping).divided_by(people), Some(3_248));
+        assert_eq!(Some(price).times(count).divided_by(0).plus(shipping), None);
+        // Steps after the failure would succeed on their own, but can't bring the value back.
+        assert_eq!(Some(0u32).minus(1).plus(1), None);
+        assert_eq!(Some(u32::MAX).times(2).divided_by(2), None);
+        assert_eq!(None::<u32>.plus(1), None);
+    }
+
+    #[test]
+    fn a_result_chain_keeps_the_first_error() {
+        let total: Result<i64, ArithmeticError> = Ok(i64::MAX / 2);
+        assert_eq!(total.times(2).plus(1), Ok(i64::MAX));
+        assert_eq!(total.times(3).plus(1), Err(ArithmeticError::Overflow(Operation::Multiply)));
+        assert_eq!(total.times(2).plus(2).divided_by(0), Err(ArithmeticError::Overflow(Operation::Add)));
+        assert_eq!(Ok(5usize).divided_by(0).minus(6), Err(ArithmeticError::DivideByZero));
+        assert_eq!(Ok(5usize).minus(6).plus(100), Err(ArithmeticError::Overflow(Operation::Subtract)));
+    }
+}
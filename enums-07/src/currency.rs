@@ -0,0 +1,315 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use change::{Change, ChangeError, CoinSystem, Valued};
+use Coin;
+
+/*
+    `Coin` spells out the US coins as enum variants, which is the right tool when the set is fixed and known when
+    the code is written. Supporting every currency that way would mean an enum per currency and a `match` per enum.
+    Here each currency is data instead: a `Currency` value lists its coins as `Denomination`s and says how its
+    amounts are written, so adding a currency means adding a `static`, not new code.
+
+    Amounts are kept in the currency's smallest unit, cents for dollars and euros and whole won for won,
+    so they are always whole numbers and never suffer from floating-point rounding.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Denomination {
+    pub currency: &'static str,
+    // In the currency's smallest unit.
+    pub value: u32,
+    pub name: &'static str,
+}
+
+impl Valued for Denomination {
+    fn value(&self) -> u32 {
+        self.value
+    }
+}
+
+impl fmt::Display for Denomination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.name)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Currency {
+    pub code: &'static str,
+    pub symbol: &'static str,
+    // Digits after the decimal separator: 2 for cents, 0 for currencies without a smaller unit in use.
+    pub minor_digits: u32,
+    pub group_separator: char,
+    pub decimal_separator: char,
+    // `$1.00` and `₩1,000`, but `1,00 €`.
+    pub symbol_first: bool,
+    pub coins: &'static [Denomination],
+}
+
+const fn coin(currency: &'static str, value: u32, name: &'static str) -> Denomination {
+    Denomination { currency, value, name }
+}
+
+pub static USD: Currency = Currency {
+    code: "USD",
+    symbol: "$",
+    minor_digits: 2,
+    group_separator: ',',
+    decimal_separator: '.',
+    symbol_first: true,
+    coins: &[coin("USD", 1, "penny"), coin("USD", 5, "nickel"), coin("USD", 10, "dime"), coin("USD", 25, "quarter")],
+};
+
+pub static KRW: Currency = Currency {
+    code: "KRW",
+    symbol: "₩",
+    minor_digits: 0,
+    group_separator: ',',
+    decimal_separator: '.',
+    symbol_first: true,
+    coins: &[coin("KRW", 10, "10 won"), coin("KRW", 50, "50 won"), coin("KRW", 100, "100 won"), coin("KRW", 500, "500 won")],
+};
+
+pub static EUR: Currency = Currency {
+    code: "EUR",
+    symbol: "€",
+    minor_digits: 2,
+    group_separator: '.',
+    decimal_separator: ',',
+    symbol_first: false,
+    coins: &[
+        coin("EUR", 1, "1 cent"), coin("EUR", 2, "2 cent"), coin("EUR", 5, "5 cent"), coin("EUR", 10, "10 cent"),
+        coin("EUR", 20, "20 cent"), coin("EUR", 50, "50 cent"), coin("EUR", 100, "1 euro"), coin("EUR", 200, "2 euro"),
+    ],
+};
+
+pub static CURRENCIES: [&Currency; 3] = [&USD, &KRW, &EUR];
+
+impl Currency {
+    pub fn by_code(code: &str) -> Option<&'static Currency> {
+        CURRENCIES.iter().cloned().find(|currency| currency.code.eq_ignore_ascii_case(code))
+    }
+
+    pub fn coin_system(&self) -> CoinSystem<Denomination> {
+        // Every built-in currency has at least one coin, all different and worth something.
+        CoinSystem::new(self.coins).unwrap()
+    }
+
+    pub fn denomination(&self, value: u32) -> Option<Denomination> {
+        self.coins.iter().cloned().find(|coin| coin.value == value)
+    }
+
+    pub fn money(&'static self, amount: u64) -> Money {
+        Money { currency: self, amount }
+    }
+
+    // The number alone, e.g. `1,500.25` or `1.500,25`, without the currency symbol.
+    pub fn format_number(&self, amount: u64) -> String {
+        let scale = 10u64.pow(self.minor_digits);
+        let digits = (amount / scale).to_string();
+
+        let mut text = String::with_capacity(digits.len() * 4 / 3 + 4);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                text.push(self.group_separator);
+            }
+            text.push(digit);
+        }
+        if self.minor_digits > 0 {
+            text.push(self.decimal_separator);
+            text.push_str(&format!("{:01$}", amount % scale, self.minor_digits as usize));
+        }
+        text
+    }
+
+    /*
+        Reads an amount written the way this currency writes it, with or without its symbol or code:
+        `$1,500.25`, `1500.25`, `USD 0.25`, `1.500,25 €`, `₩1,500`. Group separators are optional, but where used
+        they have to split the number into groups of three. Fewer decimals than the currency has are fine (`$0.5`).
+    */
+    pub fn parse_amount(&self, text: &str) -> Result<u64, ParseMoneyError> {
+        let number = self.strip_currency(text.trim()).trim();
+        if number.is_empty() {
+            return Err(ParseMoneyError::Empty);
+        }
+
+        let (whole, fraction) = match number.find(self.decimal_separator) {
+            Some(_) if self.minor_digits == 0 => return Err(ParseMoneyError::TooManyDecimals { max: 0 }),
+            Some(at) => (&number[..at], Some(&number[at + self.decimal_separator.len_utf8()..])),
+            None => (number, None),
+        };
+
+        let groups: Vec<&str> = whole.split(self.group_separator).collect();
+        let grouped_wrong = groups.len() > 1
+            && (groups[0].is_empty() || groups[0].len() > 3 || groups[1..].iter().any(|group| group.len() != 3));
+        if grouped_wrong || whole.is_empty() {
+            return Err(ParseMoneyError::MisplacedSeparator);
+        }
+
+        let mut amount: u64 = 0;
+        let mut push_digit = |ch: char| -> Result<(), ParseMoneyError> {
+            let digit = ch.to_digit(10).ok_or(ParseMoneyError::InvalidCharacter(ch))?;
+            amount = amount
+                .checked_mul(10)
+                .and_then(|amount| amount.checked_add(u64::from(digit)))
+                .ok_or(ParseMoneyError::Overflow)?;
+            Ok(())
+        };
+        for ch in groups.concat().chars() {
+            push_digit(ch)?;
+        }
+
+        let fraction = fraction.unwrap_or("");
+        if fraction.chars().count() > self.minor_digits as usize {
+            return Err(ParseMoneyError::TooManyDecimals { max: self.minor_digits });
+        }
+        if fraction.is_empty() && number.contains(self.decimal_separator) {
+            return Err(ParseMoneyError::MisplacedSeparator);
+        }
+        // Pad `0.5` out to `0.50`, so the digits are always in the smallest unit.
+        for ch in fraction.chars().chain((fraction.len()..self.minor_digits as usize).map(|_| '0')) {
+            push_digit(ch)?;
+        }
+        Ok(amount)
+    }
+
+    fn strip_currency<'a>(&self, text: &'a str) -> &'a str {
+        for marker in &[self.symbol, self.code] {
+            if let Some(rest) = strip_prefix_ignore_case(text, marker) {
+                return rest;
+            }
+            if let Some(rest) = strip_suffix_ignore_case(text, marker) {
+                return rest;
+            }
+        }
+        text
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    if head.eq_ignore_ascii_case(prefix) {
+        Some(&text[prefix.len()..])
+    } else {
+        None
+    }
+}
+
+fn strip_suffix_ignore_case<'a>(text: &'a str, suffix: &str) -> Option<&'a str> {
+    let start = text.len().checked_sub(suffix.len())?;
+    let tail = text.get(start..)?;
+    if tail.eq_ignore_ascii_case(suffix) {
+        Some(&text[..start])
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseMoneyError {
+    Empty,
+    UnknownCurrency(String),
+    InvalidCharacter(char),
+    MisplacedSeparator,
+    TooManyDecimals { max: u32 },
+    Overflow,
+}
+
+impl fmt::Display for ParseMoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseMoneyError::Empty => write!(f, "no amount given"),
+            ParseMoneyError::UnknownCurrency(ref text) => write!(f, "no known currency in {:?}", text),
+            ParseMoneyError::InvalidCharacter(ch) => write!(f, "{:?} is not part of an amount", ch),
+            ParseMoneyError::MisplacedSeparator => write!(f, "separators are in the wrong place"),
+            ParseMoneyError::TooManyDecimals { max: 0 } => write!(f, "this currency has no decimals"),
+            ParseMoneyError::TooManyDecimals { max } => write!(f, "at most {} decimals are allowed", max),
+            ParseMoneyError::Overflow => write!(f, "amount is too large"),
+        }
+    }
+}
+
+impl Error for ParseMoneyError {}
+
+// An amount of a particular currency, in its smallest unit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Money {
+    pub currency: &'static Currency,
+    pub amount: u64,
+}
+
+impl Money {
+    // The fewest coins adding up to this amount. Coin systems count in `u32`, so larger amounts are `TooLarge`.
+    pub fn to_coins(self) -> Result<Change<Denomination>, ChangeError> {
+        if self.amount > u64::from(u32::MAX) {
+            return Err(ChangeError::TooLarge { amount: self.amount });
+        }
+        self.currency.coin_system().make_change(self.amount as u32)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let number = self.currency.format_number(self.amount);
+        let text = if self.currency.symbol_first {
+            format!("{}{}", self.currency.symbol, number)
+        } else {
+            format!("{} {}", number, self.currency.symbol)
+        };
+        f.pad(&text)
+    }
+}
+
+// Works out the currency from its symbol or code, which therefore has to be there: `$0.25`, `₩1,500`, `EUR 2,50`.
+impl FromStr for Money {
+    type Err = ParseMoneyError;
+
+    fn from_str(text: &str) -> Result<Money, ParseMoneyError> {
+        let trimmed = text.trim();
+        let currency = CURRENCIES
+            .iter()
+            .cloned()
+            .find(|currency| currency.strip_currency(trimmed).len() != trimmed.len())
+            .ok_or_else(|| ParseMoneyError::UnknownCurrency(text.to_string()))?;
+        Ok(Money { currency, amount: currency.parse_amount(trimmed)? })
+    }
+}
+
+// The `Coin` enum is still the simplest way to talk about US coins; these connect it to the data-driven `USD`.
+impl Coin {
+    pub fn denomination(&self) -> Denomination {
+        USD.denomination(self.cents()).unwrap()
+    }
+
+    // A USD quarter comes back as `Coin::ALL`'s stand-in quarter, since a denomination doesn't say which state it shows.
+    pub fn from_denomination(denomination: &Denomination) -> Option<Coin> {
+        if denomination.currency != USD.code {
+            return None;
+        }
+        Coin::ALL.iter().cloned().find(|coin| coin.cents() == denomination.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_past_u32_report_their_real_size() {
+        let amount = u64::from(u32::MAX) + 1;
+        assert_eq!(USD.money(amount).to_coins(), Err(ChangeError::TooLarge { amount }));
+        assert_eq!(USD.money(u64::MAX).to_coins(), Err(ChangeError::TooLarge { amount: u64::MAX }));
+        assert_eq!(USD.money(u64::from(u32::MAX)).to_coins().map(|change| change.total()), Ok(u64::from(u32::MAX)));
+    }
+
+    #[test]
+    fn formats_and_parses_each_currency() {
+        for &(text, formatted, amount) in &[("$1,500.25", "$1,500.25", 150_025), ("₩1500", "₩1,500", 1_500), ("EUR 1.234,5", "1.234,50 €", 123_450)] {
+            let money: Money = text.parse().unwrap();
+            assert_eq!((money.to_string().as_str(), money.amount), (formatted, amount));
+        }
+        assert_eq!(USD.parse_amount("$1,50"), Err(ParseMoneyError::MisplacedSeparator));
+        assert_eq!(KRW.parse_amount("₩1.5"), Err(ParseMoneyError::TooManyDecimals { max: 0 }));
+    }
+}
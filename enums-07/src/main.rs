@@ -1,11 +1,60 @@
+mod album;
+mod change;
+mod checked;
+mod currency;
+mod recognition;
+mod us_state;
+mod vending;
+
+use std::fmt;
+
+use album::{Added, Album};
+use change::{ChangeError, CoinSystem, Inventory};
+use checked::{ArithmeticError, Checked, Integer};
+use currency::{Currency, Money, EUR, KRW, USD};
+use recognition::{NoisySensor, Reading, Recognition, Recognizer};
+use us_state::UsState;
+use vending::{Event, Setup, VendingMachine};
+
 // Enums allow you to define a type by enumerating its possible values.
+// A variant can hold data too: from 1999 to 2008 each quarter showed one of the fifty states.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum Coin {
     Penny,
     Nickel,
     Dime,
-    Quarter,
+    Quarter(UsState),
 }
 
+impl Coin {
+    // One of each kind of coin. Any quarter is worth the same, so the first state quarter stands in for all of them.
+    const ALL: [Coin; 4] = [Coin::Penny, Coin::Nickel, Coin::Dime, Coin::Quarter(UsState::Delaware)];
+
+    fn cents(&self) -> u32 {
+        match *self {
+            Coin::Penny => 1,
+            Coin::Nickel => 5,
+            Coin::Dime => 10,
+            Coin::Quarter(_) => 25,
+        }
+    }
+}
+
+impl fmt::Display for Coin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Coin::Penny => "penny",
+            Coin::Nickel => "nickel",
+            Coin::Dime => "dime",
+            Coin::Quarter(_) => "quarter",
+        };
+        f.pad(name)
+    }
+}
+
+// Spelled out as a `match` to show how it works; `x.map(|value| value + 1)` does the same.
+// `value + 1` overflows on `i32::MAX`; `checked::plus_one` is the safe version, for every integer type.
+#[allow(clippy::manual_map)]
 fn plus_one(x: Option<i32>) -> Option<i32> {
     match x {
         None => None,
@@ -18,7 +67,7 @@ fn main() {
 
     // Rust has an extremely powerful control flow operator called match that allows you to compare a value against a series of patterns
     // and then execute code based on which pattern matches. Patterns can be made up of literal values, variable names, wildcards, and many other things.
-    let value: i32 = match coin {
+    let value: i32 = match dime {
         Coin::Penny => {
             println!("one");
             1
@@ -31,8 +80,8 @@ fn main() {
             println!("ten");
             10
         },
-        Coin::Quarter => {
-            println!("twenty-five");
+        Coin::Quarter(state) => {
+            println!("State quarter from {:?}!", state);
             25
         }
     };
@@ -71,6 +120,23 @@ fn main() {
     let none: Option<i32> = None;
 
     let six = plus_one(five);
+    println!("{:?} {:?} {:?}", five, six, plus_one(none));
+
+    // `plus_one(Some(i32::MAX))` would overflow; the checked version gives `None` instead, for any integer type.
+    println!("{:?} {:?}", checked::plus_one(Some(i32::MAX)), checked::plus_one(Some(254u8)));
+    let (price, count, shipping, people): (u32, u32, u32, u32) = (1_999, 3, 500, 2);
+    println!("{:?} {:?} {:?}",
+             Some(price).times(count).plus(shipping).divided_by(people),
+             Some(price).times(count).divided_by(0),
+             Some(0u32).minus(1).plus(1));
+    let total: Result<i64, ArithmeticError> = Ok(i64::MAX / 2);
+    println!("{:?} {:?} {:?}", total.times(2).plus(1), total.times(3).plus(1), i8::MIN.try_div(-1));
+    for result in &[usize::ZERO.try_sub(1), 10usize.try_div(0), Ok(5usize).minus(6).plus(100)] {
+        match *result {
+            Ok(value) => println!("{}", value),
+            Err(ref error) => println!("error: {}", error),
+        }
+    }
 
     /*
         Rust also has a pattern we can use when we don’t want to list all possible values. (`_`)
@@ -83,7 +149,147 @@ fn main() {
             _ => (),
         }
     */
-    if let Some(1) = number {
-        println!("three");
+    if let 1 = number {
+        println!("one");
+    }
+
+    /*
+        A `match` gives each coin its value; making change goes the other way, from an amount to coins.
+        Taking the largest coin that fits works for US coins, but not for every set of coins.
+    */
+    println!("a dime is worth {} cents", value);
+    let usd = CoinSystem::new(&Coin::ALL).unwrap();
+    let change = usd.make_change(68).unwrap();
+    println!("68 cents: {} ({} coins, canonical: {})", change, change.coin_count(), usd.is_canonical());
+    println!("{:?}", change.to_vec());
+
+    let odd = CoinSystem::new(&[1, 3, 4]).unwrap();
+    println!("1/3/4 canonical: {}, greedy for 6: {}, optimal: {}",
+             odd.is_canonical(), odd.greedy(6).unwrap(), odd.make_change(6).unwrap());
+    // The 1/2/5 system of euro cents is canonical too; 1/7/10 is not (14 = 7 + 7, not 10 + 4 x 1).
+    println!("{} {}", CoinSystem::new(&[50, 20, 10, 5, 2, 1]).unwrap().is_canonical(), CoinSystem::new(&[1, 7, 10]).unwrap().is_canonical());
+    println!("{:?} {:?} {:?}", CoinSystem::<u32>::new(&[]).err(), CoinSystem::new(&[5, 0]).err(), CoinSystem::new(&[5, 5]).err());
+    println!("{:?} {:?}", CoinSystem::new(&[5, 10]).unwrap().make_change(7), CoinSystem::new(&[5, 10, 1]).unwrap().coins());
+
+    let mut drawer = Inventory::new();
+    drawer.add(Coin::Quarter(UsState::Ohio), 1);
+    drawer.add(Coin::Dime, 3);
+    drawer.add(Coin::Penny, 2);
+    println!("drawer holds {} cents, greedy would pay 30 as {}", drawer.total(), usd.greedy(30).unwrap());
+    let change = usd.make_change_from(30, &drawer).unwrap();
+    println!("30 cents from the drawer: {}", change);
+    assert_eq!(drawer.take(&change), Some(change.clone()));
+    println!("dimes left: {}, total {}: {:?}", drawer.count(Coin::Dime), change.total(), usd.make_change_from(30, &drawer));
+    match usd.make_change_from(30, &drawer) {
+        Err(ref error @ ChangeError::NotEnoughCoins { .. }) => println!("{}", error),
+        other => println!("{:?}", other),
+    }
+
+    /*
+        Enums also model things that change over time. A vending machine is always in exactly one of a few states,
+        and each state carries only the data that makes sense in it. The tests in `vending.rs` run it through scripted events.
+    */
+    use Event::*;
+    let setup = Setup::new()
+        .slot("A1", "cola", 75, 5)
+        .slot("A2", "water", 50, 1)
+        .slot("B1", "gum", 15, 0)
+        .coins(Coin::Dime, 5)
+        .coins(Coin::Nickel, 5);
+    let select = |code: &str| Select(code.to_string());
+    let (de, pa, nj, ga) = (Coin::Quarter(UsState::Delaware), Coin::Quarter(UsState::Pennsylvania),
+                            Coin::Quarter(UsState::NewJersey), Coin::Quarter(UsState::Georgia));
+
+    let mut machine = VendingMachine::new(setup.clone());
+    let restock = Restock { slot: "A2".to_string(), count: 2 };
+    for event in [InsertCoin(de), InsertCoin(ga), select("A2"), Cancel, Dispensed, ChangeTaken, restock] {
+        let outcome = machine.handle(event);
+        println!("{:<32} {}", format!("{:?}", outcome), machine.state());
+    }
+    println!("{} event(s) logged, {:?} water left, drawer {} cents", machine.log().len(), machine.stock("A2"), machine.drawer().total());
+    if let Err(error) = machine.handle(select("A1")) {
+        println!("{}", error);
     }
-}
\ No newline at end of file
+    println!("{:?}", machine.setup().slots["A1"].product);
+    println!("replaying the log ends {}", VendingMachine::replay(setup.clone(), machine.log()).state());
+
+    /*
+        Other countries' coins don't fit the `Coin` enum, so currencies are described as data instead.
+        The same change-making code works for all of them, and `Coin` still converts to and from US denominations.
+    */
+    for &(currency, amount) in &[(&USD, 25), (&USD, 150_025), (&KRW, 1_500), (&KRW, 2_860), (&EUR, 388), (&EUR, 123_456_789)] {
+        let money = currency.money(amount);
+        println!("{:>18} = {}", money, money.to_coins().map(|change| change.to_string()).unwrap_or_default());
+    }
+    println!("{} coins: {:?}", KRW.code, KRW.coins.iter().map(|coin| coin.name).collect::<Vec<_>>());
+    println!("KRW is canonical: {}, 730 won: {}", KRW.coin_system().is_canonical(), KRW.money(730).to_coins().unwrap());
+    println!("{:?}", KRW.money(735).to_coins());
+
+    for text in &["$0.25", "$1,500.25", "USD 3", "$0.5", "₩1,500", "1500 krw", "1.234,56 €", "EUR 0,05", "₩1.5", "$1,50", "$0.255", "£3", "$", "$12x"] {
+        match text.parse::<Money>() {
+            Ok(money) => println!("{:>12} -> {} ({} {})", text, money, money.amount, money.currency.code),
+            Err(error) => println!("{:>12} -> {}", text, error),
+        }
+    }
+    println!("{:?} {:?}", USD.parse_amount("18446744073709551615.00"), Currency::by_code("eur").map(|currency| currency.symbol));
+
+    let quarter = Coin::Quarter(UsState::Hawaii).denomination();
+    println!("{:?} -> {:?}, {:?}", quarter, Coin::from_denomination(&quarter), Coin::from_denomination(&KRW.coins[0]));
+
+    /*
+        Because each quarter carries its state, a collector can sort a pocketful of change into an album.
+    */
+    let mut album = Album::new();
+    let pocket = [de, Coin::Dime, pa, de, Coin::Quarter(UsState::Hawaii), Coin::Penny, nj, ga, de, Coin::Quarter(UsState::Texas)];
+    for &coin in pocket.iter() {
+        match album.add(coin) {
+            Added::New(state) => println!("new: {} ({}, {})", state, state.abbreviation(), state.release_year()),
+            Added::Duplicate(state) => println!("already have {}", state),
+            Added::NotAStateQuarter(coin) => println!("a {} has no state", coin),
+        }
+    }
+    println!("spares: {:?}, still missing {}", album.spares(), album.missing().len());
+    print!("{}", album);
+
+    for text in &["ny", " New   jersey ", "HAWAII", "Atlantis"] {
+        match text.parse::<UsState>() {
+            Ok(state) => println!("{:?} -> {:?}", text, state),
+            Err(error) => println!("{}", error),
+        }
+    }
+    for &state in UsState::ALL.iter() {
+        album.add(Coin::Quarter(state));
+    }
+    println!("complete: {}, missing: {:?}", album.is_complete(), album.missing());
+
+    /*
+        A coin sorter only sees mass and diameter, so it has to work out the `Coin` from measurements.
+        A simulated sensor adds noise to each coin's nominal size; anything outside every coin's tolerances is turned away.
+    */
+    let recognizer = Recognizer::us();
+    let mut sensor = NoisySensor::new(2024, 0.03, 0.025);
+    for spec in recognizer.specs() {
+        let (mut right, mut wrong, mut unknown) = (0, 0, 0);
+        for _ in 0..1000 {
+            match recognizer.recognize(sensor.measure(spec.nominal())) {
+                Recognition::Coin { coin, .. } if coin == spec.coin => right += 1,
+                Recognition::Coin { .. } => wrong += 1,
+                Recognition::Unknown { .. } => unknown += 1,
+            }
+        }
+        println!("{:<8} {} recognized, {} mistaken, {} rejected", spec.coin, right, wrong, unknown);
+    }
+
+    let hawaii = recognizer.spec(Coin::Quarter(UsState::Hawaii)).unwrap();
+    let readings = [
+        ("worn quarter", Reading { mass: hawaii.mass - 0.15, diameter: hawaii.diameter }),
+        ("steel washer", Reading { mass: 4.1, diameter: 24.3 }),
+        ("Canadian quarter", Reading { mass: 4.4, diameter: 23.88 }),
+        ("one-euro coin", Reading { mass: 7.5, diameter: 23.25 }),
+        ("broken sensor", Reading { mass: f64::NAN, diameter: 19.05 }),
+    ];
+    for &(name, reading) in readings.iter() {
+        println!("{:<16} {:<18} -> {}", name, reading.to_string(), recognizer.recognize(reading));
+    }
+    println!("{:?}", recognizer.recognize(hawaii.nominal()).coin());
+}
@@ -0,0 +1,245 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+use Coin;
+
+/*
+    A coin sorter can't read what is stamped on a coin, only weigh it and measure it. Each kind of coin is made to
+    a specification, a nominal mass and diameter with an allowed tolerance either way, so a reading that falls
+    inside one coin's tolerances is most likely that coin. A reading that falls outside all of them is a foreign
+    coin, a slug or a counterfeit, and is reported as unknown instead of being forced into the nearest match.
+
+    How far a reading is from a specification is measured in tolerances: 0 is spot on, 1 is at the edge of what
+    the mint allows. The worse of the mass and diameter deviations counts, since a coin has to pass both.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reading {
+    pub mass: f64,
+    pub diameter: f64,
+}
+
+impl fmt::Display for Reading {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.3} g, {:.2} mm", self.mass, self.diameter)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CoinSpec {
+    pub coin: Coin,
+    // Grams.
+    pub mass: f64,
+    pub mass_tolerance: f64,
+    // Millimetres.
+    pub diameter: f64,
+    pub diameter_tolerance: f64,
+}
+
+impl CoinSpec {
+    // The US Mint's specifications. Quarters come back as `Coin::ALL`'s stand-in, since the state can't be weighed.
+    pub const US: [CoinSpec; 4] = [
+        CoinSpec { coin: Coin::ALL[0], mass: 2.500, mass_tolerance: 0.100, diameter: 19.05, diameter_tolerance: 0.08 },
+        CoinSpec { coin: Coin::ALL[1], mass: 5.000, mass_tolerance: 0.194, diameter: 21.21, diameter_tolerance: 0.08 },
+        CoinSpec { coin: Coin::ALL[2], mass: 2.268, mass_tolerance: 0.091, diameter: 17.91, diameter_tolerance: 0.08 },
+        CoinSpec { coin: Coin::ALL[3], mass: 5.670, mass_tolerance: 0.227, diameter: 24.26, diameter_tolerance: 0.08 },
+    ];
+
+    pub fn nominal(&self) -> Reading {
+        Reading { mass: self.mass, diameter: self.diameter }
+    }
+
+    /*
+        How many tolerances `reading` is away from this coin; at most 1 means it is within specification.
+        NaN if a tolerance is zero or NaN, so such a spec never matches. `f64::max` would quietly drop the NaN instead.
+    */
+    pub fn deviation(&self, reading: Reading) -> f64 {
+        let mass = (reading.mass - self.mass).abs() / self.mass_tolerance;
+        let diameter = (reading.diameter - self.diameter).abs() / self.diameter_tolerance;
+        if mass.is_nan() || diameter.is_nan() {
+            return f64::NAN;
+        }
+        mass.max(diameter)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Recognition {
+    // `confidence` runs from 0, right at the edge of the tolerances, to 1, exactly the nominal mass and diameter.
+    Coin { coin: Coin, confidence: f64 },
+    // Outside every coin's tolerances. `nearest` is the closest coin and how many tolerances off the reading is,
+    // unless the reading itself is nonsense (negative, infinite or not a number).
+    Unknown { nearest: Option<(Coin, f64)> },
+}
+
+impl Recognition {
+    pub fn coin(&self) -> Option<Coin> {
+        match *self {
+            Recognition::Coin { coin, .. } => Some(coin),
+            Recognition::Unknown { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Recognition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Recognition::Coin { coin, confidence } => write!(f, "{} ({:.0}% sure)", coin, confidence * 100.0),
+            Recognition::Unknown { nearest: Some((coin, deviation)) } => {
+                write!(f, "unknown, nearest is a {} at {:.1} tolerances off", coin, deviation)
+            }
+            Recognition::Unknown { nearest: None } => write!(f, "unknown, not a valid reading"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Recognizer {
+    specs: Vec<CoinSpec>,
+}
+
+impl Recognizer {
+    pub fn new(specs: &[CoinSpec]) -> Recognizer {
+        Recognizer { specs: specs.to_vec() }
+    }
+
+    pub fn us() -> Recognizer {
+        Recognizer::new(&CoinSpec::US)
+    }
+
+    pub fn specs(&self) -> &[CoinSpec] {
+        &self.specs
+    }
+
+    pub fn spec(&self, coin: Coin) -> Option<&CoinSpec> {
+        self.specs.iter().find(|spec| spec.coin.cents() == coin.cents())
+    }
+
+    pub fn recognize(&self, reading: Reading) -> Recognition {
+        let valid = |value: f64| value.is_finite() && value > 0.0;
+        if !valid(reading.mass) || !valid(reading.diameter) {
+            return Recognition::Unknown { nearest: None };
+        }
+
+        let nearest = self.specs
+            .iter()
+            .map(|spec| (spec.coin, spec.deviation(reading)))
+            // A spec with a zero or NaN tolerance can give a NaN deviation; `total_cmp` ranks that after every number.
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        match nearest {
+            Some((coin, deviation)) if deviation <= 1.0 => Recognition::Coin { coin, confidence: 1.0 - deviation },
+            nearest => Recognition::Unknown { nearest },
+        }
+    }
+}
+
+/*
+    Stands in for the sorter's scale and calipers: each reading is the true value plus normally distributed noise.
+    The noise comes from a seeded generator, so the same seed gives the same readings every run.
+*/
+#[derive(Clone, Debug)]
+pub struct NoisySensor {
+    state: u64,
+    // Standard deviations, in grams and millimetres.
+    mass_noise: f64,
+    diameter_noise: f64,
+}
+
+impl NoisySensor {
+    pub fn new(seed: u64, mass_noise: f64, diameter_noise: f64) -> NoisySensor {
+        NoisySensor { state: seed, mass_noise, diameter_noise }
+    }
+
+    pub fn measure(&mut self, actual: Reading) -> Reading {
+        Reading {
+            mass: actual.mass + self.mass_noise * self.gaussian(),
+            diameter: actual.diameter + self.diameter_noise * self.gaussian(),
+        }
+    }
+
+    // Uniform in (0, 1], from a 64-bit linear congruential generator.
+    fn uniform(&mut self) -> f64 {
+        self.state = self.state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        ((self.state >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    // Standard normal, by the Box-Muller transform.
+    fn gaussian(&mut self) -> f64 {
+        let (u, v) = (self.uniform(), self.uniform());
+        (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use us_state::UsState;
+
+    #[test]
+    fn noisy_readings_are_recognized_and_never_mistaken() {
+        let recognizer = Recognizer::us();
+        let mut sensor = NoisySensor::new(2024, 0.03, 0.025);
+        for spec in recognizer.specs() {
+            let mut right = 0;
+            for _ in 0..1000 {
+                match recognizer.recognize(sensor.measure(spec.nominal())) {
+                    Recognition::Coin { coin, .. } => {
+                        assert_eq!(coin, spec.coin, "a {} was taken for a {}", spec.coin, coin);
+                        right += 1;
+                    }
+                    Recognition::Unknown { .. } => {}
+                }
+            }
+            assert!(right >= 950, "only {} of 1000 {}s recognized", right, spec.coin);
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_readings() {
+        let quarter = CoinSpec::US[3].nominal();
+        let mut a = NoisySensor::new(7, 0.1, 0.1);
+        let mut b = NoisySensor::new(7, 0.1, 0.1);
+        for _ in 0..10 {
+            assert_eq!(a.measure(quarter), b.measure(quarter));
+        }
+    }
+
+    #[test]
+    fn confidence_falls_towards_the_tolerance_edge() {
+        let recognizer = Recognizer::us();
+        let quarter = *recognizer.spec(Coin::Quarter(UsState::Hawaii)).unwrap();
+        assert_eq!(recognizer.recognize(quarter.nominal()), Recognition::Coin { coin: quarter.coin, confidence: 1.0 });
+
+        let worn = Reading { mass: quarter.mass - quarter.mass_tolerance / 2.0, diameter: quarter.diameter };
+        match recognizer.recognize(worn) {
+            Recognition::Coin { confidence, .. } => assert!((confidence - 0.5).abs() < 1e-9),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn slugs_and_bad_readings_are_unknown() {
+        let recognizer = Recognizer::us();
+        match recognizer.recognize(Reading { mass: 4.1, diameter: 24.3 }) {
+            Recognition::Unknown { nearest: Some((coin, deviation)) } => {
+                assert_eq!(coin.cents(), 25);
+                assert!(deviation > 1.0);
+            }
+            other => panic!("{:?}", other),
+        }
+        for &(mass, diameter) in &[(f64::NAN, 19.05), (2.5, f64::INFINITY), (-2.5, 19.05), (0.0, 19.05)] {
+            assert_eq!(recognizer.recognize(Reading { mass, diameter }), Recognition::Unknown { nearest: None });
+        }
+    }
+
+    #[test]
+    fn zero_or_nan_tolerances_do_not_panic() {
+        let mut specs = CoinSpec::US;
+        specs[0].mass_tolerance = 0.0;
+        specs[1].diameter_tolerance = f64::NAN;
+        let recognizer = Recognizer::new(&specs);
+        // The penny's mass is spot on, so its deviation is 0 / 0; the nickel's is NaN whatever the reading.
+        assert_eq!(recognizer.recognize(specs[0].nominal()).coin(), None);
+        assert_eq!(recognizer.recognize(specs[3].nominal()).coin(), Some(specs[3].coin));
+        assert_eq!(recognizer.recognize(Reading { mass: 5.0, diameter: 21.21 }).coin(), None);
+    }
+}
@@ -0,0 +1,168 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/*
+    From 1999 to 2008 the US Mint put a different state on the back of the quarter, five states a year
+    in the order they joined the Union. The variants below are in that same order, so comparing two states
+    compares their release dates, and a state's position in `ALL` gives its release year.
+
+    The macro writes out the enum, the `ALL` array and the name and abbreviation lookups from one table,
+    so the four can't disagree about which states exist.
+*/
+macro_rules! us_states {
+    ($($variant:ident => $name:expr, $abbreviation:expr;)*) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum UsState {
+            $($variant,)*
+        }
+
+        impl UsState {
+            pub const ALL: [UsState; 50] = [$(UsState::$variant,)*];
+
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    $(UsState::$variant => $name,)*
+                }
+            }
+
+            pub fn abbreviation(&self) -> &'static str {
+                match *self {
+                    $(UsState::$variant => $abbreviation,)*
+                }
+            }
+        }
+    };
+}
+
+us_states! {
+    Delaware => "Delaware", "DE";
+    Pennsylvania => "Pennsylvania", "PA";
+    NewJersey => "New Jersey", "NJ";
+    Georgia => "Georgia", "GA";
+    Connecticut => "Connecticut", "CT";
+    Massachusetts => "Massachusetts", "MA";
+    Maryland => "Maryland", "MD";
+    SouthCarolina => "South Carolina", "SC";
+    NewHampshire => "New Hampshire", "NH";
+    Virginia => "Virginia", "VA";
+    NewYork => "New York", "NY";
+    NorthCarolina => "North Carolina", "NC";
+    RhodeIsland => "Rhode Island", "RI";
+    Vermont => "Vermont", "VT";
+    Kentucky => "Kentucky", "KY";
+    Tennessee => "Tennessee", "TN";
+    Ohio => "Ohio", "OH";
+    Louisiana => "Louisiana", "LA";
+    Indiana => "Indiana", "IN";
+    Mississippi => "Mississippi", "MS";
+    Illinois => "Illinois", "IL";
+    Alabama => "Alabama", "AL";
+    Maine => "Maine", "ME";
+    Missouri => "Missouri", "MO";
+    Arkansas => "Arkansas", "AR";
+    Michigan => "Michigan", "MI";
+    Florida => "Florida", "FL";
+    Texas => "Texas", "TX";
+    Iowa => "Iowa", "IA";
+    Wisconsin => "Wisconsin", "WI";
+    California => "California", "CA";
+    Minnesota => "Minnesota", "MN";
+    Oregon => "Oregon", "OR";
+    Kansas => "Kansas", "KS";
+    WestVirginia => "West Virginia", "WV";
+    Nevada => "Nevada", "NV";
+    Nebraska => "Nebraska", "NE";
+    Colorado => "Colorado", "CO";
+    NorthDakota => "North Dakota", "ND";
+    SouthDakota => "South Dakota", "SD";
+    Montana => "Montana", "MT";
+    Washington => "Washington", "WA";
+    Idaho => "Idaho", "ID";
+    Wyoming => "Wyoming", "WY";
+    Utah => "Utah", "UT";
+    Oklahoma => "Oklahoma", "OK";
+    NewMexico => "New Mexico", "NM";
+    Arizona => "Arizona", "AZ";
+    Alaska => "Alaska", "AK";
+    Hawaii => "Hawaii", "HI";
+}
+
+impl UsState {
+    // The year this state's quarter came out.
+    pub fn release_year(&self) -> u16 {
+        1999 + *self as u16 / 5
+    }
+}
+
+impl fmt::Display for UsState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseStateError(String);
+
+impl fmt::Display for ParseStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} is neither the name nor the abbreviation of a US state", self.0)
+    }
+}
+
+impl Error for ParseStateError {}
+
+// Accepts the name or the two-letter abbreviation, ignoring case and extra spaces: "new york", "NY", " ny ".
+impl FromStr for UsState {
+    type Err = ParseStateError;
+
+    fn from_str(text: &str) -> Result<UsState, ParseStateError> {
+        let wanted = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        UsState::ALL
+            .iter()
+            .cloned()
+            .find(|state| state.name().eq_ignore_ascii_case(&wanted) || state.abbreviation().eq_ignore_ascii_case(&wanted))
+            .ok_or_else(|| ParseStateError(text.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn states_come_in_release_order() {
+        assert_eq!(UsState::ALL.len(), 50);
+        assert!(UsState::ALL.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!((UsState::Delaware.release_year(), UsState::Connecticut.release_year()), (1999, 1999));
+        assert_eq!((UsState::Massachusetts.release_year(), UsState::Hawaii.release_year()), (2000, 2008));
+        for year in 1999..=2008 {
+            assert_eq!(UsState::ALL.iter().filter(|state| state.release_year() == year).count(), 5);
+        }
+    }
+
+    #[test]
+    fn names_and_abbreviations_are_unique() {
+        for (i, a) in UsState::ALL.iter().enumerate() {
+            assert_eq!(a.abbreviation().len(), 2);
+            for b in &UsState::ALL[i + 1..] {
+                assert_ne!(a.name(), b.name());
+                assert_ne!(a.abbreviation(), b.abbreviation());
+            }
+        }
+        assert_eq!(format!("[{:>10}]", UsState::Ohio), "[      Ohio]");
+    }
+
+    #[test]
+    fn parses_names_and_abbreviations() {
+        for &state in UsState::ALL.iter() {
+            assert_eq!(state.name().parse(), Ok(state));
+            assert_eq!(state.abbreviation().to_lowercase().parse(), Ok(state));
+        }
+        assert_eq!(" New   jersey ".parse(), Ok(UsState::NewJersey));
+        assert_eq!("HAWAII".parse(), Ok(UsState::Hawaii));
+        let error = "Atlantis".parse::<UsState>().unwrap_err();
+        assert_eq!(error.to_string(), "\"Atlantis\" is neither the name nor the abbreviation of a US state");
+        assert!("".parse::<UsState>().is_err());
+    }
+}
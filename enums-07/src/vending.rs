@@ -0,0 +1,474 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+use change::{Change, CoinSystem, Inventory, MAX_TABLE_AMOUNT};
+use Coin;
+
+/*
+    A vending machine is a state machine: what it does with a coin or a button press depends on what it is doing,
+    and an enum says exactly which states there are and what each one knows. `Collecting` is the only state that has
+    a credit, and `Dispensing` is the only one that has a product on its way out, so the compiler won't let code
+    read a credit while dispensing or hand out a product while collecting.
+
+        Idle --coin--> Collecting --select--> Dispensing --dispensed--> ReturningChange --change taken--> Idle
+                           |                                   \________________(no change due)________/
+                           +--cancel--> ReturningChange
+        (any resting state, once the last product is sold) --> OutOfStock --restock--> Idle
+
+    Every event is logged, accepted or not. The machine does nothing random, so replaying the log on a machine
+    set up the same way leads to the same state, which makes any sequence of events easy to reproduce.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum State {
+    Idle,
+    Collecting { credit: u32 },
+    Dispensing { slot: String, change: Change<Coin> },
+    ReturningChange { change: Change<Coin> },
+    OutOfStock,
+}
+
+// The most credit a customer can build up. Coins that would take it further are pushed back out, so change for it can always be worked out.
+pub const MAX_CREDIT: u32 = MAX_TABLE_AMOUNT;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    InsertCoin(Coin),
+    Select(String),
+    Cancel,
+    // The product has dropped into the tray.
+    Dispensed,
+    // The customer has taken the coins out of the return slot.
+    ChangeTaken,
+    Restock { slot: String, count: u32 },
+}
+
+// What the machine does in response to an event, for the hardware to carry out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Output {
+    Credit(u32),
+    // Pushes a coin straight back out, e.g. because nothing is left to buy or the credit is at `MAX_CREDIT`.
+    RejectCoin(Coin),
+    DispenseProduct(String),
+    ReturnCoins(Change<Coin>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VendError {
+    // The event makes no sense in the current state, e.g. selecting a product while one is being dispensed.
+    UnexpectedEvent { state: &'static str, event: Event },
+    UnknownSlot(String),
+    SoldOut(String),
+    InsufficientCredit { price: u32, credit: u32 },
+    // The drawer doesn't have the right coins to give change; the customer can pay exactly or cancel.
+    CannotMakeChange { amount: u32 },
+}
+
+impl fmt::Display for VendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VendError::UnexpectedEvent { state, ref event } => write!(f, "{:?} is not expected while {}", event, state),
+            VendError::UnknownSlot(ref slot) => write!(f, "there is no slot {}", slot),
+            VendError::SoldOut(ref slot) => write!(f, "slot {} is sold out", slot),
+            VendError::InsufficientCredit { price, credit } => {
+                write!(f, "price is {} cents but only {} cents were inserted", price, credit)
+            }
+            VendError::CannotMakeChange { amount } => write!(f, "can't give {} cents change, please use exact change", amount),
+        }
+    }
+}
+
+impl Error for VendError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Product {
+    pub name: String,
+    pub price: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Slot {
+    pub product: Product,
+    pub count: u32,
+}
+
+// Everything a machine starts out with: products by slot code, and the coins put in the drawer for change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Setup {
+    pub slots: BTreeMap<String, Slot>,
+    pub drawer: Inventory<Coin>,
+}
+
+impl Setup {
+    pub fn new() -> Setup {
+        Setup { slots: BTreeMap::new(), drawer: Inventory::new() }
+    }
+
+    pub fn slot(mut self, code: &str, name: &str, price: u32, count: u32) -> Setup {
+        let product = Product { name: name.to_string(), price };
+        self.slots.insert(code.to_string(), Slot { product, count });
+        self
+    }
+
+    pub fn coins(mut self, coin: Coin, count: u32) -> Setup {
+        self.drawer.add(coin, count);
+        self
+    }
+}
+
+impl Default for Setup {
+    fn default() -> Setup {
+        Setup::new()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct VendingMachine {
+    setup: Setup,
+    state: State,
+    slots: BTreeMap<String, Slot>,
+    drawer: Inventory<Coin>,
+    coins: CoinSystem<Coin>,
+    log: Vec<Event>,
+}
+
+impl VendingMachine {
+    pub fn new(setup: Setup) -> VendingMachine {
+        let mut machine = VendingMachine {
+            state: State::Idle,
+            slots: setup.slots.clone(),
+            drawer: setup.drawer.clone(),
+            coins: CoinSystem::new(&Coin::ALL).unwrap(),
+            log: Vec::new(),
+            setup,
+        };
+        if machine.is_empty() {
+            machine.state = State::OutOfStock;
+        }
+        machine
+    }
+
+    // A machine set up like `setup` that has been through `events`, one after another.
+    pub fn replay(setup: Setup, events: &[Event]) -> VendingMachine {
+        let mut machine = VendingMachine::new(setup);
+        for event in events {
+            // Rejected events are part of the log too, and are rejected again here without changing anything.
+            let _ = machine.handle(event.clone());
+        }
+        machine
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    pub fn log(&self) -> &[Event] {
+        &self.log
+    }
+
+    pub fn setup(&self) -> &Setup {
+        &self.setup
+    }
+
+    pub fn drawer(&self) -> &Inventory<Coin> {
+        &self.drawer
+    }
+
+    pub fn stock(&self, slot: &str) -> Option<u32> {
+        self.slots.get(slot).map(|slot| slot.count)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.slots.values().all(|slot| slot.count == 0)
+    }
+
+    // Where the machine rests once a sale or refund is over.
+    fn resting_state(&self) -> State {
+        if self.is_empty() {
+            State::OutOfStock
+        } else {
+            State::Idle
+        }
+    }
+
+    fn state_name(&self) -> &'static str {
+        match self.state {
+            State::Idle => "idle",
+            State::Collecting { .. } => "collecting coins",
+            State::Dispensing { .. } => "dispensing",
+            State::ReturningChange { .. } => "returning change",
+            State::OutOfStock => "out of stock",
+        }
+    }
+
+    // Applies `event`. On error nothing changes, apart from the event being logged.
+    pub fn handle(&mut self, event: Event) -> Result<Vec<Output>, VendError> {
+        self.log.push(event.clone());
+
+        match (self.state.clone(), event) {
+            (State::OutOfStock, Event::InsertCoin(coin)) => Ok(vec![Output::RejectCoin(coin)]),
+
+            (State::Idle, Event::InsertCoin(coin)) => {
+                self.drawer.add(coin, 1);
+                self.state = State::Collecting { credit: coin.cents() };
+                Ok(vec![Output::Credit(coin.cents())])
+            }
+            (State::Collecting { credit }, Event::InsertCoin(coin)) => {
+                match credit.checked_add(coin.cents()).filter(|&credit| credit <= MAX_CREDIT) {
+                    Some(credit) => {
+                        self.drawer.add(coin, 1);
+                        self.state = State::Collecting { credit };
+                        Ok(vec![Output::Credit(credit)])
+                    }
+                    None => Ok(vec![Output::RejectCoin(coin)]),
+                }
+            }
+
+            (State::Collecting { credit }, Event::Select(code)) => {
+                let price = match self.slots.get(&code) {
+                    None => return Err(VendError::UnknownSlot(code)),
+                    Some(slot) if slot.count == 0 => return Err(VendError::SoldOut(code)),
+                    Some(slot) => slot.product.price,
+                };
+                if credit < price {
+                    return Err(VendError::InsufficientCredit { price, credit });
+                }
+                let amount = credit - price;
+                let change = self.coins
+                    .make_change_from(amount, &self.drawer)
+                    .map_err(|_| VendError::CannotMakeChange { amount })?;
+
+                // The change was worked out from the drawer, so it is all there. Quarters are interchangeable for change,
+                // so the drawer hands back whichever ones it has, and those are the coins the customer gets.
+                let change = self.drawer.take(&change).unwrap();
+                self.slots.get_mut(&code).unwrap().count -= 1;
+                self.state = State::Dispensing { slot: code.clone(), change };
+                Ok(vec![Output::DispenseProduct(code)])
+            }
+
+            (State::Collecting { credit }, Event::Cancel) => {
+                /*
+                    The coins just inserted are all still in the drawer, each worth one of the coin system's values, and the
+                    credit stays within `MAX_CREDIT`, so change for it can always be made, if need be from those same coins.
+                */
+                let change = self.coins.make_change_from(credit, &self.drawer).unwrap();
+                let change = self.drawer.take(&change).unwrap();
+                self.state = State::ReturningChange { change: change.clone() };
+                Ok(vec![Output::ReturnCoins(change)])
+            }
+
+            (State::Dispensing { change, .. }, Event::Dispensed) => {
+                if change.coins.is_empty() {
+                    self.state = self.resting_state();
+                    Ok(Vec::new())
+                } else {
+                    self.state = State::ReturningChange { change: change.clone() };
+                    Ok(vec![Output::ReturnCoins(change)])
+                }
+            }
+
+            (State::ReturningChange { .. }, Event::ChangeTaken) => {
+                self.state = self.resting_state();
+                Ok(Vec::new())
+            }
+
+            (State::Idle, Event::Restock { slot, count }) | (State::OutOfStock, Event::Restock { slot, count }) => {
+                match self.slots.get_mut(&slot) {
+                    Some(stocked) => stocked.count += count,
+                    None => return Err(VendError::UnknownSlot(slot)),
+                }
+                self.state = self.resting_state();
+                Ok(Vec::new())
+            }
+
+            (_, event) => Err(VendError::UnexpectedEvent { state: self.state_name(), event }),
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            State::Idle => write!(f, "idle"),
+            State::Collecting { credit } => write!(f, "collecting ({} cents in)", credit),
+            State::Dispensing { ref slot, ref change } => write!(f, "dispensing {} (change: {})", slot, change),
+            State::ReturningChange { ref change } => write!(f, "returning {}", change),
+            State::OutOfStock => write!(f, "out of stock"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use us_state::UsState;
+    use Event::*;
+
+    const DE: Coin = Coin::Quarter(UsState::Delaware);
+    const PA: Coin = Coin::Quarter(UsState::Pennsylvania);
+    const NJ: Coin = Coin::Quarter(UsState::NewJersey);
+    const GA: Coin = Coin::Quarter(UsState::Georgia);
+
+    fn setup() -> Setup {
+        Setup::new()
+            .slot("A1", "cola", 75, 5)
+            .slot("A2", "water", 50, 1)
+            .slot("B1", "gum", 15, 0)
+            .coins(Coin::Dime, 5)
+            .coins(Coin::Nickel, 5)
+    }
+
+    fn select(code: &str) -> Event {
+        Select(code.to_string())
+    }
+
+    fn credit(cents: u32) -> Result<Vec<Output>, VendError> {
+        Ok(vec![Output::Credit(cents)])
+    }
+
+    fn dispense(code: &str) -> Result<Vec<Output>, VendError> {
+        Ok(vec![Output::DispenseProduct(code.to_string())])
+    }
+
+    fn change(coins: Vec<(Coin, u32)>) -> Change<Coin> {
+        Change { coins }
+    }
+
+    // Feeds `script` to a fresh machine, checking every response and the state it ends in, then checks that replaying
+    // the machine's log reproduces that state, drawer and stock.
+    fn run(setup: Setup, script: &[(Event, Result<Vec<Output>, VendError>)], end: State) -> VendingMachine {
+        let mut machine = VendingMachine::new(setup);
+        for (step, (event, expected)) in script.iter().enumerate() {
+            let actual = machine.handle(event.clone());
+            assert_eq!(&actual, expected, "step {} ({:?})", step, event);
+        }
+        assert_eq!(machine.state(), &end);
+        assert_eq!(machine.log().len(), script.len());
+
+        let replayed = VendingMachine::replay(machine.setup().clone(), machine.log());
+        assert_eq!(replayed.state(), machine.state());
+        assert_eq!(replayed.drawer(), machine.drawer());
+        assert_eq!(replayed.slots, machine.slots);
+        assert_eq!(replayed.log(), machine.log());
+        machine
+    }
+
+    #[test]
+    fn exact_money() {
+        let machine = run(setup(), &[
+            (InsertCoin(DE), credit(25)),
+            (InsertCoin(PA), credit(50)),
+            (InsertCoin(NJ), credit(75)),
+            (select("A1"), dispense("A1")),
+            (Dispensed, Ok(vec![])),
+        ], State::Idle);
+        assert_eq!(machine.stock("A1"), Some(4));
+        assert_eq!(machine.drawer().total(), 75 + 75);
+    }
+
+    #[test]
+    fn change_due_pays_back_the_earliest_quarter() {
+        run(setup(), &[
+            (InsertCoin(DE), credit(25)),
+            (InsertCoin(PA), credit(50)),
+            (InsertCoin(NJ), credit(75)),
+            (InsertCoin(GA), credit(100)),
+            (select("A1"), dispense("A1")),
+            (Dispensed, Ok(vec![Output::ReturnCoins(change(vec![(DE, 1)]))])),
+            (ChangeTaken, Ok(vec![])),
+        ], State::Idle);
+    }
+
+    #[test]
+    fn not_enough_money_then_more() {
+        run(setup(), &[
+            (InsertCoin(DE), credit(25)),
+            (select("A2"), Err(VendError::InsufficientCredit { price: 50, credit: 25 })),
+            (InsertCoin(Coin::Dime), credit(35)),
+            (InsertCoin(PA), credit(60)),
+            (select("A2"), dispense("A2")),
+            (Dispensed, Ok(vec![Output::ReturnCoins(change(vec![(Coin::Dime, 1)]))])),
+        ], State::ReturningChange { change: change(vec![(Coin::Dime, 1)]) });
+    }
+
+    #[test]
+    fn cancel_returns_the_credit() {
+        run(setup(), &[
+            (InsertCoin(Coin::Dime), credit(10)),
+            (InsertCoin(Coin::Penny), credit(11)),
+            (select("B1"), Err(VendError::SoldOut("B1".to_string()))),
+            (select("C9"), Err(VendError::UnknownSlot("C9".to_string()))),
+            (Cancel, Ok(vec![Output::ReturnCoins(change(vec![(Coin::Dime, 1), (Coin::Penny, 1)]))])),
+            (ChangeTaken, Ok(vec![])),
+        ], State::Idle);
+    }
+
+    #[test]
+    fn credit_stops_at_the_limit_and_cancel_pays_it_back() {
+        let mut machine = VendingMachine::new(setup());
+        let quarters = MAX_CREDIT / 25;
+        for _ in 0..quarters {
+            machine.handle(InsertCoin(DE)).unwrap();
+        }
+        assert_eq!(machine.state(), &State::Collecting { credit: MAX_CREDIT });
+        assert_eq!(machine.handle(InsertCoin(PA)), Ok(vec![Output::RejectCoin(PA)]));
+        assert_eq!(machine.handle(InsertCoin(Coin::Penny)), Ok(vec![Output::RejectCoin(Coin::Penny)]));
+        assert_eq!(machine.drawer().count(PA) + machine.drawer().count(Coin::Penny), 0);
+
+        let refund = change(vec![(DE, quarters)]);
+        assert_eq!(machine.handle(Cancel), Ok(vec![Output::ReturnCoins(refund.clone())]));
+        assert_eq!(machine.state(), &State::ReturningChange { change: refund });
+        assert_eq!(machine.drawer(), &setup().drawer);
+    }
+
+    #[test]
+    fn no_coins_for_change() {
+        run(Setup::new().slot("A1", "cola", 60, 1), &[
+            (InsertCoin(DE), credit(25)),
+            (InsertCoin(PA), credit(50)),
+            (InsertCoin(NJ), credit(75)),
+            (select("A1"), Err(VendError::CannotMakeChange { amount: 15 })),
+            (Cancel, Ok(vec![Output::ReturnCoins(change(vec![(DE, 1), (PA, 1), (NJ, 1)]))])),
+            (ChangeTaken, Ok(vec![])),
+        ], State::Idle);
+    }
+
+    #[test]
+    fn sold_out_and_restocked() {
+        let machine = run(Setup::new().slot("A1", "cola", 75, 1), &[
+            (Dispensed, Err(VendError::UnexpectedEvent { state: "idle", event: Dispensed })),
+            (InsertCoin(DE), credit(25)),
+            (InsertCoin(PA), credit(50)),
+            (InsertCoin(NJ), credit(75)),
+            (select("A1"), dispense("A1")),
+            (InsertCoin(Coin::Dime), Err(VendError::UnexpectedEvent { state: "dispensing", event: InsertCoin(Coin::Dime) })),
+            (Dispensed, Ok(vec![])),
+            (InsertCoin(Coin::Dime), Ok(vec![Output::RejectCoin(Coin::Dime)])),
+            (Restock { slot: "A1".to_string(), count: 3 }, Ok(vec![])),
+        ], State::Idle);
+        assert_eq!(machine.stock("A1"), Some(3));
+    }
+
+    #[test]
+    fn empty_machine_starts_out_of_stock() {
+        run(Setup::new().slot("A1", "cola", 75, 0), &[
+            (InsertCoin(DE), Ok(vec![Output::RejectCoin(DE)])),
+            (Restock { slot: "B9".to_string(), count: 1 }, Err(VendError::UnknownSlot("B9".to_string()))),
+        ], State::OutOfStock);
+    }
+
+    #[test]
+    fn replaying_every_prefix_of_the_log_reaches_the_same_state() {
+        let mut machine = VendingMachine::new(setup());
+        let events = [InsertCoin(DE), select("A2"), InsertCoin(GA), Cancel, ChangeTaken, InsertCoin(PA), InsertCoin(NJ),
+                      InsertCoin(Coin::Dime), select("A2"), Dispensed, ChangeTaken, InsertCoin(Coin::Nickel)];
+        for (i, event) in events.iter().enumerate() {
+            let _ = machine.handle(event.clone());
+            let replayed = VendingMachine::replay(setup(), &machine.log()[..=i]);
+            assert_eq!(replayed.state(), machine.state(), "after {:?}", event);
+            assert_eq!(replayed.drawer(), machine.drawer(), "after {:?}", event);
+        }
+        assert_eq!(machine.state(), &State::Collecting { credit: 5 });
+        assert_eq!(machine.stock("A2"), Some(0));
+    }
+}
@@ -0,0 +1,547 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/*
+    A small configuration format, one setting per line:
+
+        # Comments start with `#` or `;`.
+        name = demo
+        [server]                 ; they can also follow a section header or a quoted value
+        host = "0.0.0.0"         # quotes keep spaces and allow \" \\ \n \t escapes
+        url = http://example.com/#top
+        timeout = 1m30s
+
+    An unquoted value runs to the end of the line, `#` and `;` included, so it can hold URLs and the like.
+
+    Keys under a `[section]` header are looked up as `section.key`. Values are kept as text and only turn into
+    numbers, booleans, durations or lists when a getter asks for them, so a bad value is reported by the getter,
+    with the place it came from.
+
+    Parsing doesn't stop at the first mistake: every problem in the file is collected and reported together,
+    each with its file, line and column, so they can all be fixed in one go.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Origin {
+    // Lines and columns count from 1; columns count characters, not bytes.
+    File { file: String, line: usize, column: usize },
+    Env { variable: String },
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Origin::File { ref file, line, column } => write!(f, "{}:{}:{}", file, line, column),
+            Origin::Env { ref variable } => write!(f, "environment variable {}", variable),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Problem {
+    MissingEquals,
+    EmptyKey,
+    InvalidKeyCharacter(char),
+    DuplicateKey { key: String, first_line: usize },
+    UnterminatedSection,
+    EmptySectionName,
+    UnterminatedString,
+    InvalidEscape(char),
+    // Something other than a comment follows a `]` or a closing quote.
+    TrailingCharacters,
+    InvalidValue { key: String, expected: &'static str, found: String },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Problem::MissingEquals => write!(f, "expected `key = value`, `[section]` or a comment"),
+            Problem::EmptyKey => write!(f, "the key before `=` is missing"),
+            Problem::InvalidKeyCharacter(ch) => {
+                write!(f, "{:?} can't be used in a name; use letters, digits, `_`, `-` and `.`", ch)
+            }
+            Problem::DuplicateKey { ref key, first_line } => write!(f, "{} is already set on line {}", key, first_line),
+            Problem::UnterminatedSection => write!(f, "the section header has no closing `]`"),
+            Problem::EmptySectionName => write!(f, "the section name is missing"),
+            Problem::UnterminatedString => write!(f, "the quoted value has no closing `\"`"),
+            Problem::InvalidEscape(ch) => write!(f, "`\\{}` is not an escape; use \\\" \\\\ \\n or \\t", ch),
+            Problem::TrailingCharacters => write!(f, "unexpected text; comments have to start with `#` or `;`"),
+            Problem::InvalidValue { ref key, expected, ref found } => {
+                write!(f, "{} should be {}, not {:?}", key, expected, found)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigError {
+    pub origin: Origin,
+    pub problem: Problem,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.origin, self.problem)
+    }
+}
+
+impl Error for ConfigError {}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io { path: PathBuf, source: io::Error },
+    // Every problem found in the file, in the order they appear.
+    Invalid(Vec<ConfigError>),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Io { ref path, .. } => write!(f, "couldn't read {}", path.display()),
+            LoadError::Invalid(ref errors) => {
+                write!(f, "{} problem(s) in the configuration", errors.len())?;
+                for error in errors {
+                    write!(f, "\n  {}", error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            LoadError::Io { ref source, .. } => Some(source),
+            LoadError::Invalid(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Value {
+    text: String,
+    // Where the value starts, for reporting it if it turns out to be the wrong type.
+    origin: Origin,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    values: BTreeMap<String, Value>,
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, LoadError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| LoadError::Io { path: path.to_path_buf(), source })?;
+        Config::parse(&path.display().to_string(), &text).map_err(LoadError::Invalid)
+    }
+
+    // `file` is only used to say where errors are.
+    pub fn parse(file: &str, text: &str) -> Result<Config, Vec<ConfigError>> {
+        let mut parser = Parser { file, config: Config::default(), section: String::new(), errors: Vec::new() };
+        for (index, line) in text.lines().enumerate() {
+            parser.line(index + 1, line);
+        }
+        if parser.errors.is_empty() {
+            Ok(parser.config)
+        } else {
+            Err(parser.errors)
+        }
+    }
+
+    /*
+        Replaces settings with environment variables named after them: with prefix `APP`, `server.request-timeout`
+        is overridden by `APP_SERVER_REQUEST_TIMEOUT`. Only settings already in the file can be overridden, since
+        a variable name doesn't say where the section ends and the key begins.
+    */
+    pub fn apply_env(&mut self, prefix: &str) {
+        self.apply_vars(prefix, env::vars());
+    }
+
+    // `apply_env` with the variables given instead of read from the environment.
+    pub fn apply_vars<I: IntoIterator<Item = (String, String)>>(&mut self, prefix: &str, vars: I) {
+        let names: BTreeMap<String, String> =
+            self.values.keys().map(|key| (env_name(prefix, key), key.clone())).collect();
+        for (variable, text) in vars {
+            if let Some(key) = names.get(&variable) {
+                self.values.insert(key.clone(), Value { text, origin: Origin::Env { variable } });
+            }
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(|key| key.as_str())
+    }
+
+    pub fn origin(&self, key: &str) -> Option<&Origin> {
+        self.values.get(key).map(|value| &value.origin)
+    }
+
+    pub fn string(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|value| value.text.as_str())
+    }
+
+    // The typed getters give `Ok(None)` for a missing setting, so `config.int("port")?.unwrap_or(8080)` sets a default.
+    pub fn int(&self, key: &str) -> Result<Option<i64>, ConfigError> {
+        self.typed(key, "a whole number", |text| text.replace('_', "").parse().ok())
+    }
+
+    // `true`/`false`, `yes`/`no`, `on`/`off` or `1`/`0`, in any case.
+    pub fn bool(&self, key: &str) -> Result<Option<bool>, ConfigError> {
+        self.typed(key, "true or false", |text| match text.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Some(true),
+            "false" | "no" | "off" | "0" => Some(false),
+            _ => None,
+        })
+    }
+
+    // Numbers with units, added up: `250ms`, `30s`, `1h30m`, `2d`.
+    pub fn duration(&self, key: &str) -> Result<Option<Duration>, ConfigError> {
+        self.typed(key, "a duration such as 30s, 5m or 1h30m", parse_duration)
+    }
+
+    // Comma-separated, with spaces around the items trimmed; an empty value is an empty list.
+    pub fn list(&self, key: &str) -> Option<Vec<&str>> {
+        self.string(key).map(|text| {
+            if text.trim().is_empty() {
+                Vec::new()
+            } else {
+                text.split(',').map(|item| item.trim()).collect()
+            }
+        })
+    }
+
+    fn typed<T, F: Fn(&str) -> Option<T>>(&self, key: &str, expected: &'static str, parse: F) -> Result<Option<T>, ConfigError> {
+        let value = match self.values.get(key) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        match parse(value.text.trim()) {
+            Some(parsed) => Ok(Some(parsed)),
+            None => Err(ConfigError {
+                origin: value.origin.clone(),
+                problem: Problem::InvalidValue { key: key.to_string(), expected, found: value.text.clone() },
+            }),
+        }
+    }
+}
+
+fn env_name(prefix: &str, key: &str) -> String {
+    let key: String = key.chars().map(|ch| if ch == '.' || ch == '-' { '_' } else { ch.to_ascii_uppercase() }).collect();
+    if prefix.is_empty() {
+        key
+    } else {
+        format!("{}_{}", prefix, key)
+    }
+}
+
+fn parse_duration(text: &str) -> Option<Duration> {
+    let mut total = Duration::from_secs(0);
+    let mut rest = text;
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let digits = rest.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(rest.len());
+        let number: u64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit = rest.find(|ch: char| ch.is_ascii_digit()).unwrap_or(rest.len());
+        let millis = match &rest[..unit] {
+            "ms" => 1,
+            "s" => 1_000,
+            "m" => 60_000,
+            "h" => 3_600_000,
+            "d" => 86_400_000,
+            _ => return None,
+        };
+        rest = &rest[unit..];
+        total = total.checked_add(Duration::from_millis(number.checked_mul(millis)?))?;
+    }
+    Some(total)
+}
+
+fn is_name_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '-' || ch == '.'
+}
+
+fn is_comment(text: &str) -> bool {
+    text.is_empty() || text.starts_with('#') || text.starts_with(';')
+}
+
+struct Parser<'a> {
+    file: &'a str,
+    config: Config,
+    // The current section's name followed by `.`, or empty before the first header.
+    section: String,
+    errors: Vec<ConfigError>,
+}
+
+impl<'a> Parser<'a> {
+    fn origin(&self, line: usize, column: usize) -> Origin {
+        Origin::File { file: self.file.to_string(), line, column }
+    }
+
+    fn error(&mut self, line: usize, column: usize, problem: Problem) {
+        let origin = self.origin(line, column);
+        self.errors.push(ConfigError { origin, problem });
+    }
+
+    // The 1-based column of byte offset `at` in `text`.
+    fn column(text: &str, at: usize) -> usize {
+        text[..at].chars().count() + 1
+    }
+
+    fn line(&mut self, number: usize, text: &str) {
+        let trimmed = text.trim_start();
+        let start = text.len() - trimmed.len();
+        if is_comment(trimmed.trim_end()) {
+            return;
+        }
+
+        if trimmed.starts_with('[') {
+            self.section_header(number, text, start);
+            return;
+        }
+
+        let equals = match text.find('=') {
+            Some(equals) => equals,
+            None => return self.error(number, Parser::column(text, start), Problem::MissingEquals),
+        };
+        let key = text[..equals].trim();
+        if key.is_empty() {
+            return self.error(number, Parser::column(text, start), Problem::EmptyKey);
+        }
+        if let Some((offset, ch)) = key.char_indices().find(|&(_, ch)| !is_name_char(ch)) {
+            return self.error(number, Parser::column(text, start + offset), Problem::InvalidKeyCharacter(ch));
+        }
+        let key = format!("{}{}", self.section, key);
+
+        let after = &text[equals + 1..];
+        let value_start = equals + 1 + (after.len() - after.trim_start().len());
+        let value = match self.value(number, text, value_start) {
+            Some(value) => value,
+            None => return,
+        };
+
+        if let Some(&Value { origin: Origin::File { line: first_line, .. }, .. }) = self.config.values.get(&key) {
+            return self.error(number, Parser::column(text, start), Problem::DuplicateKey { key, first_line });
+        }
+        let origin = self.origin(number, Parser::column(text, value_start));
+        self.config.values.insert(key, Value { text: value, origin });
+    }
+
+    // Even a broken header starts a new section, so the keys after it aren't mistaken for the previous section's.
+    fn section_header(&mut self, number: usize, text: &str, open: usize) {
+        let close = match text[open..].find(']') {
+            Some(close) => open + close,
+            None => {
+                self.error(number, Parser::column(text, open), Problem::UnterminatedSection);
+                self.section = format!("{}.", text[open + 1..].trim());
+                return;
+            }
+        };
+        let name = text[open + 1..close].trim();
+        let name_start = open + 1 + (text[open + 1..close].len() - text[open + 1..close].trim_start().len());
+        if name.is_empty() {
+            self.error(number, Parser::column(text, open), Problem::EmptySectionName);
+        } else if let Some((offset, ch)) = name.char_indices().find(|&(_, ch)| !is_name_char(ch)) {
+            self.error(number, Parser::column(text, name_start + offset), Problem::InvalidKeyCharacter(ch));
+        }
+        self.trailing(number, text, close + 1);
+        self.section = format!("{}.", name);
+    }
+
+    // Reads the value starting at byte `start`, reporting problems and returning `None` if there are any.
+    fn value(&mut self, number: usize, text: &str, start: usize) -> Option<String> {
+        let rest = &text[start..];
+        if !rest.starts_with('"') {
+            return Some(rest.trim_end().to_string());
+        }
+
+        let mut value = String::new();
+        let mut chars = rest.char_indices().skip(1);
+        let mut valid = true;
+        while let Some((offset, ch)) = chars.next() {
+            match ch {
+                '"' => {
+                    let end = start + offset + 1;
+                    return if self.trailing(number, text, end) && valid { Some(value) } else { None };
+                }
+                '\\' => match chars.next() {
+                    Some((_, '"')) => value.push('"'),
+                    Some((_, '\\')) => value.push('\\'),
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, other)) => {
+                        self.error(number, Parser::column(text, start + offset), Problem::InvalidEscape(other));
+                        valid = false;
+                    }
+                    None => break,
+                },
+                ch => value.push(ch),
+            }
+        }
+        self.error(number, Parser::column(text, start), Problem::UnterminatedString);
+        None
+    }
+
+    // Checks that only a comment follows byte `end` on the line.
+    fn trailing(&mut self, number: usize, text: &str, end: usize) -> bool {
+        let rest = text[end..].trim_start();
+        if is_comment(rest.trim_end()) {
+            return true;
+        }
+        let column = Parser::column(text, text.len() - rest.len());
+        self.error(number, column, Problem::TrailingCharacters);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    fn positions(errors: &[ConfigError]) -> Vec<(usize, usize)> {
+        errors.iter().map(|error| match error.origin {
+            Origin::File { line, column, .. } => (line, column),
+            Origin::Env { .. } => panic!("{:?}", error),
+        }).collect()
+    }
+
+    fn demo() -> Config {
+        let text = "# settings for the demo\n\
+                    name = demo\n\
+                    \n\
+                    [server]   ; where to listen\n\
+                    host = \"0.0.0.0\"\n\
+                    port = 8_080\n\
+                    request-timeout = 1m30s\n\
+                    verbose = yes\n\
+                    url = http://example.com/#top\n\
+                    \n\
+                    [users]\n\
+                    admins = ann, bo , cy\n\
+                    banned =\n";
+        Config::parse("demo.conf", text).unwrap()
+    }
+
+    #[test]
+    fn every_mistake_is_reported_with_its_position() {
+        let text = "name = demo\nport 8080\n[server\nhost = \"0.0.0.0\nport = 1\nport = 2\n\"key\" = 1\n[db]  extra\nurl = \"a\\qb\"\n";
+        let errors = Config::parse("broken.conf", text).unwrap_err();
+        assert_eq!(positions(&errors), vec![(2, 1), (3, 1), (4, 8), (6, 1), (7, 1), (8, 7), (9, 9)]);
+        let problems: Vec<&Problem> = errors.iter().map(|error| &error.problem).collect();
+        assert_eq!(problems, vec![
+            &Problem::MissingEquals,
+            &Problem::UnterminatedSection,
+            &Problem::UnterminatedString,
+            &Problem::DuplicateKey { key: "server.port".to_string(), first_line: 5 },
+            &Problem::InvalidKeyCharacter('"'),
+            &Problem::TrailingCharacters,
+            &Problem::InvalidEscape('q'),
+        ]);
+        assert_eq!(errors[0].to_string(), "broken.conf:2:1: expected `key = value`, `[section]` or a comment");
+    }
+
+    #[test]
+    fn load_reports_a_missing_file_once() {
+        let error = Config::load("no-such.conf").unwrap_err();
+        assert_eq!(error.to_string(), "couldn't read no-such.conf");
+        match error {
+            LoadError::Io { ref source, .. } => assert_eq!(source.kind(), ErrorKind::NotFound),
+            ref other => panic!("{:?}", other),
+        }
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn load_reads_a_file() {
+        let empty = Path::new(env!("CARGO_MANIFEST_DIR")).join("hello.txt");
+        assert_eq!(Config::load(&empty).unwrap().keys().count(), 0);
+
+        let path = env::temp_dir().join(format!("error_handling-10-load-{}.conf", std::process::id()));
+        fs::write(&path, "[a]\nb = 1\nc\n").unwrap();
+        let loaded = Config::load(&path);
+        fs::remove_file(&path).unwrap();
+        match loaded {
+            Err(LoadError::Invalid(ref errors)) => {
+                assert_eq!(positions(errors), vec![(3, 1)]);
+                assert_eq!(errors[0].origin, Origin::File { file: path.display().to_string(), line: 3, column: 1 });
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn getters_convert_the_text() {
+        let config = demo();
+        assert_eq!(config.keys().collect::<Vec<_>>(), vec![
+            "name", "server.host", "server.port", "server.request-timeout", "server.url", "server.verbose",
+            "users.admins", "users.banned",
+        ]);
+        assert_eq!(config.string("name"), Some("demo"));
+        assert_eq!(config.string("server.host"), Some("0.0.0.0"));
+        assert_eq!(config.string("server.url"), Some("http://example.com/#top"));
+        assert_eq!(config.int("server.port"), Ok(Some(8080)));
+        assert_eq!(config.int("server.backlog").map(|backlog| backlog.unwrap_or(128)), Ok(128));
+        assert_eq!(config.bool("server.verbose"), Ok(Some(true)));
+        assert_eq!(config.duration("server.request-timeout"), Ok(Some(Duration::from_secs(90))));
+        assert_eq!(config.list("users.admins"), Some(vec!["ann", "bo", "cy"]));
+        assert_eq!(config.list("users.banned"), Some(vec![]));
+        assert_eq!(config.list("users.missing"), None);
+        assert_eq!(config.origin("server.host"), Some(&Origin::File { file: "demo.conf".to_string(), line: 5, column: 8 }));
+    }
+
+    #[test]
+    fn wrong_types_are_reported_where_the_value_came_from() {
+        let config = demo();
+        for key in &["server.host", "server.verbose", "name"] {
+            match config.int(key) {
+                Err(ConfigError { problem: Problem::InvalidValue { key: ref found, .. }, ref origin }) => {
+                    assert_eq!(found, key);
+                    assert_eq!(Some(origin), config.origin(key));
+                }
+                other => panic!("{}: {:?}", key, other),
+            }
+        }
+        assert!(config.bool("name").is_err());
+        assert!(config.duration("server.port").is_err());
+    }
+
+    #[test]
+    fn durations_take_units() {
+        let timeouts = Config::parse("timeouts.conf", "a = 250ms\nb = 1h30m\nc = 2d\nd = 90\n").unwrap();
+        assert_eq!(timeouts.duration("a"), Ok(Some(Duration::from_millis(250))));
+        assert_eq!(timeouts.duration("b"), Ok(Some(Duration::from_secs(5400))));
+        assert_eq!(timeouts.duration("c"), Ok(Some(Duration::from_secs(172_800))));
+        assert!(timeouts.duration("d").is_err());
+    }
+
+    #[test]
+    fn environment_variables_override_the_file() {
+        let mut config = demo();
+        config.apply_vars("APP", vec![
+            ("APP_SERVER_PORT".to_string(), "9090".to_string()),
+            ("APP_SERVER_REQUEST_TIMEOUT".to_string(), "soon".to_string()),
+            ("APP_USERS_UNKNOWN".to_string(), "ignored".to_string()),
+            ("OTHER_NAME".to_string(), "ignored".to_string()),
+        ]);
+        assert_eq!(config.int("server.port"), Ok(Some(9090)));
+        assert_eq!(config.origin("server.port"), Some(&Origin::Env { variable: "APP_SERVER_PORT".to_string() }));
+        assert_eq!(config.string("users.unknown"), None);
+        assert_eq!(config.string("name"), Some("demo"));
+        match config.duration("server.request-timeout") {
+            Err(ConfigError { problem: Problem::InvalidValue { .. }, ref origin }) => {
+                assert_eq!(origin, &Origin::Env { variable: "APP_SERVER_REQUEST_TIMEOUT".to_string() });
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+}
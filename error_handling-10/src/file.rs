@@ -0,0 +1,203 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/*
+    Everything that can go wrong in `open_or_create`, each with the path it happened to, so the message can say
+    which file is meant. `Display` is one sentence for the person using the program; the underlying `io::Error`
+    is left to `source`, so code that prints the whole chain shows each detail once.
+*/
+#[derive(Debug)]
+pub enum FileError {
+    // The file wasn't there, and creating it failed too; `source` is the reason creating failed.
+    NotFoundCreateFailed { path: PathBuf, source: io::Error },
+    PermissionDenied { path: PathBuf, source: io::Error },
+    IsADirectory { path: PathBuf },
+    Other { path: PathBuf, source: io::Error },
+}
+
+impl FileError {
+    pub fn path(&self) -> &Path {
+        match *self {
+            FileError::NotFoundCreateFailed { ref path, .. }
+            | FileError::PermissionDenied { ref path, .. }
+            | FileError::IsADirectory { ref path }
+            | FileError::Other { ref path, .. } => path,
+        }
+    }
+
+    // Sorts an error from opening or creating `path` into the variant that explains it best.
+    fn from_io(path: &Path, source: io::Error) -> FileError {
+        let path = path.to_path_buf();
+        match source.kind() {
+            ErrorKind::PermissionDenied => FileError::PermissionDenied { path, source },
+            ErrorKind::IsADirectory => FileError::IsADirectory { path },
+            _ => FileError::Other { path, source },
+        }
+    }
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FileError::NotFoundCreateFailed { ref path, ref source } => {
+                write!(f, "{} doesn't exist and couldn't be created", path.display())?;
+                match source.kind() {
+                    ErrorKind::NotFound => write!(f, ", because the folder it belongs in doesn't exist"),
+                    ErrorKind::PermissionDenied => write!(f, ", because you don't have permission to create files there"),
+                    _ => Ok(()),
+                }
+            }
+            FileError::PermissionDenied { ref path, .. } => {
+                write!(f, "you don't have permission to open {}", path.display())
+            }
+            FileError::IsADirectory { ref path } => write!(f, "{} is a folder, not a file", path.display()),
+            FileError::Other { ref path, .. } => write!(f, "couldn't open {}", path.display()),
+        }
+    }
+}
+
+impl Error for FileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            FileError::NotFoundCreateFailed { ref source, .. }
+            | FileError::PermissionDenied { ref source, .. }
+            | FileError::Other { ref source, .. } => Some(source),
+            FileError::IsADirectory { .. } => None,
+        }
+    }
+}
+
+/*
+    Opens `path` with `options`, first creating it as an empty file if it doesn't exist yet.
+
+    The file is created on its own and then opened with `options`, so read-only options still work and whatever
+    access they ask for is what the returned `File` has. If another process creates the file in between,
+    it is simply opened.
+*/
+pub fn open_or_create<P: AsRef<Path>>(path: P, options: &OpenOptions) -> Result<File, FileError> {
+    let path = path.as_ref();
+    let file = match options.open(path) {
+        Ok(file) => file,
+        Err(ref error) if error.kind() == ErrorKind::NotFound => {
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(_) => {}
+                Err(ref error) if error.kind() == ErrorKind::AlreadyExists => {}
+                Err(source) => return Err(FileError::NotFoundCreateFailed { path: path.to_path_buf(), source }),
+            }
+            options.open(path).map_err(|error| FileError::from_io(path, error))?
+        }
+        Err(error) => return Err(FileError::from_io(path, error)),
+    };
+
+    // Opening a directory for reading succeeds on some systems, but it can't be used as a file.
+    match file.metadata() {
+        Ok(ref metadata) if metadata.is_dir() => Err(FileError::IsADirectory { path: path.to_path_buf() }),
+        Ok(_) => Ok(file),
+        Err(error) => Err(FileError::from_io(path, error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::process;
+
+    // A fresh, empty directory for one test, removed again when it goes out of scope.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let path = env::temp_dir().join(format!("error_handling-10-{}-{}", name, process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn read_write() -> OpenOptions {
+        let mut options = OpenOptions::new();
+        options.read(true).write(true);
+        options
+    }
+
+    #[test]
+    fn opens_an_existing_file_without_truncating_it() {
+        let dir = TempDir::new("existing");
+        let path = dir.0.join("notes.txt");
+        fs::write(&path, "kept").unwrap();
+
+        let mut text = String::new();
+        open_or_create(&path, &OpenOptions::new().read(true).clone()).unwrap().read_to_string(&mut text).unwrap();
+        assert_eq!(text, "kept");
+    }
+
+    #[test]
+    fn creates_a_missing_file_and_opens_it_with_the_options_given() {
+        let dir = TempDir::new("missing");
+        let path = dir.0.join("new.txt");
+
+        open_or_create(&path, &read_write()).unwrap().write_all(b"hello").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello");
+        // Read-only options still get a file created for them.
+        let other = dir.0.join("read-only.txt");
+        assert!(open_or_create(&other, &OpenOptions::new().read(true).clone()).is_ok());
+        assert!(other.is_file());
+    }
+
+    #[test]
+    fn missing_folder_reports_not_found_then_create_failed() {
+        let dir = TempDir::new("no-folder");
+        let path = dir.0.join("absent").join("file.txt");
+
+        let error = open_or_create(&path, &read_write()).unwrap_err();
+        match error {
+            FileError::NotFoundCreateFailed { ref source, .. } => assert_eq!(source.kind(), ErrorKind::NotFound),
+            ref other => panic!("{:?}", other),
+        }
+        assert_eq!(error.path(), path.as_path());
+        assert_eq!(error.to_string(), format!("{} doesn't exist and couldn't be created, because the folder it belongs in doesn't exist", path.display()));
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn directory_is_reported_as_such() {
+        let dir = TempDir::new("directory");
+        for options in &[OpenOptions::new().read(true).clone(), read_write()] {
+            let error = open_or_create(&dir.0, options).unwrap_err();
+            match error {
+                FileError::IsADirectory { ref path } => assert_eq!(path, &dir.0),
+                ref other => panic!("{:?}", other),
+            }
+            assert!(error.source().is_none());
+        }
+    }
+
+    #[test]
+    fn display_leaves_the_io_detail_to_source() {
+        let path = Path::new("data.txt");
+        let denied = FileError::from_io(path, io::Error::new(ErrorKind::PermissionDenied, "denied by test"));
+        assert_eq!(denied.to_string(), "you don't have permission to open data.txt");
+        assert_eq!(denied.source().unwrap().to_string(), "denied by test");
+
+        let other = FileError::from_io(path, io::Error::other("disk on fire"));
+        assert_eq!(other.to_string(), "couldn't open data.txt");
+        assert_eq!(other.source().unwrap().to_string(), "disk on fire");
+
+        let source = io::Error::other("quota exceeded");
+        let failed = FileError::NotFoundCreateFailed { path: path.to_path_buf(), source };
+        assert_eq!(failed.to_string(), "data.txt doesn't exist and couldn't be created");
+        assert!(!failed.to_string().contains("quota"));
+    }
+}
@@ -1,5 +1,12 @@
-use std::fs::File;
-use std::io::ErrorKind;
+mod config;
+mod file;
+
+use std::env;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+
+use config::Config;
+use file::{open_or_create, FileError};
 
 /*
     Rust groups errors into two major categories: recoverable and unrecoverable errors. For a recoverable error, such as a file not found error,
@@ -11,6 +18,17 @@ use std::io::ErrorKind;
     when the program encounters an unrecoverable error. This chapter covers calling `panic!` first and then talks about returning `Result<T, E>` values.
     Additionally, we’ll explore considerations when deciding whether to try to recover from an error or to stop execution.
 */
+
+// Prints `error` followed by the chain of errors that caused it, most specific last.
+fn report(error: &dyn Error) {
+    println!("error: {}", error);
+    let mut cause = error.source();
+    while let Some(error) = cause {
+        println!("  caused by: {}", error);
+        cause = error.source();
+    }
+}
+
 fn main() {
     //  A backtrace is a list of all the functions that have been called to get to this point. Backtraces in Rust work as they do in other languages:
     // the key to reading the backtrace is to start from the top and read until you see files you wrote. That’s the spot where the problem originated.
@@ -37,15 +55,23 @@ fn main() {
             },
         };
     */
-    let _f = File::open("some_text.txt").map_err(|error| {
-        if error.kind() == ErrorKind::NotFound {
-            File::create("some_text.txt").unwrap_or_else(|error| {
-                panic!("Tried to create file but there was a problem: {:?}", error);
-            })
-        } else {
-            panic!("There was a problem opening the file: {:?}", error);
+    /*
+        Panicking is a poor answer when the file can't be created either: the person running the program gets a debug dump
+        instead of a reason. `open_or_create` returns a `FileError` saying what went wrong with which file instead.
+    */
+    let mut options = OpenOptions::new();
+    options.read(true).append(true);
+    let scratch = env::temp_dir().join("error_handling-10-some_text.txt");
+    let _ = fs::remove_file(&scratch);
+    let missing_folder = env::temp_dir().join("error_handling-10-no-such-folder").join("some_text.txt");
+    for path in &[scratch.clone(), env::temp_dir(), missing_folder] {
+        match open_or_create(path, &options) {
+            Ok(_) => println!("opened {}", path.display()),
+            Err(ref error @ FileError::IsADirectory { .. }) => println!("skipped {}, it's a folder", error.path().display()),
+            Err(error) => report(&error),
         }
-    });
+    }
+    let _ = fs::remove_file(&scratch);
 
     /*
         Using match works well enough, but it can be a bit verbose and doesn’t always communicate intent well.
@@ -66,4 +92,62 @@ fn main() {
         let f = File::open("hello.txt").expect("Failed to open hello.txt");
         ```
     */
+
+    /*
+        Reading a file and finding it is wrong is the other common case. The config loader reports every mistake it finds,
+        each with the file, line and column, rather than giving up at the first one.
+    */
+    let broken = env::temp_dir().join("error_handling-10-broken.conf");
+    fs::write(&broken, "name = demo\nport 8080\n[server\nhost = \"0.0.0.0\nport = 1\nport = 2\n\"key\" = 1\n[db]  extra\nurl = \"a\\qb\"\n").unwrap();
+    match Config::load(&broken) {
+        Ok(_) => println!("{} loaded", broken.display()),
+        Err(error) => println!("{}", error),
+    }
+    let _ = fs::remove_file(&broken);
+    if let Err(error) = Config::load("no-such.conf") {
+        report(&error);
+    }
+
+    let text = "# settings for the demo\n\
+                name = demo\n\
+                \n\
+                [server]   ; where to listen\n\
+                host = \"0.0.0.0\"\n\
+                port = 8_080\n\
+                request-timeout = 1m30s\n\
+                verbose = yes\n\
+                url = http://example.com/#top\n\
+                \n\
+                [users]\n\
+                admins = ann, bo , cy\n\
+                banned =\n";
+    let mut config = match Config::parse("demo.conf", text) {
+        Ok(config) => config,
+        Err(errors) => {
+            for error in &errors {
+                println!("{}", error);
+            }
+            return;
+        }
+    };
+    config.apply_env("ERROR_HANDLING_10");
+    config.apply_vars("APP", vec![
+        ("APP_SERVER_PORT".to_string(), "9090".to_string()),
+        ("APP_SERVER_REQUEST_TIMEOUT".to_string(), "soon".to_string()),
+    ]);
+    println!("{:?}", config.keys().collect::<Vec<_>>());
+    println!("name {:?}, url {:?}", config.string("name"), config.string("server.url"));
+    println!("backlog {:?}", config.int("server.backlog").map(|backlog| backlog.unwrap_or(128)));
+    println!("verbose {:?}, admins {:?}", config.bool("server.verbose"), config.list("users.admins"));
+    for key in &["server.port", "server.host"] {
+        if let Some(origin) = config.origin(key) {
+            println!("{} = {:?} from {}", key, config.string(key), origin);
+        }
+    }
+    for key in &["server.request-timeout", "server.host", "server.verbose", "name"] {
+        match config.duration(key) {
+            Ok(duration) => println!("{} is {:?}", key, duration),
+            Err(error) => println!("{}", error),
+        }
+    }
 }
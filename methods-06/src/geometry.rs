@@ -0,0 +1,234 @@
+use std::cmp;
+use std::fmt;
+
+use Rectangle;
+
+/*
+    A `Rectangle` now has a position as well as a size. `x` and `y` are its top-left corner, with `y` growing downwards
+    as on a screen, and it covers the half-open ranges `x..x + width` and `y..y + height`. With half-open ranges,
+    two rectangles that only touch along an edge don't overlap, and cutting a rectangle in two leaves no gap between
+    the pieces and no pixel in both.
+
+    Coordinates are `u32`, so a rectangle can't reach past `u32::MAX`. One that is placed so it would is cut off there:
+    `right` and `bottom` saturate instead of overflowing, and the methods below that look at or cut up the covered area
+    work on the cut-off rectangle. Only `width`, `height` and the size-only checks (`can_fit`, `rotated`) still see
+    the size it was given.
+*/
+impl Rectangle {
+    pub fn placed(x: u32, y: u32, width: u32, height: u32) -> Rectangle {
+        Rectangle { x, y, width, height }
+    }
+
+    pub fn left(&self) -> u32 {
+        self.x
+    }
+
+    pub fn top(&self) -> u32 {
+        self.y
+    }
+
+    pub fn right(&self) -> u32 {
+        self.x.saturating_add(self.width)
+    }
+
+    pub fn bottom(&self) -> u32 {
+        self.y.saturating_add(self.height)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    // The part of the rectangle that lies inside the coordinate space.
+    fn clipped(&self) -> Rectangle {
+        Rectangle::placed(self.x, self.y, self.right() - self.x, self.bottom() - self.y)
+    }
+
+    pub fn moved_to(&self, x: u32, y: u32) -> Rectangle {
+        Rectangle { x, y, ..*self }
+    }
+
+    // The same rectangle turned by 90°, keeping its top-left corner where it is.
+    pub fn rotated(&self) -> Rectangle {
+        Rectangle { width: self.height, height: self.width, ..*self }
+    }
+
+    pub fn contains_point(&self, x: u32, y: u32) -> bool {
+        self.x <= x && x < self.right() && self.y <= y && y < self.bottom()
+    }
+
+    // Whether `other`, where it is, lies entirely inside `self`.
+    pub fn contains(&self, other: &Rectangle) -> bool {
+        self.x <= other.x && other.right() <= self.right() && self.y <= other.y && other.bottom() <= self.bottom()
+    }
+
+    // Whether the two share any area; rectangles that only touch along an edge or at a corner don't.
+    pub fn intersects(&self, other: &Rectangle) -> bool {
+        self.x < other.right() && other.x < self.right() && self.y < other.bottom() && other.y < self.bottom()
+    }
+
+    pub fn intersection(&self, other: &Rectangle) -> Option<Rectangle> {
+        if !self.intersects(other) {
+            return None;
+        }
+        let x = cmp::max(self.x, other.x);
+        let y = cmp::max(self.y, other.y);
+        let right = cmp::min(self.right(), other.right());
+        let bottom = cmp::min(self.bottom(), other.bottom());
+        Some(Rectangle::placed(x, y, right - x, bottom - y))
+    }
+
+    // The smallest rectangle containing both, which also covers whatever lies between them.
+    pub fn union(&self, other: &Rectangle) -> Rectangle {
+        let x = cmp::min(self.x, other.x);
+        let y = cmp::min(self.y, other.y);
+        let right = cmp::max(self.right(), other.right());
+        let bottom = cmp::max(self.bottom(), other.bottom());
+        Rectangle::placed(x, y, right - x, bottom - y)
+    }
+
+    /*
+        `can_hold` asks whether `other` fits with room to spare on both sides. Packing wants to know whether it fits at all,
+        so `can_fit` also accepts a rectangle of exactly the same width or height. Positions are ignored by both.
+    */
+    pub fn can_fit(&self, other: &Rectangle) -> bool {
+        self.width >= other.width && self.height >= other.height
+    }
+
+    // Whether `other` fits either as it is or turned by 90°.
+    pub fn can_fit_rotated(&self, other: &Rectangle) -> bool {
+        self.can_fit(other) || self.can_fit(&other.rotated())
+    }
+
+    // Cuts the rectangle with a vertical line `at` units from its left edge, or returns `None` if the line misses it.
+    pub fn split_vertical(&self, at: u32) -> Option<(Rectangle, Rectangle)> {
+        let this = self.clipped();
+        if at == 0 || at >= this.width {
+            return None;
+        }
+        Some((
+            Rectangle { width: at, ..this },
+            Rectangle { x: this.x + at, width: this.width - at, ..this },
+        ))
+    }
+
+    // Cuts the rectangle with a horizontal line `at` units below its top edge, or returns `None` if the line misses it.
+    pub fn split_horizontal(&self, at: u32) -> Option<(Rectangle, Rectangle)> {
+        let this = self.clipped();
+        if at == 0 || at >= this.height {
+            return None;
+        }
+        Some((
+            Rectangle { height: at, ..this },
+            Rectangle { y: this.y + at, height: this.height - at, ..this },
+        ))
+    }
+
+    /*
+        What is left of `self` once `other` is cut out of it, as at most four rectangles that don't overlap:
+
+            +-----------------+
+            |       top       |
+            +-----+-----+-----+
+            |left |other|right|
+            +-----+-----+-----+
+            |     bottom      |
+            +-----------------+
+
+        The top and bottom pieces take the full width, so the pieces are never split more than they have to be.
+    */
+    pub fn subtract(&self, other: &Rectangle) -> Vec<Rectangle> {
+        let this = self.clipped();
+        let cut = match this.intersection(other) {
+            Some(cut) => cut,
+            None => return if this.is_empty() { Vec::new() } else { vec![this] },
+        };
+
+        let pieces = [
+            Rectangle::placed(this.x, this.y, this.width, cut.y - this.y),
+            Rectangle::placed(this.x, cut.bottom(), this.width, this.bottom() - cut.bottom()),
+            Rectangle::placed(this.x, cut.y, cut.x - this.x, cut.height),
+            Rectangle::placed(cut.right(), cut.y, this.right() - cut.right(), cut.height),
+        ];
+        pieces.iter().filter(|piece| !piece.is_empty()).cloned().collect()
+    }
+}
+
+impl fmt::Display for Rectangle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{} at ({}, {})", self.width, self.height, self.x, self.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edges_saturate_at_the_end_of_the_coordinate_space() {
+        let max = u32::MAX;
+        let edge = Rectangle::placed(max - 10, max - 10, 100, 100);
+        assert_eq!((edge.right(), edge.bottom()), (max, max));
+        assert!(edge.contains_point(max - 1, max - 1));
+        assert!(!edge.contains_point(max, max));
+
+        let other = Rectangle::placed(max - 20, max - 5, 50, 50);
+        assert_eq!(edge.intersection(&other), Some(Rectangle::placed(max - 10, max - 5, 10, 5)));
+        assert_eq!(edge.union(&other), Rectangle::placed(max - 20, max - 10, 20, 10));
+        assert!(Rectangle::placed(0, 0, max, max).contains(&edge));
+        assert_eq!(edge.subtract(&other), vec![Rectangle::placed(max - 10, max - 10, 10, 5)]);
+        assert_eq!(edge.subtract(&Rectangle::placed(0, 0, 1, 1)), vec![Rectangle::placed(max - 10, max - 10, 10, 10)]);
+        assert!(Rectangle::placed(max, 0, 5, 5).subtract(&other).is_empty());
+    }
+
+    #[test]
+    fn split_lines_past_the_end_miss() {
+        let edge = Rectangle::placed(u32::MAX - 10, 0, 100, 100);
+        assert_eq!(
+            edge.split_vertical(5),
+            Some((Rectangle::placed(u32::MAX - 10, 0, 5, 100), Rectangle::placed(u32::MAX - 5, 0, 5, 100)))
+        );
+        assert_eq!(edge.split_vertical(10), None);
+        assert_eq!(edge.split_vertical(50), None);
+        let (top, bottom) = edge.split_horizontal(50).unwrap();
+        assert_eq!((top.width, bottom.width), (10, 10));
+        assert_eq!(Rectangle::placed(0, u32::MAX - 10, 100, 100).split_horizontal(20), None);
+    }
+
+    #[test]
+    fn area_does_not_overflow() {
+        let huge = Rectangle::new(u32::MAX, u32::MAX);
+        assert_eq!(huge.area(), u64::from(u32::MAX) * u64::from(u32::MAX));
+        assert_eq!(Rectangle::new(70_000, 70_000).area(), 4_900_000_000);
+    }
+
+    #[test]
+    fn subtracting_leaves_pieces_that_cover_the_rest_exactly() {
+        let window = Rectangle::placed(0, 0, 100, 60);
+        let dialog = Rectangle::placed(20, 10, 50, 30);
+        let free = window.subtract(&dialog);
+        assert_eq!(free, vec![
+            Rectangle::placed(0, 0, 100, 10),
+            Rectangle::placed(0, 40, 100, 20),
+            Rectangle::placed(0, 10, 20, 30),
+            Rectangle::placed(70, 10, 30, 30),
+        ]);
+        let free_area: u64 = free.iter().map(Rectangle::area).sum();
+        assert_eq!(free_area + dialog.area(), window.area());
+        assert!(free.iter().all(|piece| window.contains(piece) && !piece.intersects(&dialog)));
+        for (i, piece) in free.iter().enumerate() {
+            assert!(free[..i].iter().all(|other| !other.intersects(piece)));
+        }
+    }
+
+    #[test]
+    fn subtracting_at_the_edges() {
+        let window = Rectangle::placed(0, 0, 100, 60);
+        let tooltip = Rectangle::placed(60, 35, 60, 10);
+        assert_eq!(tooltip.subtract(&window), vec![Rectangle::placed(100, 35, 20, 10)]);
+        assert!(window.subtract(&window).is_empty());
+        assert!(window.subtract(&Rectangle::placed(0, 0, 200, 200)).is_empty());
+        assert_eq!(window.subtract(&Rectangle::placed(100, 0, 10, 10)), vec![window]);
+        assert!(Rectangle::default().subtract(&window).is_empty());
+    }
+}
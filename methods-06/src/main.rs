@@ -1,4 +1,20 @@
+mod geometry;
+mod packing;
+mod quadtree;
+mod render;
+mod shape;
+
+use std::time::Instant;
+
+use packing::{Heuristic, Packer};
+use quadtree::QuadTree;
+use render::{Canvas, SvgOptions};
+use shape::{Circle, Point, Polygon, Scene, Shape, Square, Triangle};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 struct Rectangle {
+    x: u32,
+    y: u32,
     width: u32,
     height: u32,
 }
@@ -10,24 +26,190 @@ struct Rectangle {
     which represents the instance of the struct the method is being called on.
 */
 impl Rectangle {
-    fn _new(width: u32, height: u32) -> Rectangle {
-        Rectangle { width, height }
+    fn new(width: u32, height: u32) -> Rectangle {
+        Rectangle { x: 0, y: 0, width, height }
     }
 
-    fn _area(&self) -> u32 {
-        self.width * self.height
+    // In `u64`, since two `u32` sides can multiply to more than a `u32` holds.
+    fn area(&self) -> u64 {
+        u64::from(self.width) * u64::from(self.height)
     }
 
-    fn _can_hold(&self, other: &Rectangle) -> bool {
+    fn can_hold(&self, other: &Rectangle) -> bool {
         self.width > other.width && self.height > other.height
     }
 }
 
 fn main() {
-    let rect1 = Rectangle { width: 30, height: 50 };
+    let rect1 = Rectangle::new(30, 50);
 
     println!(
         "The area of the rectangle is {} square pixels.",
-        rect1._area()
+        rect1.area()
     );
-}
\ No newline at end of file
+
+    /*
+        Methods can be spread over several `impl` blocks, even in other modules. The geometry module adds a position
+        and the operations layout code needs: overlap tests, containment, and cutting rectangles into pieces.
+    */
+    let window = Rectangle::placed(0, 0, 100, 60);
+    let dialog = Rectangle::placed(20, 10, 50, 30);
+    let tooltip = Rectangle::placed(60, 35, 60, 10);
+
+    println!("{} holds {}: {}, contains it: {}", window, dialog, window.can_hold(&dialog), window.contains(&dialog));
+    println!("{} intersects {}: {:?}", dialog, tooltip, dialog.intersection(&tooltip));
+    println!("union: {}", dialog.union(&tooltip));
+    println!("{:?}", dialog.intersection(&Rectangle::placed(70, 10, 5, 5)));
+    println!("(20, 10) in dialog: {}, (70, 40) in dialog: {}", dialog.contains_point(20, 10), dialog.contains_point(70, 40));
+
+    let slot = Rectangle::new(30, 50);
+    let label = Rectangle::new(50, 30);
+    println!("{} can hold itself: {}, can fit itself: {}", slot, slot.can_hold(&slot), slot.can_fit(&slot));
+    println!("{} fits {}: {}, turned: {}", label, slot, slot.can_fit(&label), slot.can_fit_rotated(&label));
+    println!("{}", label.rotated().moved_to(5, 5));
+
+    println!("{:?}", window.split_vertical(25));
+    println!("{:?} {:?}", window.split_horizontal(60), Rectangle::default().is_empty());
+    let free = window.subtract(&dialog);
+    println!("window minus dialog: {}", free.iter().map(|piece| piece.to_string()).collect::<Vec<_>>().join(", "));
+    let free_area: u64 = free.iter().map(Rectangle::area).sum();
+    println!("free area {} + dialog {} = window {}", free_area, dialog.area(), window.area());
+    println!("{:?}", tooltip.subtract(&window));
+    println!("{}, {}, {}, {}", tooltip.left(), tooltip.top(), tooltip.right(), tooltip.bottom());
+
+    /*
+        Packing puts many rectangles into as few fixed-size bins as possible. The packer only looks at sizes,
+        and hands back a positioned copy of each item that fit, plus the ones that didn't and why.
+    */
+    let labels = vec![Rectangle::new(40, 20); 7];
+    let packing = Packer::new(100, 50).heuristic(Heuristic::Skyline).pack(&labels);
+    print!("{}", packing);
+
+    let mut seed: u32 = 12345;
+    let mut random = |limit: u32| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (seed >> 16) % limit + 1
+    };
+    let mut sprites: Vec<Rectangle> = (0..200).map(|_| Rectangle::new(random(64), random(32))).collect();
+    sprites.push(Rectangle::new(300, 10));
+    sprites.push(Rectangle::new(0, 10));
+
+    for &heuristic in &[Heuristic::Skyline, Heuristic::MaxRects] {
+        for &allow_rotation in &[false, true] {
+            let packing = Packer::new(256, 256).heuristic(heuristic).allow_rotation(allow_rotation).pack(&sprites);
+            println!("{:?}, rotation {}: {} bin(s), {:.1}% used, unfit {:?}",
+                     heuristic, allow_rotation, packing.bins.len(), packing.utilization() * 100.0, packing.unfit);
+        }
+    }
+
+    let limited = Packer::new(128, 128).allow_rotation(true).max_bins(1).pack(&sprites);
+    println!("one 128x128 bin: {} placed, {} unfit, {:.1}% used",
+             limited.placed_count(), limited.unfit.len(), limited.bin_utilization(0) * 100.0);
+    let first = limited.bins[0].placements[0];
+    println!("largest sprite #{} went to {} (rotated: {})", first.index, first.rect, first.rotated);
+
+    /*
+        Hit-testing by checking every rectangle gets slow once there are tens of thousands of them.
+        A quadtree only checks the ones near the point, so it answers the same questions in a fraction of the time.
+    */
+    let screen = Rectangle::new(4096, 4096);
+    let regions: Vec<Rectangle> = (0..20_000)
+        .map(|_| Rectangle::placed(random(4000) - 1, random(4000) - 1, random(96), random(96)))
+        .collect();
+    let mut index = QuadTree::new(screen);
+    let ids: Vec<_> = regions.iter().enumerate().map(|(i, &region)| index.insert(region, i)).collect();
+    let probes: Vec<(u32, u32)> = (0..1_000).map(|_| (random(4096) - 1, random(4096) - 1)).collect();
+
+    let started = Instant::now();
+    let linear_hits: Vec<Vec<usize>> = probes.iter()
+        .map(|&(x, y)| (0..regions.len()).filter(|&i| regions[i].contains_point(x, y)).collect())
+        .collect();
+    let linear_time = started.elapsed();
+
+    let started = Instant::now();
+    let tree_hits: Vec<Vec<usize>> = probes.iter()
+        .map(|&(x, y)| index.query_point(x, y).into_iter().map(|(_, &i)| i).collect())
+        .collect();
+    let tree_time = started.elapsed();
+
+    let hits = |found: &[Vec<usize>]| found.iter().map(Vec::len).sum::<usize>();
+    println!("{} point queries over {} regions: {} hits by linear scan in {:?}, {} by quadtree in {:?} ({:.0}x faster)",
+             probes.len(), index.len(), hits(&linear_hits), linear_time, hits(&tree_hits), tree_time,
+             linear_time.as_secs_f64() / tree_time.as_secs_f64());
+
+    let distance = |region: &Rectangle, x: u32, y: u32| {
+        let dx = if x < region.left() { region.left() - x } else { x.saturating_sub(region.right() - 1) };
+        let dy = if y < region.top() { region.top() - y } else { y.saturating_sub(region.bottom() - 1) };
+        u64::from(dx) * u64::from(dx) + u64::from(dy) * u64::from(dy)
+    };
+    let started = Instant::now();
+    let linear_nearest: Vec<usize> = probes.iter()
+        .map(|&(x, y)| (0..regions.len()).min_by_key(|&i| (distance(&regions[i], x, y), i)).unwrap())
+        .collect();
+    let linear_time = started.elapsed();
+    let started = Instant::now();
+    let tree_nearest: Vec<usize> = probes.iter().map(|&(x, y)| *index.nearest(x, y).unwrap().1).collect();
+    let tree_time = started.elapsed();
+    println!("nearest neighbour: linear scan {:?}, quadtree {:?}, same answers: {}",
+             linear_time, tree_time, tree_nearest == linear_nearest);
+
+    // Remove every other region and add one far outside the tree's bounds; range queries still find what's left.
+    for id in ids.iter().step_by(2) {
+        index.remove(*id);
+    }
+    println!("removing twice: {:?}", index.remove(ids[0]));
+    let outlier = index.insert(Rectangle::placed(5000, 5000, 10, 10), regions.len());
+    let range = Rectangle::placed(1000, 1000, 600, 400);
+    let in_range = index.query_range(&range);
+    println!("{} left, {} in {}, outlier {:?} nearest to (6000, 6000): {:?}",
+             index.len(), in_range.len(), range, index.get(outlier), index.nearest(6000, 6000).map(|(id, _)| id == outlier));
+    println!("{} items iterated, empty: {}", index.iter().count(), QuadTree::<()>::new(screen).is_empty());
+
+    // Looking at a layout beats reading its coordinates. Overlaps, here the tooltip hanging over the dialog, stand out in red or `#`.
+    let mut canvas = Canvas::new();
+    canvas.add(window, "window").add(dialog, "dialog").add_colored(tooltip, "<tooltip>", "#333");
+    print!("{}", canvas.to_ascii(48));
+    print!("{}", canvas.to_svg(&SvgOptions { scale: 2.0, grid: Some(20), ..SvgOptions::default() }));
+
+    let mut sheet = Canvas::new();
+    let packing = Packer::new(256, 128).allow_rotation(true).pack(&sprites[..40]);
+    for placement in &packing.bins[0].placements {
+        sheet.add(placement.rect, &format!("sprite {}", placement.index));
+    }
+    let art = sheet.to_ascii(64);
+    print!("{}", art.lines().take(19).map(|line| format!("{}\n", line)).collect::<String>());
+    println!("overlaps in packed sheet: {}", sheet.overlaps().len());
+
+    /*
+        A trait describes behaviour many types can share. Once `Rectangle`, `Circle` and the rest all implement `Shape`,
+        a `Vec<Box<dyn Shape>>` can hold any mix of them, and each call goes to the right type's method at run time.
+        `Rectangle` keeps its own integer `area`; the trait's `f64` one is reached through the trait.
+    */
+    println!("{} = {}, perimeter {}", rect1.area(), Shape::area(&rect1), rect1.perimeter());
+
+    let l_shape = Polygon::new(vec![
+        Point::new(0.0, 0.0), Point::new(4.0, 0.0), Point::new(4.0, 1.0),
+        Point::new(1.0, 1.0), Point::new(1.0, 3.0), Point::new(0.0, 3.0),
+    ]).unwrap();
+    println!("L: area {}, perimeter {}, {:?}", l_shape.area(), l_shape.perimeter(), l_shape.bounding_box());
+    println!("(0.5, 2) in L: {}, (2, 2) in L: {}, {} corners",
+             l_shape.contains_point(Point::new(0.5, 2.0)), l_shape.contains_point(Point::new(2.0, 2.0)), l_shape.points().len());
+    println!("{:?}", Polygon::new(vec![Point::new(0.0, 0.0), Point::new(1.0, 1.0)]));
+
+    let mut scene = Scene::new();
+    scene
+        .add(Rectangle::placed(0, 0, 4, 2))
+        .add(Circle::new(Point::new(5.0, 5.0), 1.0))
+        .add(Square::new(2, 2, 3))
+        .add(Triangle::new(Point::new(0.0, 0.0), Point::new(4.0, 0.0), Point::new(0.0, 3.0)))
+        .add(l_shape);
+    scene.sort_by_area();
+    for shape in scene.shapes() {
+        println!("{:>6.2} {:>6.2}  {:?}", shape.area(), shape.perimeter(), shape);
+    }
+    println!("total area {:.2}, bounding box {:?}", scene.total_area(), scene.bounding_box());
+    println!("at (0.5, 0.5): {:?}", scene.shapes_at(Point::new(0.5, 0.5)));
+    println!("at (5.5, 5.5): {} shape(s), {:?}", scene.shapes_at(Point::new(5.5, 5.5)).len(), Square::new(1, 1, 2).to_rectangle());
+    let bounds = Circle::new(Point::default(), 2.0).bounding_box();
+    println!("{} x {}", bounds.width(), bounds.height());
+}
@@ -0,0 +1,506 @@
+use std::cmp::{self, Reverse};
+use std::fmt;
+
+use Rectangle;
+
+/*
+    Bin packing places rectangles side by side inside fixed-size containers ("bins") without overlaps,
+    trying to use as few bins and waste as little space as possible. Doing that perfectly is NP-hard,
+    so packers use heuristics that are fast and usually close:
+
+    - Skyline keeps only the outline of the top edge of what has been placed so far, like a city skyline seen from
+      below (`y` grows downwards here), and puts each rectangle where it ends up highest. It is fast and
+      works well for items of similar height, such as text labels, but can't fill holes left under the skyline.
+    - MaxRects keeps every maximal free rectangle, overlapping ones included, and picks the free rectangle the item
+      fits most snugly into. It is slower but packs mixed sizes, such as sprites, noticeably tighter.
+
+    Either way, items are packed largest first, since small items fill the gaps large ones leave but not the other way round.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Heuristic {
+    Skyline,
+    MaxRects,
+}
+
+#[derive(Clone, Debug)]
+pub struct Packer {
+    bin: Rectangle,
+    heuristic: Heuristic,
+    allow_rotation: bool,
+    max_bins: Option<usize>,
+}
+
+// Where the item at `index` of the input ended up. `rect` has the item's size, or its size turned by 90° if `rotated`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Placement {
+    pub index: usize,
+    pub rect: Rectangle,
+    pub rotated: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnfitReason {
+    Empty,
+    // Larger than a whole bin, even turned round if rotation is allowed.
+    TooLarge,
+    // Would fit into an empty bin, but every bin allowed is already too full.
+    OutOfBins,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Unfit {
+    pub index: usize,
+    pub reason: UnfitReason,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Bin {
+    pub placements: Vec<Placement>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Packing {
+    pub bin_size: Rectangle,
+    pub bins: Vec<Bin>,
+    pub unfit: Vec<Unfit>,
+}
+
+impl Packer {
+    pub fn new(width: u32, height: u32) -> Packer {
+        Packer {
+            bin: Rectangle::new(width, height),
+            heuristic: Heuristic::MaxRects,
+            allow_rotation: false,
+            max_bins: None,
+        }
+    }
+
+    pub fn heuristic(mut self, heuristic: Heuristic) -> Packer {
+        self.heuristic = heuristic;
+        self
+    }
+
+    pub fn allow_rotation(mut self, allow_rotation: bool) -> Packer {
+        self.allow_rotation = allow_rotation;
+        self
+    }
+
+    // Without a limit, a new bin is opened whenever an item fits in none of the existing ones.
+    pub fn max_bins(mut self, max_bins: usize) -> Packer {
+        self.max_bins = Some(max_bins);
+        self
+    }
+
+    // Packs the sizes of `items`; their positions are ignored.
+    pub fn pack(&self, items: &[Rectangle]) -> Packing {
+        let mut order: Vec<usize> = (0..items.len()).collect();
+        order.sort_by_key(|&index| {
+            let item = items[index];
+            (Reverse(cmp::max(item.width, item.height)), Reverse(u64::from(item.width) * u64::from(item.height)))
+        });
+
+        let mut bins: Vec<(Bin, Free)> = Vec::new();
+        let mut unfit = Vec::new();
+
+        for index in order {
+            let item = items[index];
+            if item.is_empty() {
+                unfit.push(Unfit { index, reason: UnfitReason::Empty });
+                continue;
+            }
+            let fits = if self.allow_rotation { self.bin.can_fit_rotated(&item) } else { self.bin.can_fit(&item) };
+            if !fits {
+                unfit.push(Unfit { index, reason: UnfitReason::TooLarge });
+                continue;
+            }
+
+            let placed = bins.iter_mut().any(|&mut (ref mut bin, ref mut free)| {
+                match free.insert(item.width, item.height, self.allow_rotation) {
+                    Some((rect, rotated)) => {
+                        bin.placements.push(Placement { index, rect, rotated });
+                        true
+                    }
+                    None => false,
+                }
+            });
+            if placed {
+                continue;
+            }
+
+            if self.max_bins.is_some_and(|max| bins.len() >= max) {
+                unfit.push(Unfit { index, reason: UnfitReason::OutOfBins });
+                continue;
+            }
+            let mut free = Free::new(self.heuristic, self.bin.width, self.bin.height);
+            // The item fits an empty bin, checked above, so this can't fail.
+            let (rect, rotated) = free.insert(item.width, item.height, self.allow_rotation).unwrap();
+            bins.push((Bin { placements: vec![Placement { index, rect, rotated }] }, free));
+        }
+
+        unfit.sort_by_key(|unfit| unfit.index);
+        Packing { bin_size: self.bin, bins: bins.into_iter().map(|(bin, _)| bin).collect(), unfit }
+    }
+}
+
+impl Bin {
+    // `Rectangle::area` is a `u64`, so this can't overflow for any bin a `u32` can describe.
+    pub fn used_area(&self) -> u64 {
+        self.placements.iter().map(|placement| placement.rect.area()).sum()
+    }
+}
+
+impl Packing {
+    fn bin_area(&self) -> u64 {
+        self.bin_size.area()
+    }
+
+    // The share of bin `index` covered by items, from 0 to 1.
+    pub fn bin_utilization(&self, index: usize) -> f64 {
+        self.bins[index].used_area() as f64 / self.bin_area() as f64
+    }
+
+    // The share of all bins together covered by items.
+    pub fn utilization(&self) -> f64 {
+        if self.bins.is_empty() {
+            return 0.0;
+        }
+        let used: u64 = self.bins.iter().map(Bin::used_area).sum();
+        used as f64 / (self.bin_area() * self.bins.len() as u64) as f64
+    }
+
+    pub fn placed_count(&self) -> usize {
+        self.bins.iter().map(|bin| bin.placements.len()).sum()
+    }
+}
+
+impl fmt::Display for Packing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} item(s) in {} bin(s) of {}x{}, {:.1}% used",
+                 self.placed_count(), self.bins.len(), self.bin_size.width, self.bin_size.height, self.utilization() * 100.0)?;
+        for (i, bin) in self.bins.iter().enumerate() {
+            writeln!(f, "  bin {}: {} item(s), {:.1}% used", i, bin.placements.len(), self.bin_utilization(i) * 100.0)?;
+        }
+        for unfit in &self.unfit {
+            writeln!(f, "  item {} did not fit: {:?}", unfit.index, unfit.reason)?;
+        }
+        Ok(())
+    }
+}
+
+// The free space of one bin, in the form the heuristic needs.
+#[derive(Debug)]
+enum Free {
+    Skyline(Skyline),
+    MaxRects(MaxRects),
+}
+
+impl Free {
+    fn new(heuristic: Heuristic, width: u32, height: u32) -> Free {
+        match heuristic {
+            Heuristic::Skyline => Free::Skyline(Skyline::new(width, height)),
+            Heuristic::MaxRects => Free::MaxRects(MaxRects::new(width, height)),
+        }
+    }
+
+    // Places an item of the given size, returning where it went and whether it was turned round, or `None` if it doesn't fit.
+    fn insert(&mut self, width: u32, height: u32, allow_rotation: bool) -> Option<(Rectangle, bool)> {
+        let upright = self.find(width, height).map(|(score, rect)| (score, rect, false));
+        let turned = if allow_rotation && width != height {
+            self.find(height, width).map(|(score, rect)| (score, rect, true))
+        } else {
+            None
+        };
+
+        let best = match (upright, turned) {
+            (Some(a), Some(b)) => Some(if b.0 < a.0 { b } else { a }),
+            (a, b) => a.or(b),
+        };
+        let (_, rect, rotated) = best?;
+        self.commit(&rect);
+        Some((rect, rotated))
+    }
+
+    // The best spot for an item of exactly this size, with a score where lower is better.
+    fn find(&self, width: u32, height: u32) -> Option<((u32, u32), Rectangle)> {
+        match *self {
+            Free::Skyline(ref skyline) => skyline.find(width, height),
+            Free::MaxRects(ref max_rects) => max_rects.find(width, height),
+        }
+    }
+
+    fn commit(&mut self, rect: &Rectangle) {
+        match *self {
+            Free::Skyline(ref mut skyline) => skyline.commit(rect),
+            Free::MaxRects(ref mut max_rects) => max_rects.commit(rect),
+        }
+    }
+}
+
+// A stretch of the skyline: everything from `x` to `x + width` is taken down to `y`.
+#[derive(Clone, Copy, Debug)]
+struct Segment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+#[derive(Debug)]
+struct Skyline {
+    height: u32,
+    // Sorted by `x`, covering the whole bin width without gaps.
+    segments: Vec<Segment>,
+}
+
+impl Skyline {
+    fn new(width: u32, height: u32) -> Skyline {
+        Skyline { height, segments: vec![Segment { x: 0, y: 0, width }] }
+    }
+
+    /*
+        The lowest `y` an item of `width` can sit at with its left edge at segment `start`, if it stays inside the bin.
+        Segments never reach past the bin, so only the item's right edge can overflow, and then it doesn't fit anyway.
+    */
+    fn rest_on(&self, start: usize, width: u32) -> Option<u32> {
+        let left = self.segments[start].x;
+        let right = left.checked_add(width)?;
+        let mut y = 0;
+        for segment in &self.segments[start..] {
+            if segment.x >= right {
+                return Some(y);
+            }
+            y = cmp::max(y, segment.y);
+        }
+        let last = self.segments.last().unwrap();
+        if right <= last.x + last.width {
+            Some(y)
+        } else {
+            None
+        }
+    }
+
+    // Prefers the spot where the item's bottom edge ends up highest, then the leftmost one.
+    fn find(&self, width: u32, height: u32) -> Option<((u32, u32), Rectangle)> {
+        (0..self.segments.len())
+            .filter_map(|start| {
+                let y = self.rest_on(start, width)?;
+                let bottom = y.checked_add(height).filter(|&bottom| bottom <= self.height)?;
+                let x = self.segments[start].x;
+                Some(((bottom, x), Rectangle::placed(x, y, width, height)))
+            })
+            .min_by_key(|&(score, _)| score)
+    }
+
+    fn commit(&mut self, rect: &Rectangle) {
+        let mut segments = Vec::with_capacity(self.segments.len() + 2);
+        let mut inserted = false;
+        for segment in &self.segments {
+            let end = segment.x + segment.width;
+            // Keep whatever part of the segment lies outside the item's columns, and the item's bottom edge in between.
+            if segment.x < rect.x {
+                segments.push(Segment { width: cmp::min(end, rect.x) - segment.x, ..*segment });
+            }
+            if !inserted && end > rect.x {
+                segments.push(Segment { x: rect.x, y: rect.bottom(), width: rect.width });
+                inserted = true;
+            }
+            if end > rect.right() {
+                let x = cmp::max(segment.x, rect.right());
+                segments.push(Segment { x, y: segment.y, width: end - x });
+            }
+        }
+
+        // Neighbouring segments at the same height are one segment.
+        self.segments.clear();
+        for segment in segments {
+            match self.segments.last_mut() {
+                Some(last) if last.y == segment.y => last.width += segment.width,
+                _ => self.segments.push(segment),
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct MaxRects {
+    free: Vec<Rectangle>,
+}
+
+impl MaxRects {
+    fn new(width: u32, height: u32) -> MaxRects {
+        MaxRects { free: vec![Rectangle::new(width, height)] }
+    }
+
+    /*
+        "Best short side fit": the free rectangle whose shorter leftover side is smallest, so the item touches
+        as much of the free space's edges as possible and the leftovers stay large in one direction.
+    */
+    fn find(&self, width: u32, height: u32) -> Option<((u32, u32), Rectangle)> {
+        let item = Rectangle::new(width, height);
+        self.free
+            .iter()
+            .filter(|free| free.can_fit(&item))
+            .map(|free| {
+                let (spare_x, spare_y) = (free.width - width, free.height - height);
+                ((cmp::min(spare_x, spare_y), cmp::max(spare_x, spare_y)), item.moved_to(free.x, free.y))
+            })
+            .min_by_key(|&(score, _)| score)
+    }
+
+    fn commit(&mut self, rect: &Rectangle) {
+        let mut free = Vec::with_capacity(self.free.len() + 4);
+        for space in &self.free {
+            if space.intersects(rect) {
+                free.extend(maximal_remainders(space, rect));
+            } else {
+                free.push(*space);
+            }
+        }
+
+        // Drop free rectangles that lie inside another one; they add nothing but work.
+        let mut kept: Vec<Rectangle> = Vec::with_capacity(free.len());
+        for (i, space) in free.iter().enumerate() {
+            let redundant = free.iter().enumerate().any(|(j, other)| {
+                i != j && other.contains(space) && (space != other || j < i)
+            });
+            if !redundant {
+                kept.push(*space);
+            }
+        }
+        self.free = kept;
+    }
+}
+
+/*
+    Unlike `Rectangle::subtract`, which cuts the leftover space into pieces that don't overlap, MaxRects keeps each side
+    of the cut at its full size, so pieces overlap in the corners. That way no piece is narrower than it has to be,
+    and a large item can still find a spot that spans what `subtract` would have split in two.
+*/
+fn maximal_remainders(space: &Rectangle, cut: &Rectangle) -> Vec<Rectangle> {
+    let mut pieces = Vec::with_capacity(4);
+    if cut.x > space.x {
+        pieces.push(Rectangle::placed(space.x, space.y, cut.x - space.x, space.height));
+    }
+    if cut.right() < space.right() {
+        pieces.push(Rectangle::placed(cut.right(), space.y, space.right() - cut.right(), space.height));
+    }
+    if cut.y > space.y {
+        pieces.push(Rectangle::placed(space.x, space.y, space.width, cut.y - space.y));
+    }
+    if cut.bottom() < space.bottom() {
+        pieces.push(Rectangle::placed(space.x, cut.bottom(), space.width, space.bottom() - cut.bottom()));
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every item is placed once, at its own size, inside its bin and clear of the other items in that bin.
+    fn check_packing(packing: &Packing, items: &[Rectangle]) {
+        let bin = packing.bin_size;
+        assert_eq!(packing.placed_count() + packing.unfit.len(), items.len());
+        let mut seen = vec![false; items.len()];
+        for placements in packing.bins.iter().map(|bin| &bin.placements) {
+            for (i, placement) in placements.iter().enumerate() {
+                assert!(!seen[placement.index]);
+                seen[placement.index] = true;
+                let item = items[placement.index];
+                let size = if placement.rotated { item.rotated() } else { item };
+                assert_eq!((placement.rect.width, placement.rect.height), (size.width, size.height));
+                assert!(bin.contains(&placement.rect));
+                assert!(placements[..i].iter().all(|other| !other.rect.intersects(&placement.rect)));
+            }
+        }
+        for unfit in &packing.unfit {
+            assert!(!seen[unfit.index]);
+        }
+    }
+
+    fn sprites() -> Vec<Rectangle> {
+        let mut seed: u32 = 12345;
+        let mut random = |limit: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) % limit + 1
+        };
+        let mut sprites: Vec<Rectangle> = (0..200).map(|_| Rectangle::new(random(64), random(32))).collect();
+        sprites.push(Rectangle::new(300, 10));
+        sprites.push(Rectangle::new(0, 10));
+        sprites
+    }
+
+    #[test]
+    fn labels_fill_skyline_rows() {
+        let labels = vec![Rectangle::new(40, 20); 7];
+        let packing = Packer::new(100, 50).heuristic(Heuristic::Skyline).pack(&labels);
+        check_packing(&packing, &labels);
+        // Two rows of two fit in a bin, so seven labels need two bins.
+        assert_eq!(packing.bins.iter().map(|bin| bin.placements.len()).collect::<Vec<_>>(), vec![4, 3]);
+        assert!(packing.unfit.is_empty());
+        assert_eq!(packing.bin_utilization(0), 0.64);
+    }
+
+    #[test]
+    fn every_heuristic_places_every_sprite_that_fits() {
+        let sprites = sprites();
+        for &heuristic in &[Heuristic::Skyline, Heuristic::MaxRects] {
+            for &allow_rotation in &[false, true] {
+                let packing = Packer::new(256, 256).heuristic(heuristic).allow_rotation(allow_rotation).pack(&sprites);
+                check_packing(&packing, &sprites);
+                assert_eq!(packing.unfit, vec![
+                    Unfit { index: 200, reason: UnfitReason::TooLarge },
+                    Unfit { index: 201, reason: UnfitReason::Empty },
+                ]);
+                assert!(packing.utilization() > 0.5 && packing.utilization() <= 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_lets_tall_items_into_wide_bins() {
+        let items = [Rectangle::new(10, 40)];
+        assert_eq!(Packer::new(40, 10).pack(&items).unfit, vec![Unfit { index: 0, reason: UnfitReason::TooLarge }]);
+        let packing = Packer::new(40, 10).allow_rotation(true).pack(&items);
+        assert_eq!(packing.bins[0].placements, vec![Placement { index: 0, rect: Rectangle::new(40, 10), rotated: true }]);
+    }
+
+    #[test]
+    fn a_bin_limit_leaves_the_rest_out_of_bins() {
+        let sprites = sprites();
+        let limited = Packer::new(128, 128).allow_rotation(true).max_bins(1).pack(&sprites);
+        check_packing(&limited, &sprites);
+        assert_eq!(limited.bins.len(), 1);
+        assert!(limited.unfit.iter().any(|unfit| unfit.reason == UnfitReason::OutOfBins));
+        // Items are packed largest first, so the first placement is the largest sprite that fits.
+        let first = limited.bins[0].placements[0];
+        let largest = (0..200).max_by_key(|&i| (cmp::max(sprites[i].width, sprites[i].height), Reverse(i))).unwrap();
+        assert_eq!(cmp::max(sprites[first.index].width, sprites[first.index].height),
+                   cmp::max(sprites[largest].width, sprites[largest].height));
+        assert!(Packer::new(10, 10).max_bins(0).pack(&sprites[..1]).bins.is_empty());
+    }
+
+    #[test]
+    fn areas_past_u32_do_not_overflow() {
+        let items = [Rectangle::new(70_000, 70_000)];
+        for &heuristic in &[Heuristic::Skyline, Heuristic::MaxRects] {
+            let packing = Packer::new(100_000, 100_000).heuristic(heuristic).pack(&items);
+            assert_eq!(packing.bins[0].used_area(), 4_900_000_000);
+            assert_eq!(packing.utilization(), 0.49);
+        }
+    }
+
+    #[test]
+    fn skyline_edges_near_u32_max_do_not_overflow() {
+        let max = u32::MAX;
+        let wide = [Rectangle::new(max - 5, 5), Rectangle::new(10, 5)];
+        let packing = Packer::new(max, 10).heuristic(Heuristic::Skyline).pack(&wide);
+        check_packing(&packing, &wide);
+        assert_eq!(packing.bins.len(), 1);
+        assert_eq!(packing.bins[0].placements[1].rect, Rectangle::placed(0, 5, 10, 5));
+
+        let tall = [Rectangle::new(10, max - 5), Rectangle::new(10, 10)];
+        let packing = Packer::new(10, max).heuristic(Heuristic::Skyline).pack(&tall);
+        check_packing(&packing, &tall);
+        assert_eq!(packing.bins.len(), 2);
+    }
+}
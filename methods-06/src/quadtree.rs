@@ -0,0 +1,348 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::mem;
+
+use Rectangle;
+
+/*
+    A quadtree splits a square-ish area into four quarters, each quarter into four more, and so on, but only where
+    there are many items. Each item is stored in the smallest quarter that contains it whole, so a query only has
+    to look at the quarters its point or range touches instead of at every item.
+
+    Items that straddle the line between two quarters stay in the larger node above them; items that stick out of
+    the tree's bounds altogether stay in the root. Both are still found by every query, they just cost a check each time.
+
+    Nodes and items live in `Vec`s and refer to each other by index, which keeps the borrow checker out of the way
+    of a structure whose parents point to children and whose items point back to their node.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemId(usize);
+
+const NODE_CAPACITY: usize = 8;
+const MAX_DEPTH: usize = 10;
+
+#[derive(Debug)]
+struct Node {
+    bounds: Rectangle,
+    depth: usize,
+    items: Vec<usize>,
+    children: Option<[usize; 4]>,
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+    rect: Rectangle,
+    value: T,
+    node: usize,
+}
+
+#[derive(Debug)]
+pub struct QuadTree<T> {
+    nodes: Vec<Node>,
+    // Removed items leave `None` behind, so an `ItemId` is never reused for a different item.
+    entries: Vec<Option<Entry<T>>>,
+    len: usize,
+}
+
+// Squared distance from a point to the nearest point of `rect`; 0 if the point is inside it. Saturates for points near opposite corners of `u32`.
+fn distance_squared(rect: &Rectangle, x: u32, y: u32) -> u64 {
+    let gap = |point: u32, low: u32, high: u32| -> u64 {
+        if point < low {
+            u64::from(low - point)
+        } else if point >= high {
+            u64::from(point - high + 1)
+        } else {
+            0
+        }
+    };
+    let dx = gap(x, rect.left(), rect.right());
+    let dy = gap(y, rect.top(), rect.bottom());
+    (dx * dx).saturating_add(dy * dy)
+}
+
+impl<T> QuadTree<T> {
+    pub fn new(bounds: Rectangle) -> QuadTree<T> {
+        QuadTree {
+            nodes: vec![Node { bounds, depth: 0, items: Vec::new(), children: None }],
+            entries: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, id: ItemId) -> Option<(&Rectangle, &T)> {
+        let entry = self.entries.get(id.0)?.as_ref()?;
+        Some((&entry.rect, &entry.value))
+    }
+
+    pub fn insert(&mut self, rect: Rectangle, value: T) -> ItemId {
+        let index = self.entries.len();
+        let node = self.node_for(&rect);
+        self.entries.push(Some(Entry { rect, value, node }));
+        self.nodes[node].items.push(index);
+        self.len += 1;
+
+        if self.nodes[node].items.len() > NODE_CAPACITY && self.nodes[node].children.is_none() {
+            self.split(node);
+        }
+        ItemId(index)
+    }
+
+    pub fn remove(&mut self, id: ItemId) -> Option<(Rectangle, T)> {
+        let entry = self.entries.get_mut(id.0)?.take()?;
+        let items = &mut self.nodes[entry.node].items;
+        let position = items.iter().position(|&item| item == id.0).unwrap();
+        items.swap_remove(position);
+        self.len -= 1;
+        Some((entry.rect, entry.value))
+    }
+
+    // The deepest existing node whose bounds contain `rect` whole.
+    fn node_for(&self, rect: &Rectangle) -> usize {
+        let mut node = 0;
+        while let Some(children) = self.nodes[node].children {
+            match children.iter().find(|&&child| self.nodes[child].bounds.contains(rect)) {
+                Some(&child) => node = child,
+                None => break,
+            }
+        }
+        node
+    }
+
+    fn split(&mut self, node: usize) {
+        let Node { bounds, depth, .. } = self.nodes[node];
+        if depth >= MAX_DEPTH || bounds.width < 2 || bounds.height < 2 {
+            return;
+        }
+
+        let (left, right) = bounds.split_vertical(bounds.width / 2).unwrap();
+        let (top_left, bottom_left) = left.split_horizontal(bounds.height / 2).unwrap();
+        let (top_right, bottom_right) = right.split_horizontal(bounds.height / 2).unwrap();
+        let first = self.nodes.len();
+        for &quarter in &[top_left, top_right, bottom_left, bottom_right] {
+            self.nodes.push(Node { bounds: quarter, depth: depth + 1, items: Vec::new(), children: None });
+        }
+        let children = [first, first + 1, first + 2, first + 3];
+        self.nodes[node].children = Some(children);
+
+        // Move down every item that fits inside one of the new quarters.
+        let items = mem::take(&mut self.nodes[node].items);
+        for item in items {
+            let rect = self.entries[item].as_ref().unwrap().rect;
+            let target = children.iter().cloned().find(|&child| self.nodes[child].bounds.contains(&rect)).unwrap_or(node);
+            self.nodes[target].items.push(item);
+            self.entries[item].as_mut().unwrap().node = target;
+        }
+
+        for &child in &children {
+            if self.nodes[child].items.len() > NODE_CAPACITY {
+                self.split(child);
+            }
+        }
+    }
+
+    // Visits every node whose bounds pass `visit_node`, and every item in those nodes.
+    fn visit<'a, N, F>(&'a self, mut visit_node: N, mut visit_item: F)
+    where
+        N: FnMut(&Rectangle) -> bool,
+        F: FnMut(usize, &'a Entry<T>),
+    {
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            for &item in &node.items {
+                visit_item(item, self.entries[item].as_ref().unwrap());
+            }
+            if let Some(children) = node.children {
+                stack.extend(children.iter().filter(|&&child| visit_node(&self.nodes[child].bounds)));
+            }
+        }
+    }
+
+    // Every item covering the point `(x, y)`.
+    pub fn query_point(&self, x: u32, y: u32) -> Vec<(ItemId, &T)> {
+        let mut found = Vec::new();
+        self.visit(
+            |bounds| bounds.contains_point(x, y),
+            |item, entry| {
+                if entry.rect.contains_point(x, y) {
+                    found.push((ItemId(item), &entry.value));
+                }
+            },
+        );
+        found
+    }
+
+    // Every item sharing some area with `range`.
+    pub fn query_range(&self, range: &Rectangle) -> Vec<(ItemId, &T)> {
+        let mut found = Vec::new();
+        self.visit(
+            |bounds| bounds.intersects(range),
+            |item, entry| {
+                if entry.rect.intersects(range) {
+                    found.push((ItemId(item), &entry.value));
+                }
+            },
+        );
+        found
+    }
+
+    /*
+        The item closest to `(x, y)`, measuring to the nearest point of each item, so items covering the point are at distance 0.
+        Nodes are searched closest first, and the search stops as soon as the next node is further away
+        than the best item found so far, since nothing inside it can be closer.
+    */
+    pub fn nearest(&self, x: u32, y: u32) -> Option<(ItemId, &T)> {
+        let mut best: Option<(u64, usize)> = None;
+        // The root can hold items outside its bounds, so it is always searched first, at distance 0.
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse((0, 0)));
+
+        while let Some(Reverse((distance, node))) = queue.pop() {
+            if best.is_some_and(|(best_distance, _)| distance > best_distance) {
+                break;
+            }
+            let node = &self.nodes[node];
+            for &item in &node.items {
+                let item_distance = distance_squared(&self.entries[item].as_ref().unwrap().rect, x, y);
+                if best.is_none_or(|best| (item_distance, item) < best) {
+                    best = Some((item_distance, item));
+                }
+            }
+            if let Some(children) = node.children {
+                for &child in &children {
+                    queue.push(Reverse((distance_squared(&self.nodes[child].bounds, x, y), child)));
+                }
+            }
+        }
+
+        best.map(|(_, item)| (ItemId(item), &self.entries[item].as_ref().unwrap().value))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ItemId, &Rectangle, &T)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| entry.as_ref().map(|entry| (ItemId(index), &entry.rect, &entry.value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_regions(count: usize, seed: u32) -> Vec<Rectangle> {
+        let mut seed = seed;
+        let mut random = |limit: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) % limit
+        };
+        (0..count).map(|_| Rectangle::placed(random(1000), random(1000), random(48) + 1, random(48) + 1)).collect()
+    }
+
+    fn sorted(found: Vec<(ItemId, &usize)>) -> Vec<usize> {
+        let mut found: Vec<usize> = found.into_iter().map(|(_, &i)| i).collect();
+        found.sort();
+        found
+    }
+
+    fn linear_nearest(regions: &[Option<Rectangle>], x: u32, y: u32) -> Option<usize> {
+        (0..regions.len())
+            .filter_map(|i| regions[i].map(|region| ((distance_squared(&region, x, y), i), i)))
+            .min()
+            .map(|(_, i)| i)
+    }
+
+    // A tree over 1024x1024 with regions all over it, plus a few outside its bounds, removed and not.
+    fn populated() -> (QuadTree<usize>, Vec<Option<Rectangle>>) {
+        let mut regions: Vec<Option<Rectangle>> = random_regions(3_000, 7).into_iter().map(Some).collect();
+        regions.push(Some(Rectangle::placed(5000, 5000, 10, 10)));
+        regions.push(Some(Rectangle::placed(1020, 0, 10, 10)));
+        let mut tree = QuadTree::new(Rectangle::new(1024, 1024));
+        let ids: Vec<ItemId> = regions.iter().enumerate().map(|(i, region)| tree.insert(region.unwrap(), i)).collect();
+        for i in (0..regions.len()).step_by(3) {
+            assert_eq!(tree.remove(ids[i]).map(|(_, value)| value), Some(i));
+            assert!(tree.remove(ids[i]).is_none());
+            regions[i] = None;
+        }
+        (tree, regions)
+    }
+
+    #[test]
+    fn queries_agree_with_a_linear_scan() {
+        let (tree, regions) = populated();
+        assert_eq!(tree.len(), regions.iter().filter(|region| region.is_some()).count());
+        let live = |keep: &dyn Fn(&Rectangle) -> bool| -> Vec<usize> {
+            (0..regions.len()).filter(|&i| regions[i].as_ref().is_some_and(keep)).collect()
+        };
+
+        for x in (0..1100).step_by(37) {
+            for y in (0..1100).step_by(41) {
+                assert_eq!(sorted(tree.query_point(x, y)), live(&|region| region.contains_point(x, y)));
+                assert_eq!(tree.nearest(x, y).map(|(_, &i)| i), linear_nearest(&regions, x, y));
+            }
+        }
+        for &range in &[Rectangle::placed(100, 200, 300, 150), Rectangle::new(1024, 1024), Rectangle::placed(1000, 0, 100, 100)] {
+            assert_eq!(sorted(tree.query_range(&range)), live(&|region| region.intersects(&range)));
+        }
+        // The region sticking out of the bounds is still found, while the removed one far outside them isn't.
+        assert_eq!(tree.nearest(1025, 5).map(|(_, &i)| i), Some(regions.len() - 1));
+        assert!(tree.query_point(5005, 5005).is_empty());
+        assert_eq!(tree.nearest(u32::MAX, u32::MAX).map(|(_, &i)| i), linear_nearest(&regions, u32::MAX, u32::MAX));
+    }
+
+    #[test]
+    fn points_on_node_boundaries() {
+        // Enough items to split the root at x = 32 and y = 32, some inside a quarter and some straddling the midlines.
+        let mut tree = QuadTree::new(Rectangle::new(64, 64));
+        let regions = [
+            Rectangle::placed(0, 0, 32, 32),
+            Rectangle::placed(32, 0, 32, 32),
+            Rectangle::placed(0, 32, 32, 32),
+            Rectangle::placed(32, 32, 32, 32),
+            Rectangle::placed(31, 31, 2, 2),
+            Rectangle::placed(30, 0, 4, 64),
+            Rectangle::placed(0, 30, 64, 4),
+            Rectangle::placed(16, 16, 16, 16),
+            Rectangle::placed(32, 32, 1, 1),
+            Rectangle::placed(63, 63, 1, 1),
+        ];
+        for (i, &region) in regions.iter().enumerate() {
+            tree.insert(region, i);
+        }
+        assert!(tree.nodes.len() > 1);
+
+        for &(x, y) in &[(31, 31), (32, 31), (31, 32), (32, 32), (0, 0), (63, 63), (64, 0), (0, 64), (33, 33)] {
+            let expected: Vec<usize> = (0..regions.len()).filter(|&i| regions[i].contains_point(x, y)).collect();
+            assert_eq!(sorted(tree.query_point(x, y)), expected, "point ({}, {})", x, y);
+        }
+        // Right and bottom edges are exclusive: the quarter ending at 32 doesn't own the point at 32.
+        assert_eq!(sorted(tree.query_point(32, 32)), vec![3, 4, 5, 6, 8]);
+        assert!(tree.query_point(64, 64).is_empty());
+        // A range touching a region only along an edge doesn't intersect it.
+        assert_eq!(sorted(tree.query_range(&Rectangle::placed(34, 0, 30, 30))), vec![1]);
+        assert_eq!(tree.nearest(32, 32).map(|(_, &i)| i), Some(3));
+        assert_eq!(tree.nearest(64, 64).map(|(_, &i)| i), Some(3));
+    }
+
+    #[test]
+    fn nearest_in_an_empty_tree() {
+        let mut tree = QuadTree::new(Rectangle::new(100, 100));
+        assert!(tree.is_empty());
+        assert!(tree.nearest(50, 50).is_none());
+
+        let id = tree.insert(Rectangle::placed(10, 10, 5, 5), "only");
+        assert_eq!(tree.nearest(99, 99), Some((id, &"only")));
+        assert_eq!(tree.remove(id), Some((Rectangle::placed(10, 10, 5, 5), "only")));
+        assert!(tree.nearest(50, 50).is_none());
+        assert!(tree.get(id).is_none());
+        assert_eq!(tree.iter().count(), 0);
+    }
+}
@@ -0,0 +1,297 @@
+use std::cmp;
+use std::fmt::Write;
+
+use Rectangle;
+
+/*
+    Draws a set of positioned rectangles, either as an SVG image or as ASCII art for the terminal,
+    so a layout can be checked by eye instead of by reading coordinates.
+    Wherever two rectangles overlap, both renderers mark the overlap, since in a layout that is usually the bug.
+*/
+#[derive(Clone, Debug)]
+struct Item {
+    rect: Rectangle,
+    label: String,
+    color: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Canvas {
+    items: Vec<Item>,
+}
+
+// Colours handed out in turn to rectangles that don't set their own.
+const PALETTE: [&str; 8] = ["#4e79a7", "#f28e2b", "#59a14f", "#b07aa1", "#76b7b2", "#edc948", "#9c755f", "#bab0ac"];
+const OVERLAP_COLOR: &str = "#e15759";
+// Limits that keep a layout with huge or lopsided extents from producing a huge drawing.
+const MAX_ASCII_ROWS: usize = 200;
+const MAX_GRID_LINES: u32 = 1000;
+
+#[derive(Clone, Debug)]
+pub struct SvgOptions {
+    // Pixels per layout unit.
+    pub scale: f64,
+    // Spacing of the background grid in layout units, or `None` for no grid. Widened if it would draw over `MAX_GRID_LINES` lines.
+    pub grid: Option<u32>,
+    pub labels: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> SvgOptions {
+        SvgOptions { scale: 1.0, grid: Some(10), labels: true }
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+impl Canvas {
+    pub fn new() -> Canvas {
+        Canvas::default()
+    }
+
+    pub fn add(&mut self, rect: Rectangle, label: &str) -> &mut Canvas {
+        self.items.push(Item { rect, label: label.to_string(), color: None });
+        self
+    }
+
+    // `color` is any SVG colour, e.g. `"teal"` or `"#336699"`. The ASCII renderer has no colours and ignores it.
+    pub fn add_colored(&mut self, rect: Rectangle, label: &str, color: &str) -> &mut Canvas {
+        self.items.push(Item { rect, label: label.to_string(), color: Some(color.to_string()) });
+        self
+    }
+
+    // Every pair of rectangles that overlap, by the order they were added in, with the area they share.
+    pub fn overlaps(&self) -> Vec<(usize, usize, Rectangle)> {
+        let mut overlaps = Vec::new();
+        for (i, a) in self.items.iter().enumerate() {
+            for (j, b) in self.items.iter().enumerate().skip(i + 1) {
+                if let Some(shared) = a.rect.intersection(&b.rect) {
+                    overlaps.push((i, j, shared));
+                }
+            }
+        }
+        overlaps
+    }
+
+    // The area to draw: from the origin to the far corner of the furthest rectangle.
+    fn extent(&self) -> Rectangle {
+        self.items.iter().fold(Rectangle::new(0, 0), |extent, item| extent.union(&item.rect))
+    }
+
+    pub fn to_svg(&self, options: &SvgOptions) -> String {
+        let extent = self.extent();
+        let scale = options.scale;
+        let (width, height) = (f64::from(extent.right()) * scale, f64::from(extent.bottom()) * scale);
+
+        let mut svg = String::new();
+        // Writing to a `String` can't fail, so the `fmt::Result`s below are ignored.
+        let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+                         width, height, width, height);
+        let _ = writeln!(svg, r#"  <rect width="100%" height="100%" fill="white"/>"#);
+
+        if let Some(step) = options.grid.filter(|&step| step > 0) {
+            let longest = cmp::max(extent.right(), extent.bottom());
+            let step = cmp::max(step, longest / MAX_GRID_LINES + 1);
+            let _ = writeln!(svg, r##"  <g stroke="#e0e0e0" stroke-width="1">"##);
+            for x in (0..=extent.right()).step_by(step as usize) {
+                let x = f64::from(x) * scale;
+                let _ = writeln!(svg, r#"    <line x1="{}" y1="0" x2="{}" y2="{}"/>"#, x, x, height);
+            }
+            for y in (0..=extent.bottom()).step_by(step as usize) {
+                let y = f64::from(y) * scale;
+                let _ = writeln!(svg, r#"    <line x1="0" y1="{}" x2="{}" y2="{}"/>"#, y, width, y);
+            }
+            let _ = writeln!(svg, "  </g>");
+        }
+
+        for (i, item) in self.items.iter().enumerate() {
+            let color = item.color.as_ref().map_or(PALETTE[i % PALETTE.len()], String::as_str);
+            let rect = item.rect;
+            let _ = writeln!(svg, r#"  <rect x="{}" y="{}" width="{}" height="{}" fill="{}" fill-opacity="0.6" stroke="{}"/>"#,
+                             f64::from(rect.x) * scale, f64::from(rect.y) * scale,
+                             f64::from(rect.width) * scale, f64::from(rect.height) * scale,
+                             escape_xml(color), escape_xml(color));
+        }
+
+        for (_, _, shared) in self.overlaps() {
+            let _ = writeln!(svg, r#"  <rect x="{}" y="{}" width="{}" height="{}" fill="{}" fill-opacity="0.8" stroke="{}" stroke-dasharray="4 2"/>"#,
+                             f64::from(shared.x) * scale, f64::from(shared.y) * scale,
+                             f64::from(shared.width) * scale, f64::from(shared.height) * scale,
+                             OVERLAP_COLOR, OVERLAP_COLOR);
+        }
+
+        // Labels go last so no rectangle is drawn over them.
+        if options.labels {
+            for item in &self.items {
+                let rect = item.rect;
+                let _ = writeln!(svg, r#"  <text x="{}" y="{}" font-family="monospace" font-size="12" text-anchor="middle" dominant-baseline="middle">{}</text>"#,
+                                 (f64::from(rect.x) + f64::from(rect.width) / 2.0) * scale,
+                                 (f64::from(rect.y) + f64::from(rect.height) / 2.0) * scale,
+                                 escape_xml(&item.label));
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /*
+        Scales the layout so it is `columns` characters wide. Terminal characters are about twice as tall as they are wide,
+        so each row stands for twice the height each column stands for width, which keeps squares looking square.
+        A layout too tall for that to fit in `MAX_ASCII_ROWS` rows is scaled down further, and then takes up fewer columns.
+
+        Every cell a rectangle touches shows its letter, `A` for the first one added, `B` for the second, and so on;
+        cells where two rectangles overlap show `#`. A legend below the drawing says which letter is which.
+    */
+    pub fn to_ascii(&self, columns: usize) -> String {
+        let extent = self.extent();
+        let (right, bottom) = (f64::from(extent.right().max(1)), f64::from(extent.bottom()));
+        let cell_width = f64::max(right / columns.max(1) as f64, bottom / (2 * MAX_ASCII_ROWS) as f64);
+        let cell_height = cell_width * 2.0;
+        let columns = ((right / cell_width).ceil() as usize).clamp(1, columns.max(1));
+        let rows = ((bottom / cell_height).ceil() as usize).clamp(1, MAX_ASCII_ROWS);
+
+        // The cells whose area overlaps `rect`, as half-open column and row ranges.
+        let cells = |rect: &Rectangle| {
+            let first_column = (f64::from(rect.left()) / cell_width).floor() as usize;
+            let end_column = (f64::from(rect.right()) / cell_width).ceil() as usize;
+            let first_row = (f64::from(rect.top()) / cell_height).floor() as usize;
+            let end_row = (f64::from(rect.bottom()) / cell_height).ceil() as usize;
+            (first_column..end_column.min(columns), first_row..end_row.min(rows))
+        };
+
+        let mut grid = vec![vec![' '; columns]; rows];
+        let mut fill = |rect: &Rectangle, mark: char| {
+            let (column_range, row_range) = cells(rect);
+            for row in row_range {
+                for cell in &mut grid[row][column_range.clone()] {
+                    *cell = mark;
+                }
+            }
+        };
+        // Rectangles that only touch can still share a cell at this scale; the one added later wins it.
+        for (i, item) in self.items.iter().enumerate().filter(|&(_, item)| !item.rect.is_empty()) {
+            fill(&item.rect, letter(i));
+        }
+        let overlaps = self.overlaps();
+        for &(_, _, shared) in &overlaps {
+            fill(&shared, '#');
+        }
+
+        let mut art = String::new();
+        let border = format!("+{}+\n", "-".repeat(columns));
+        art.push_str(&border);
+        for row in &grid {
+            art.push('|');
+            art.extend(row.iter());
+            art.push_str("|\n");
+        }
+        art.push_str(&border);
+
+        for (i, item) in self.items.iter().enumerate() {
+            let _ = writeln!(art, "{} {}: {}", letter(i), item.label, item.rect);
+        }
+        for (i, j, shared) in overlaps {
+            let _ = writeln!(art, "# {} and {} overlap: {}", self.items[i].label, self.items[j].label, shared);
+        }
+        art
+    }
+}
+
+// `A` to `Z`, then `a` to `z`, then `?` for any rectangle after the 52nd.
+fn letter(index: usize) -> char {
+    const LETTERS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+    LETTERS.get(index).map_or('?', |&letter| letter as char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The drawing between the borders, one string per row.
+    fn drawing(art: &str) -> Vec<&str> {
+        art.lines().skip(1).take_while(|line| line.starts_with('|')).map(|line| line.trim_matches('|')).collect()
+    }
+
+    #[test]
+    fn ascii_marks_rectangles_and_overlaps() {
+        let mut canvas = Canvas::new();
+        canvas.add(Rectangle::placed(0, 0, 40, 20), "a").add(Rectangle::placed(20, 10, 40, 20), "b");
+        let art = canvas.to_ascii(6);
+        assert_eq!(drawing(&art), vec!["AA##BB", "  BBBB"]);
+        assert!(art.starts_with("+------+\n"));
+        assert!(art.ends_with("A a: 40x20 at (0, 0)\nB b: 40x20 at (20, 10)\n# a and b overlap: 20x10 at (20, 10)\n"));
+    }
+
+    #[test]
+    fn ascii_of_an_empty_canvas() {
+        assert_eq!(Canvas::new().to_ascii(0), "+-+\n| |\n+-+\n");
+        let mut canvas = Canvas::new();
+        canvas.add(Rectangle::placed(5, 5, 0, 10), "empty");
+        assert!(drawing(&canvas.to_ascii(4)).iter().all(|row| row.trim().is_empty()));
+    }
+
+    #[test]
+    fn ascii_rows_are_capped_for_tall_layouts() {
+        let mut canvas = Canvas::new();
+        canvas.add(Rectangle::placed(0, 0, 1, 100_000), "pole").add(Rectangle::placed(0, 0, 1, u32::MAX), "taller");
+        let art = canvas.to_ascii(80);
+        let rows = drawing(&art);
+        assert_eq!(rows.len(), MAX_ASCII_ROWS);
+        assert!(rows.iter().all(|row| row == &"#" || row == &"B"));
+
+        let mut square = Canvas::new();
+        square.add(Rectangle::new(u32::MAX, u32::MAX), "everything");
+        let art = square.to_ascii(80);
+        let rows = drawing(&art);
+        assert_eq!(rows.len(), 40);
+        assert!(rows.iter().all(|row| row.len() == 80 && row.chars().all(|cell| cell == 'A')));
+    }
+
+    #[test]
+    fn letters_run_out_after_52_rectangles() {
+        assert_eq!((letter(0), letter(25), letter(26), letter(51), letter(52)), ('A', 'Z', 'a', 'z', '?'));
+    }
+
+    #[test]
+    fn svg_draws_rectangles_overlaps_and_escaped_labels() {
+        let mut canvas = Canvas::new();
+        canvas.add(Rectangle::placed(0, 0, 40, 20), "a & b").add_colored(Rectangle::placed(20, 10, 40, 20), "<b>", "\"red\"");
+        let svg = canvas.to_svg(&SvgOptions { scale: 2.0, grid: None, labels: true });
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="120" height="60" viewBox="0 0 120 60">"#));
+        assert!(svg.contains(r##"<rect x="0" y="0" width="80" height="40" fill="#4e79a7""##));
+        assert!(svg.contains(r#"fill="&quot;red&quot;""#));
+        assert!(svg.contains(r##"<rect x="40" y="20" width="40" height="20" fill="#e15759""##));
+        assert!(svg.contains(">a &amp; b</text>") && svg.contains(">&lt;b&gt;</text>"));
+        assert!(!svg.contains("<line"));
+        assert!(svg.ends_with("</svg>\n"));
+
+        let plain = canvas.to_svg(&SvgOptions { labels: false, grid: Some(0), ..SvgOptions::default() });
+        assert!(!plain.contains("<text") && !plain.contains("<line"));
+    }
+
+    #[test]
+    fn svg_grid_lines_are_capped() {
+        let mut canvas = Canvas::new();
+        canvas.add(Rectangle::new(60, 30), "small");
+        let lines = |canvas: &Canvas| canvas.to_svg(&SvgOptions { grid: Some(10), ..SvgOptions::default() }).matches("<line").count();
+        assert_eq!(lines(&canvas), 7 + 4);
+
+        let mut huge = Canvas::new();
+        huge.add(Rectangle::new(u32::MAX, 10), "huge");
+        let count = lines(&huge);
+        assert!(count > 2 && count <= 2 * (MAX_GRID_LINES as usize + 1));
+    }
+}
@@ -0,0 +1,401 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+use Rectangle;
+
+/*
+    `Rectangle` is one kind of shape among many. The `Shape` trait collects what every shape can answer, however it is
+    stored, so code that only needs areas or hit-tests can take any of them, even several kinds mixed in one `Vec`.
+
+    Shapes other than `Rectangle` and `Square` live in continuous space, so the trait works in `f64` throughout,
+    with `y` growing downwards as for `Rectangle`.
+*/
+pub trait Shape: fmt::Debug {
+    fn area(&self) -> f64;
+
+    fn perimeter(&self) -> f64;
+
+    // The smallest axis-aligned box containing the whole shape.
+    fn bounding_box(&self) -> Bounds;
+
+    fn contains_point(&self, point: Point) -> bool;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    pub fn distance(&self, other: &Point) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Bounds {
+    pub fn width(&self) -> f64 {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max.y - self.min.y
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            min: Point::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y)),
+            max: Point::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y)),
+        }
+    }
+
+    fn around(points: &[Point]) -> Bounds {
+        let first = Bounds { min: points[0], max: points[0] };
+        points[1..].iter().fold(first, |bounds, &point| bounds.union(&Bounds { min: point, max: point }))
+    }
+}
+
+impl Shape for Rectangle {
+    fn area(&self) -> f64 {
+        f64::from(self.width) * f64::from(self.height)
+    }
+
+    fn perimeter(&self) -> f64 {
+        2.0 * (f64::from(self.width) + f64::from(self.height))
+    }
+
+    fn bounding_box(&self) -> Bounds {
+        Bounds {
+            min: Point::new(f64::from(self.left()), f64::from(self.top())),
+            max: Point::new(f64::from(self.right()), f64::from(self.bottom())),
+        }
+    }
+
+    // Half-open like the integer `contains_point`: the left and top edges are inside, the right and bottom ones are not.
+    fn contains_point(&self, point: Point) -> bool {
+        let bounds = self.bounding_box();
+        bounds.min.x <= point.x && point.x < bounds.max.x && bounds.min.y <= point.y && point.y < bounds.max.y
+    }
+}
+
+// A rectangle whose sides are all the same length, so it only needs one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Square {
+    pub x: u32,
+    pub y: u32,
+    pub side: u32,
+}
+
+impl Square {
+    pub fn new(x: u32, y: u32, side: u32) -> Square {
+        Square { x, y, side }
+    }
+
+    pub fn to_rectangle(self) -> Rectangle {
+        Rectangle::placed(self.x, self.y, self.side, self.side)
+    }
+}
+
+impl Shape for Square {
+    fn area(&self) -> f64 {
+        f64::from(self.side) * f64::from(self.side)
+    }
+
+    fn perimeter(&self) -> f64 {
+        4.0 * f64::from(self.side)
+    }
+
+    fn bounding_box(&self) -> Bounds {
+        self.to_rectangle().bounding_box()
+    }
+
+    fn contains_point(&self, point: Point) -> bool {
+        Shape::contains_point(&self.to_rectangle(), point)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Circle {
+    pub center: Point,
+    pub radius: f64,
+}
+
+impl Circle {
+    pub fn new(center: Point, radius: f64) -> Circle {
+        Circle { center, radius }
+    }
+}
+
+impl Shape for Circle {
+    fn area(&self) -> f64 {
+        PI * self.radius * self.radius
+    }
+
+    fn perimeter(&self) -> f64 {
+        2.0 * PI * self.radius
+    }
+
+    fn bounding_box(&self) -> Bounds {
+        Bounds {
+            min: Point::new(self.center.x - self.radius, self.center.y - self.radius),
+            max: Point::new(self.center.x + self.radius, self.center.y + self.radius),
+        }
+    }
+
+    fn contains_point(&self, point: Point) -> bool {
+        self.center.distance(&point) <= self.radius
+    }
+}
+
+/*
+    The shoelace formula: walking round the polygon, each edge adds the signed area of the triangle it makes with the origin.
+    Parts outside the polygon are added once going out and subtracted once coming back, so only the polygon's area is left.
+    The sign says which way round the points go, which `area` doesn't care about.
+*/
+fn signed_area(points: &[Point]) -> f64 {
+    let twice_area: f64 = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum();
+    twice_area / 2.0
+}
+
+fn outline_length(points: &[Point]) -> f64 {
+    points.iter().zip(points.iter().cycle().skip(1)).map(|(a, b)| a.distance(b)).sum()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Triangle {
+    pub corners: [Point; 3],
+}
+
+impl Triangle {
+    pub fn new(a: Point, b: Point, c: Point) -> Triangle {
+        Triangle { corners: [a, b, c] }
+    }
+}
+
+impl Shape for Triangle {
+    fn area(&self) -> f64 {
+        signed_area(&self.corners).abs()
+    }
+
+    fn perimeter(&self) -> f64 {
+        outline_length(&self.corners)
+    }
+
+    fn bounding_box(&self) -> Bounds {
+        Bounds::around(&self.corners)
+    }
+
+    // Inside (or on an edge) when the point is on the same side of all three edges, whichever way round the corners go.
+    fn contains_point(&self, point: Point) -> bool {
+        let [a, b, c] = self.corners;
+        let side = |from: Point, to: Point| (to.x - from.x) * (point.y - from.y) - (to.y - from.y) * (point.x - from.x);
+        let sides = [side(a, b), side(b, c), side(c, a)];
+        sides.iter().all(|&s| s >= 0.0) || sides.iter().all(|&s| s <= 0.0)
+    }
+}
+
+// A closed outline through `points` in order, with an edge from the last point back to the first. It may be concave.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polygon {
+    points: Vec<Point>,
+}
+
+impl Polygon {
+    // `None` for fewer than three points, which don't enclose anything.
+    pub fn new(points: Vec<Point>) -> Option<Polygon> {
+        if points.len() < 3 {
+            None
+        } else {
+            Some(Polygon { points })
+        }
+    }
+
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+}
+
+impl Shape for Polygon {
+    fn area(&self) -> f64 {
+        signed_area(&self.points).abs()
+    }
+
+    fn perimeter(&self) -> f64 {
+        outline_length(&self.points)
+    }
+
+    fn bounding_box(&self) -> Bounds {
+        Bounds::around(&self.points)
+    }
+
+    /*
+        Points on an edge or corner are inside, as for `Triangle`. Otherwise this casts a ray from the point towards +x and counts
+        the edges it crosses: an odd count means the point is inside. Each edge counts as covering its lower end but not its
+        upper one, so a ray through a corner is counted once, not twice.
+    */
+    fn contains_point(&self, point: Point) -> bool {
+        let edges = || self.points.iter().zip(self.points.iter().cycle().skip(1));
+        let on_edge = |a: &Point, b: &Point| {
+            let cross = (b.x - a.x) * (point.y - a.y) - (b.y - a.y) * (point.x - a.x);
+            cross == 0.0 && a.x.min(b.x) <= point.x && point.x <= a.x.max(b.x) && a.y.min(b.y) <= point.y && point.y <= a.y.max(b.y)
+        };
+        if edges().any(|(a, b)| on_edge(a, b)) {
+            return true;
+        }
+
+        let mut inside = false;
+        for (a, b) in edges() {
+            if (a.y > point.y) != (b.y > point.y) {
+                let crossing_x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                if point.x < crossing_x {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+}
+
+// Any mix of shapes, each behind a `Box<dyn Shape>` since they differ in size.
+#[derive(Debug, Default)]
+pub struct Scene {
+    shapes: Vec<Box<dyn Shape>>,
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        Scene::default()
+    }
+
+    pub fn add<S: Shape + 'static>(&mut self, shape: S) -> &mut Scene {
+        self.shapes.push(Box::new(shape));
+        self
+    }
+
+    pub fn shapes(&self) -> &[Box<dyn Shape>] {
+        &self.shapes
+    }
+
+    // The sum of every shape's area; where shapes overlap, the shared area counts once per shape.
+    pub fn total_area(&self) -> f64 {
+        self.shapes.iter().map(|shape| shape.area()).sum()
+    }
+
+    /*
+        Smallest first. Shapes of equal area keep the order they were added in; a NaN area, e.g. from a NaN radius, sorts last.
+        `total_cmp` puts NaNs with the sign bit set before everything else, so every NaN is replaced by a positive one first.
+    */
+    pub fn sort_by_area(&mut self) {
+        let area = |shape: &dyn Shape| {
+            let area = shape.area();
+            if area.is_nan() { f64::NAN } else { area }
+        };
+        self.shapes.sort_by(|a, b| area(&**a).total_cmp(&area(&**b)));
+    }
+
+    pub fn bounding_box(&self) -> Option<Bounds> {
+        let mut boxes = self.shapes.iter().map(|shape| shape.bounding_box());
+        let first = boxes.next()?;
+        Some(boxes.fold(first, |all, bounds| all.union(&bounds)))
+    }
+
+    pub fn shapes_at(&self, point: Point) -> Vec<&dyn Shape> {
+        self.shapes.iter().filter(|shape| shape.contains_point(point)).map(|shape| &**shape).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_by_area_puts_nan_last() {
+        let mut scene = Scene::new();
+        scene
+            .add(Circle::new(Point::new(0.0, 0.0), f64::NAN))
+            .add(Square::new(0, 0, 3))
+            .add(Circle::new(Point::new(0.0, 0.0), 1.0))
+            .add(Square::new(5, 5, 1));
+        scene.sort_by_area();
+        let areas: Vec<f64> = scene.shapes().iter().map(|shape| shape.area()).collect();
+        assert_eq!(&areas[..3], &[1.0, PI, 9.0]);
+        assert!(areas[3].is_nan());
+    }
+
+    #[test]
+    fn negative_nan_areas_sort_last_too() {
+        let negative_nan = -f64::NAN;
+        assert!(negative_nan.is_sign_negative());
+        let mut scene = Scene::new();
+        scene
+            .add(Polygon::new(vec![Point::new(0.0, 0.0), Point::new(negative_nan, 0.0), Point::new(0.0, 1.0)]).unwrap())
+            .add(Circle::new(Point::new(0.0, 0.0), negative_nan))
+            .add(Square::new(0, 0, 2))
+            .add(Circle::new(Point::new(0.0, 0.0), f64::INFINITY));
+        scene.sort_by_area();
+        let areas: Vec<f64> = scene.shapes().iter().map(|shape| shape.area()).collect();
+        assert_eq!(&areas[..2], &[4.0, f64::INFINITY]);
+        assert!(areas[2..].iter().all(|area| area.is_nan()));
+    }
+
+    fn points(coordinates: &[(f64, f64)]) -> Vec<Point> {
+        coordinates.iter().map(|&(x, y)| Point::new(x, y)).collect()
+    }
+
+    #[test]
+    fn shoelace_area_ignores_the_winding_order() {
+        let square = points(&[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)]);
+        assert_eq!(signed_area(&square), 16.0);
+        let clockwise: Vec<Point> = square.iter().rev().cloned().collect();
+        assert_eq!(signed_area(&clockwise), -16.0);
+        assert_eq!(Polygon::new(clockwise).unwrap().area(), 16.0);
+
+        // An L shape: a 4x4 square with its 2x2 top right quarter cut out, away from the origin.
+        let l_shape = points(&[(10.0, 10.0), (12.0, 10.0), (12.0, 12.0), (14.0, 12.0), (14.0, 14.0), (10.0, 14.0)]);
+        let l_polygon = Polygon::new(l_shape.clone()).unwrap();
+        assert_eq!(l_polygon.area(), 12.0);
+        assert_eq!(Polygon::new(l_shape.into_iter().rev().collect()).unwrap().area(), 12.0);
+        assert_eq!(l_polygon.perimeter(), 16.0);
+
+        let triangle = Triangle::new(Point::new(0.0, 0.0), Point::new(0.0, 3.0), Point::new(4.0, 0.0));
+        assert_eq!((triangle.area(), triangle.perimeter()), (6.0, 12.0));
+        assert!(Polygon::new(points(&[(0.0, 0.0), (1.0, 1.0)])).is_none());
+        assert_eq!(Polygon::new(points(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)])).unwrap().area(), 0.0);
+    }
+
+    #[test]
+    fn polygon_edges_and_corners_are_inside() {
+        let l_shape = Polygon::new(points(&[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (4.0, 2.0), (4.0, 4.0), (0.0, 4.0)])).unwrap();
+        for point in l_shape.points() {
+            assert!(l_shape.contains_point(*point), "corner {:?}", point);
+        }
+        for &(x, y) in &[(1.0, 0.0), (2.0, 1.0), (3.0, 2.0), (4.0, 3.0), (2.0, 4.0), (0.0, 2.0)] {
+            assert!(l_shape.contains_point(Point::new(x, y)), "edge point ({}, {})", x, y);
+        }
+        for &(x, y) in &[(1.0, 1.0), (3.0, 3.0), (1.0, 3.0)] {
+            assert!(l_shape.contains_point(Point::new(x, y)), "inner point ({}, {})", x, y);
+        }
+        // The cut-out corner, points just past the edges, and points level with a corner but outside.
+        for &(x, y) in &[(3.0, 1.0), (2.5, 0.0), (4.5, 3.0), (-0.1, 2.0), (5.0, 2.0), (5.0, 0.0), (-1.0, 4.0)] {
+            assert!(!l_shape.contains_point(Point::new(x, y)), "outer point ({}, {})", x, y);
+        }
+
+        let triangle = Triangle::new(Point::new(0.0, 0.0), Point::new(4.0, 0.0), Point::new(0.0, 4.0));
+        assert!(triangle.contains_point(Point::new(2.0, 2.0)) && triangle.contains_point(Point::new(4.0, 0.0)));
+        assert!(!triangle.contains_point(Point::new(2.1, 2.0)));
+    }
+}
@@ -0,0 +1,167 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt;
+
+/*
+    A global allocator is the piece of code every `Box`, `Vec` and `String` ends up calling when it needs heap memory.
+    `CountingAllocator` forwards every request to the system allocator and keeps a tally on the side,
+    so moves (which never touch the heap) and clones (which do) become measurable.
+
+    The tallies are kept per thread, so a measurement is not disturbed by whatever other threads allocate in the meantime.
+    Thread-locals initialized with `const` need no heap memory of their own, which matters inside an allocator.
+*/
+pub struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+    static DEALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+    static REALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+    static BYTES_ALLOCATED: Cell<u64> = const { Cell::new(0) };
+    static BYTES_DEALLOCATED: Cell<u64> = const { Cell::new(0) };
+}
+
+// `try_with` rather than `with`: the allocator is still called while a thread's locals are being torn down.
+fn bump(counter: &'static std::thread::LocalKey<Cell<u64>>, amount: u64) {
+    let _ = counter.try_with(|cell| cell.set(cell.get() + amount));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            bump(&ALLOCATIONS, 1);
+            bump(&BYTES_ALLOCATED, layout.size() as u64);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            bump(&ALLOCATIONS, 1);
+            bump(&BYTES_ALLOCATED, layout.size() as u64);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        bump(&DEALLOCATIONS, 1);
+        bump(&BYTES_DEALLOCATED, layout.size() as u64);
+    }
+
+    // A successful realloc is counted as giving back the old block and taking the new one.
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            bump(&REALLOCATIONS, 1);
+            bump(&BYTES_DEALLOCATED, layout.size() as u64);
+            bump(&BYTES_ALLOCATED, new_size as u64);
+        }
+        new_ptr
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocationStats {
+    pub allocations: u64,
+    pub deallocations: u64,
+    pub reallocations: u64,
+    pub bytes_allocated: u64,
+    pub bytes_deallocated: u64,
+}
+
+impl AllocationStats {
+    // Bytes still held at the end of the measured block; negative if it freed memory allocated before it started.
+    pub fn net_bytes(&self) -> i64 {
+        self.bytes_allocated as i64 - self.bytes_deallocated as i64
+    }
+
+    pub fn is_allocation_free(&self) -> bool {
+        self.allocations == 0 && self.reallocations == 0
+    }
+
+    fn since(&self, earlier: &AllocationStats) -> AllocationStats {
+        AllocationStats {
+            allocations: self.allocations - earlier.allocations,
+            deallocations: self.deallocations - earlier.deallocations,
+            reallocations: self.reallocations - earlier.reallocations,
+            bytes_allocated: self.bytes_allocated - earlier.bytes_allocated,
+            bytes_deallocated: self.bytes_deallocated - earlier.bytes_deallocated,
+        }
+    }
+}
+
+impl fmt::Display for AllocationStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} allocs, {} deallocs, {} reallocs, {} bytes allocated, {} bytes freed",
+            self.allocations, self.deallocations, self.reallocations, self.bytes_allocated, self.bytes_deallocated
+        )
+    }
+}
+
+// Running totals for the current thread since it started.
+pub fn snapshot() -> AllocationStats {
+    AllocationStats {
+        allocations: ALLOCATIONS.with(Cell::get),
+        deallocations: DEALLOCATIONS.with(Cell::get),
+        reallocations: REALLOCATIONS.with(Cell::get),
+        bytes_allocated: BYTES_ALLOCATED.with(Cell::get),
+        bytes_deallocated: BYTES_DEALLOCATED.with(Cell::get),
+    }
+}
+
+/*
+    Runs `f` and reports what it did to the heap on this thread. Only meaningful when `CountingAllocator`
+    is registered with `#[global_allocator]`; otherwise every count stays at zero.
+
+    Whatever `f` returns is handed back and dropped by the caller, outside the measured block,
+    so returning a value shows up as an allocation without its matching deallocation.
+*/
+pub fn measure<F, R>(f: F) -> (R, AllocationStats)
+where
+    F: FnOnce() -> R,
+{
+    let before = snapshot();
+    let result = f();
+    let after = snapshot();
+
+    (result, after.since(&before))
+}
+
+// These rely on `main.rs` registering `CountingAllocator`, which it does for the test build too.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[inline(never)]
+    fn pass_through(value: String) -> String {
+        value
+    }
+
+    #[test]
+    fn moving_a_string_does_not_allocate() {
+        let text = String::from("hello, world");
+        let (text, moved) = measure(|| pass_through(text));
+        assert!(moved.is_allocation_free(), "a move allocated: {}", moved);
+
+        let (boxed, moved) = measure(move || Box::new(text));
+        assert_eq!(moved.allocations, 1, "boxing allocates only the box: {}", moved);
+        let (_, moved) = measure(move || pass_through(*boxed));
+        assert!(moved.is_allocation_free(), "a move out of a box allocated: {}", moved);
+    }
+
+    #[test]
+    fn cloning_a_string_allocates_its_length() {
+        let text = String::from("hello, world");
+        let (copy, cloned) = measure(|| text.clone());
+        assert_eq!(cloned.allocations, 1);
+        assert_eq!(cloned.bytes_allocated, text.len() as u64);
+
+        let ((), dropped) = measure(move || drop(copy));
+        assert_eq!(dropped.deallocations, 1);
+        assert_eq!(dropped.net_bytes(), -(text.len() as i64));
+    }
+}
@@ -1,3 +1,15 @@
+mod alloc_counter;
+mod normalize;
+mod tracked;
+
+use alloc_counter::CountingAllocator;
+use normalize::Pipeline;
+use tracked::{Ledger, Tracked};
+
+// Route every heap allocation of this program through the counter so moves and clones can be measured below.
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
 fn main() {
     /*
         All programs have to manage the way they use a computer’s memory while running.
@@ -80,9 +92,59 @@ fn main() {
 
     // Passing a variable to a function will move or copy, just as assignment does. Returning values can also transfer ownership.
     let _str3 = takes_and_gives_back(_str2);
+
+    /*
+        A move only copies the pointer, length and capacity on the stack, while `clone` asks the allocator for a new heap buffer.
+        With the counting allocator registered we can check that claim instead of taking it on faith.
+    */
+    let (_str3, moved) = alloc_counter::measure(|| takes_and_gives_back(_str3));
+    let (_str3_cl, cloned) = alloc_counter::measure(|| _str3.clone());
+    println!("takes_and_gives_back: {} (allocation-free: {})", moved, moved.is_allocation_free());
+    println!("clone: {}", cloned);
+
+    let ((), dropped) = alloc_counter::measure(|| drop(_str3_cl));
+    println!("drop: {} (net {} bytes)", dropped, dropped.net_bytes());
+    println!("thread totals so far: {}", alloc_counter::snapshot());
+
+    /*
+        Ownership changes leave no trace at runtime, which makes them hard to follow in a larger program.
+        Wrapping a value in `Tracked` writes its lifecycle to a ledger that we can print as a timeline afterwards.
+    */
+    let ledger = Ledger::new();
+    let mut greeting = ledger.track("greeting", String::from("hello"));
+    greeting.get_mut().push_str(", world");
+
+    let greeting = takes_and_gives_back_tracked(greeting.move_to("takes_and_gives_back_tracked"));
+    let greeting_cl = greeting.clone();
+    println!("{} / {}", greeting.get(), greeting_cl.get());
+
+    let original_id = greeting.id();
+    takes_ownership(greeting.into_inner());
+    println!("still alive: {:?}", ledger.live());
+    drop(greeting_cl);
+
+    ledger.print_timeline();
+    println!("events for [{}]: {}", original_id, ledger.events_for(original_id).len());
+
+    /*
+        Handing a `String` back and forth keeps ownership in one place, but it means owning the text even when nothing changes it.
+        The normalization pipeline passes `Cow<str>` between its stages instead, so clean input goes all the way through as a borrow
+        and only the stages that really rewrite the text allocate.
+    */
+    let pipeline = Pipeline::standard();
+    for input in &["already clean", "  Hello,\t\tWORLD  ", "Straße", "\u{1112}\u{1161}\u{11AB}\u{1100}\u{1173}\u{11AF}"] {
+        let (normalized, stats) = alloc_counter::measure(|| pipeline.run(input));
+        println!("{:?} -> {} [{}]", input, normalized, stats);
+    }
+
     takes_ownership(_str3);
 }
 
+fn takes_and_gives_back_tracked(tracked: Tracked<String>) -> Tracked<String> {
+    println!("{} ({}) passed through", tracked.label(), tracked.id());
+    tracked
+}
+
 fn takes_ownership(str: String) {
     println!("{}", str);
 }
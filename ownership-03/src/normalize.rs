@@ -0,0 +1,376 @@
+use std::borrow::Cow;
+use std::fmt;
+
+/*
+    Passing a `String` in and getting it back out works, but it forces every caller to own the text even when nothing changes.
+    `Cow<str>` ("clone on write") holds either a borrowed `&str` or an owned `String`, so a stage can hand its input straight back
+    when there is nothing to do and only pay for a new buffer when it actually rewrites the text.
+
+    Each stage takes a `Cow` and returns one. A stage that can shorten the text in place (like trimming an owned string)
+    reuses the buffer it was given instead of allocating a new one.
+*/
+pub trait Stage {
+    fn name(&self) -> &'static str;
+
+    fn apply<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str>;
+}
+
+// Strips leading and trailing whitespace.
+pub struct Trim;
+
+impl Stage for Trim {
+    fn name(&self) -> &'static str {
+        "trim"
+    }
+
+    fn apply<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        match text {
+            Cow::Borrowed(s) => Cow::Borrowed(s.trim()),
+            Cow::Owned(mut s) => {
+                let end = s.trim_end().len();
+                s.truncate(end);
+                let start = s.len() - s.trim_start().len();
+                s.drain(..start);
+                Cow::Owned(s)
+            }
+        }
+    }
+}
+
+// Replaces every run of whitespace (tabs, newlines, no-break spaces, ...) with a single ASCII space.
+pub struct CollapseWhitespace;
+
+impl CollapseWhitespace {
+    fn needs_rewrite(text: &str) -> bool {
+        let mut previous_was_space = false;
+        for ch in text.chars() {
+            if ch.is_whitespace() {
+                if previous_was_space || ch != ' ' {
+                    return true;
+                }
+                previous_was_space = true;
+            } else {
+                previous_was_space = false;
+            }
+        }
+        false
+    }
+}
+
+impl Stage for CollapseWhitespace {
+    fn name(&self) -> &'static str {
+        "collapse-whitespace"
+    }
+
+    fn apply<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        if !CollapseWhitespace::needs_rewrite(&text) {
+            return text;
+        }
+
+        let mut out = String::with_capacity(text.len());
+        let mut in_whitespace = false;
+        for ch in text.chars() {
+            if ch.is_whitespace() {
+                if !in_whitespace {
+                    out.push(' ');
+                }
+                in_whitespace = true;
+            } else {
+                out.push(ch);
+                in_whitespace = false;
+            }
+        }
+        Cow::Owned(out)
+    }
+}
+
+/*
+    Case folding maps text to a form in which case differences disappear, for caseless comparison.
+    It is mostly the same as lowercasing, except for a handful of characters whose folded form differs:
+    'ß' folds to "ss" and the final sigma 'ς' folds to 'σ', so "STRASSE" and "straße" compare equal.
+*/
+pub struct CaseFold;
+
+impl CaseFold {
+    fn fold_char(ch: char, out: &mut String) {
+        match ch {
+            'ß' | 'ẞ' => out.push_str("ss"),
+            'ς' => out.push('σ'),
+            _ => out.extend(ch.to_lowercase()),
+        }
+    }
+
+    fn is_folded(ch: char) -> bool {
+        match ch {
+            'ß' | 'ẞ' | 'ς' => false,
+            _ => {
+                let mut lower = ch.to_lowercase();
+                lower.next() == Some(ch) && lower.next().is_none()
+            }
+        }
+    }
+}
+
+impl Stage for CaseFold {
+    fn name(&self) -> &'static str {
+        "case-fold"
+    }
+
+    fn apply<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        let first_change = match text.char_indices().find(|&(_, ch)| !CaseFold::is_folded(ch)) {
+            Some((index, _)) => index,
+            None => return text,
+        };
+
+        // Everything before the first character that changes can be copied over as one block.
+        let mut out = String::with_capacity(text.len());
+        out.push_str(&text[..first_change]);
+        for ch in text[first_change..].chars() {
+            CaseFold::fold_char(ch, &mut out);
+        }
+        Cow::Owned(out)
+    }
+}
+
+/*
+    Korean text can spell a syllable either as one precomposed character ('한', U+D55C) or as a sequence of conjoining jamo
+    (ᄒ U+1112, ᅡ U+1161, ᆫ U+11AB). Both look the same on screen but compare unequal byte for byte.
+    This stage composes leading consonant + vowel (+ trailing consonant) sequences into precomposed syllables,
+    using the arithmetic from the Unicode standard (section 3.12), which is what NFC does for Hangul.
+*/
+pub struct ComposeHangul;
+
+const S_BASE: u32 = 0xAC00;
+const L_BASE: u32 = 0x1100;
+const V_BASE: u32 = 0x1161;
+const T_BASE: u32 = 0x11A7;
+const L_COUNT: u32 = 19;
+const V_COUNT: u32 = 21;
+const T_COUNT: u32 = 28;
+const N_COUNT: u32 = V_COUNT * T_COUNT;
+const S_COUNT: u32 = L_COUNT * N_COUNT;
+
+impl ComposeHangul {
+    fn compose_pair(first: char, second: char) -> Option<char> {
+        let (first, second) = (first as u32, second as u32);
+
+        // leading consonant + vowel -> LV syllable
+        if (L_BASE..L_BASE + L_COUNT).contains(&first) && (V_BASE..V_BASE + V_COUNT).contains(&second) {
+            let index = (first - L_BASE) * N_COUNT + (second - V_BASE) * T_COUNT;
+            return std::char::from_u32(S_BASE + index);
+        }
+
+        // LV syllable + trailing consonant -> LVT syllable
+        let is_lv_syllable = (S_BASE..S_BASE + S_COUNT).contains(&first) && (first - S_BASE).is_multiple_of(T_COUNT);
+        if is_lv_syllable && (T_BASE + 1..T_BASE + T_COUNT).contains(&second) {
+            return std::char::from_u32(first + (second - T_BASE));
+        }
+
+        None
+    }
+
+    fn first_composition(text: &str) -> Option<usize> {
+        let mut previous: Option<(usize, char)> = None;
+        for (index, ch) in text.char_indices() {
+            if let Some((previous_index, previous_ch)) = previous {
+                if ComposeHangul::compose_pair(previous_ch, ch).is_some() {
+                    return Some(previous_index);
+                }
+            }
+            previous = Some((index, ch));
+        }
+        None
+    }
+}
+
+impl Stage for ComposeHangul {
+    fn name(&self) -> &'static str {
+        "compose-hangul"
+    }
+
+    fn apply<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        let start = match ComposeHangul::first_composition(&text) {
+            Some(start) => start,
+            None => return text,
+        };
+
+        let mut out = String::with_capacity(text.len());
+        out.push_str(&text[..start]);
+
+        let mut pending: Option<char> = None;
+        for ch in text[start..].chars() {
+            pending = match pending {
+                None => Some(ch),
+                Some(previous) => match ComposeHangul::compose_pair(previous, ch) {
+                    Some(composed) => Some(composed),
+                    None => {
+                        out.push(previous);
+                        Some(ch)
+                    }
+                },
+            };
+        }
+        if let Some(last) = pending {
+            out.push(last);
+        }
+
+        Cow::Owned(out)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StageReport {
+    pub stage: &'static str,
+    pub allocated: bool,
+}
+
+#[derive(Debug)]
+pub struct Normalized<'a> {
+    pub text: Cow<'a, str>,
+    pub stages: Vec<StageReport>,
+}
+
+impl<'a> Normalized<'a> {
+    pub fn allocating_stages(&self) -> Vec<&'static str> {
+        self.stages.iter().filter(|report| report.allocated).map(|report| report.stage).collect()
+    }
+}
+
+impl<'a> fmt::Display for Normalized<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.text {
+            Cow::Borrowed(_) => "borrowed",
+            Cow::Owned(_) => "owned",
+        };
+        write!(f, "{:?} ({}; allocated in: {:?})", self.text, kind, self.allocating_stages())
+    }
+}
+
+pub struct Pipeline {
+    stages: Vec<Box<dyn Stage>>,
+}
+
+impl Pipeline {
+    // No stages at all, so `run` hands every input back borrowed; add stages with `then`, or start from `standard`.
+    pub fn new() -> Pipeline {
+        Pipeline { stages: Vec::new() }
+    }
+
+    // trim, collapse whitespace, case fold and compose Hangul, in that order
+    pub fn standard() -> Pipeline {
+        Pipeline::new()
+            .then(Trim)
+            .then(CollapseWhitespace)
+            .then(CaseFold)
+            .then(ComposeHangul)
+    }
+
+    pub fn then<S: Stage + 'static>(mut self, stage: S) -> Pipeline {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /*
+        A stage allocated if it turned borrowed text into owned text, or if the owned buffer it returned
+        is not the one it was given.
+    */
+    pub fn run<'a>(&self, input: &'a str) -> Normalized<'a> {
+        let mut text = Cow::Borrowed(input);
+        let mut stages = Vec::with_capacity(self.stages.len());
+
+        for stage in &self.stages {
+            let owned_buffer = match text {
+                Cow::Borrowed(_) => None,
+                Cow::Owned(ref s) => Some(s.as_ptr()),
+            };
+
+            text = stage.apply(text);
+
+            let allocated = match (owned_buffer, &text) {
+                (_, Cow::Borrowed(_)) => false,
+                (None, Cow::Owned(_)) => true,
+                (Some(before), Cow::Owned(after)) => before != after.as_ptr(),
+            };
+            stages.push(StageReport { stage: stage.name(), allocated });
+        }
+
+        Normalized { text, stages }
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Pipeline {
+        Pipeline::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_input_goes_through_borrowed() {
+        let clean = Pipeline::standard().run("already clean");
+        assert_eq!(clean.text, "already clean");
+        assert!(matches!(clean.text, Cow::Borrowed(_)));
+        assert!(clean.allocating_stages().is_empty());
+        assert_eq!(clean.stages.len(), 4);
+    }
+
+    #[test]
+    fn only_the_stages_that_rewrite_allocate() {
+        let normalized = Pipeline::standard().run("  Hello,\t\tWORLD  ");
+        assert_eq!(normalized.text, "hello, world");
+        assert_eq!(normalized.allocating_stages(), vec!["collapse-whitespace", "case-fold"]);
+
+        // Trimming borrowed text just narrows the slice.
+        let trimmed = Pipeline::new().then(Trim).run("  padded  ");
+        assert_eq!(trimmed.text, "padded");
+        assert!(matches!(trimmed.text, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn trim_reuses_an_owned_buffer() {
+        let normalized = Pipeline::new().then(CaseFold).then(Trim).run("  PADDED\u{a0}");
+        assert_eq!(normalized.text, "padded");
+        assert_eq!(normalized.allocating_stages(), vec!["case-fold"]);
+    }
+
+    #[test]
+    fn whitespace_runs_become_one_space() {
+        let collapse = |text: &'static str| CollapseWhitespace.apply(Cow::Borrowed(text));
+        assert_eq!(collapse("a \t\n b\u{a0}c"), "a b c");
+        assert_eq!(collapse("\t"), " ");
+        assert!(matches!(collapse("one two three"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn case_folding_handles_sharp_s_and_final_sigma() {
+        let fold = |text: &'static str| CaseFold.apply(Cow::Borrowed(text));
+        assert_eq!(fold("Straße"), "strasse");
+        assert_eq!(fold("STRASSE"), fold("straẞe"));
+        assert_eq!(fold("ΟΔΟΣ"), "οδοσ");
+        assert_eq!(fold("οδος"), "οδοσ");
+        assert!(matches!(fold("lower ünïcode"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn conjoining_jamo_compose_into_syllables() {
+        let compose = |text: &'static str| ComposeHangul.apply(Cow::Borrowed(text));
+        assert_eq!(compose("\u{1112}\u{1161}\u{11AB}\u{1100}\u{1173}\u{11AF}"), "한글");
+        // A lone vowel or trailing consonant has nothing to join with.
+        assert_eq!(compose("x\u{1161}\u{11AB}"), "x\u{1161}\u{11AB}");
+        // An LVT syllable can't take another trailing consonant.
+        assert_eq!(compose("\u{D55C}\u{11AB}"), "\u{D55C}\u{11AB}");
+        assert!(matches!(compose("한글"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn new_and_default_are_both_empty() {
+        for pipeline in &[Pipeline::new(), Pipeline::default()] {
+            let normalized = pipeline.run("  As Is  ");
+            assert_eq!(normalized.text, "  As Is  ");
+            assert!(normalized.stages.is_empty());
+        }
+    }
+}
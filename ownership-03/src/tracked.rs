@@ -0,0 +1,278 @@
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/*
+    `Tracked<T>` wraps a value and reports what happens to it to a shared `Ledger`: when it is created, cloned, borrowed,
+    handed over and dropped. Each wrapped value gets its own id, and a clone gets a fresh id that remembers where it came from.
+
+    A move in Rust is a plain bit copy that runs no code, so the compiler gives us no hook to observe it.
+    Moves therefore only appear in the ledger when they are announced with `move_to`.
+    In the same way, borrows are only recorded when they go through `get` and `get_mut`.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    Created,
+    Cloned { source: u64 },
+    Borrowed,
+    BorrowedMut,
+    Moved { to: String },
+    Unwrapped,
+    Dropped,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub sequence: usize,
+    pub id: u64,
+    pub label: String,
+    pub kind: EventKind,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:<3} [{}] {:<12} ", self.sequence, self.id, self.label)?;
+        match self.kind {
+            EventKind::Created => write!(f, "created"),
+            EventKind::Cloned { source } => write!(f, "cloned from [{}]", source),
+            EventKind::Borrowed => write!(f, "borrowed (&)"),
+            EventKind::BorrowedMut => write!(f, "borrowed (&mut)"),
+            EventKind::Moved { ref to } => write!(f, "moved into {}", to),
+            EventKind::Unwrapped => write!(f, "unwrapped; no longer tracked"),
+            EventKind::Dropped => write!(f, "dropped"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct LedgerState {
+    next_id: u64,
+    events: Vec<Event>,
+}
+
+// Cloning a `Ledger` gives another handle to the same log, so it can be passed around freely.
+#[derive(Clone, Debug, Default)]
+pub struct Ledger {
+    state: Arc<Mutex<LedgerState>>,
+}
+
+impl Ledger {
+    pub fn new() -> Ledger {
+        Ledger::default()
+    }
+
+    pub fn track<T>(&self, label: &str, value: T) -> Tracked<T> {
+        let id = self.allocate_id();
+        self.record(id, label, EventKind::Created);
+
+        Tracked {
+            id,
+            label: label.to_string(),
+            value: Some(value),
+            ledger: self.clone(),
+        }
+    }
+
+    pub fn events(&self) -> Vec<Event> {
+        self.lock().events.clone()
+    }
+
+    pub fn events_for(&self, id: u64) -> Vec<Event> {
+        self.events().into_iter().filter(|event| event.id == id).collect()
+    }
+
+    // Ids of values that were created or cloned but have neither been dropped nor unwrapped yet.
+    pub fn live(&self) -> Vec<u64> {
+        let mut live = Vec::new();
+        for event in self.events() {
+            match event.kind {
+                EventKind::Created | EventKind::Cloned { .. } => live.push(event.id),
+                EventKind::Dropped | EventKind::Unwrapped => live.retain(|&id| id != event.id),
+                _ => (),
+            }
+        }
+        live
+    }
+
+    pub fn timeline(&self) -> String {
+        self.events().iter().map(|event| format!("{}\n", event)).collect()
+    }
+
+    pub fn print_timeline(&self) {
+        print!("{}", self.timeline());
+    }
+
+    /*
+        A panic while the lock is held poisons it. The ledger only ever appends events and bumps a counter, so its state
+        is still consistent afterwards, and carrying on keeps a drop during unwinding from turning into an abort.
+    */
+    fn lock(&self) -> MutexGuard<'_, LedgerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn allocate_id(&self) -> u64 {
+        let mut state = self.lock();
+        state.next_id += 1;
+        state.next_id
+    }
+
+    fn record(&self, id: u64, label: &str, kind: EventKind) {
+        let mut state = self.lock();
+        let sequence = state.events.len();
+        state.events.push(Event { sequence, id, label: label.to_string(), kind });
+    }
+}
+
+pub struct Tracked<T> {
+    id: u64,
+    label: String,
+    // Only `None` after `into_inner` has taken the value out, just before the wrapper is dropped.
+    value: Option<T>,
+    ledger: Ledger,
+}
+
+impl<T> Tracked<T> {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn get(&self) -> &T {
+        self.ledger.record(self.id, &self.label, EventKind::Borrowed);
+        self.value.as_ref().unwrap()
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.ledger.record(self.id, &self.label, EventKind::BorrowedMut);
+        self.value.as_mut().unwrap()
+    }
+
+    // Records that ownership is being handed to `to` (a function, a struct field, another thread) and passes the value on.
+    pub fn move_to(self, to: &str) -> Tracked<T> {
+        self.ledger.record(self.id, &self.label, EventKind::Moved { to: to.to_string() });
+        self
+    }
+
+    pub fn into_inner(mut self) -> T {
+        self.ledger.record(self.id, &self.label, EventKind::Unwrapped);
+        self.value.take().unwrap()
+    }
+}
+
+impl<T: Clone> Clone for Tracked<T> {
+    fn clone(&self) -> Tracked<T> {
+        let id = self.ledger.allocate_id();
+        self.ledger.record(id, &self.label, EventKind::Cloned { source: self.id });
+
+        Tracked {
+            id,
+            label: self.label.clone(),
+            value: self.value.clone(),
+            ledger: self.ledger.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Tracked<T> {
+    fn drop(&mut self) {
+        if self.value.is_some() {
+            self.ledger.record(self.id, &self.label, EventKind::Dropped);
+        }
+    }
+}
+
+// Formatting goes straight to the value so that printing does not add a borrow event to the ledger.
+impl<T: fmt::Debug> fmt::Debug for Tracked<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Tracked")
+            .field("id", &self.id)
+            .field("label", &self.label)
+            .field("value", self.value.as_ref().unwrap())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn kinds(ledger: &Ledger, id: u64) -> Vec<EventKind> {
+        ledger.events_for(id).into_iter().map(|event| event.kind).collect()
+    }
+
+    #[test]
+    fn clones_get_their_own_id_and_drops_are_counted() {
+        let ledger = Ledger::new();
+        let original = ledger.track("name", String::from("ann"));
+        let copy = original.clone();
+        let other = copy.clone();
+        assert_eq!((original.id(), copy.id(), other.id()), (1, 2, 3));
+        assert_eq!(ledger.live(), vec![1, 2, 3]);
+        assert_eq!(kinds(&ledger, 3), vec![EventKind::Cloned { source: 2 }]);
+
+        drop(copy);
+        assert_eq!(ledger.live(), vec![1, 3]);
+        assert_eq!(original.into_inner(), "ann");
+        assert_eq!(ledger.live(), vec![3]);
+        assert_eq!(kinds(&ledger, 1), vec![EventKind::Created, EventKind::Unwrapped]);
+        drop(other);
+        assert!(ledger.live().is_empty());
+
+        let sequences: Vec<usize> = ledger.events().iter().map(|event| event.sequence).collect();
+        assert_eq!(sequences, (0..sequences.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn borrows_and_moves_are_only_recorded_when_announced() {
+        let ledger = Ledger::new();
+        let mut list = ledger.track("list", vec![1]);
+        list.get_mut().push(2);
+        let list = list.move_to("worker");
+        assert_eq!(list.get(), &vec![1, 2]);
+        let _ = format!("{:?}", list);
+        let id = list.id();
+        drop(list);
+        assert_eq!(kinds(&ledger, id), vec![
+            EventKind::Created,
+            EventKind::BorrowedMut,
+            EventKind::Moved { to: "worker".to_string() },
+            EventKind::Borrowed,
+            EventKind::Dropped,
+        ]);
+    }
+
+    #[test]
+    fn ledger_stays_usable_after_a_panic_with_the_lock_held() {
+        let ledger = Ledger::new();
+        let before = ledger.track("before", 1);
+        let poisoner = ledger.clone();
+        let result = thread::spawn(move || {
+            let _guard = poisoner.lock();
+            panic!("panicking while holding the ledger lock");
+        }).join();
+        assert!(result.is_err());
+        assert!(ledger.state.is_poisoned());
+
+        let after = ledger.track("after", 2);
+        assert_eq!(*after.get() + *before.get(), 3);
+        drop(before);
+        assert_eq!(ledger.live(), vec![after.id()]);
+        assert!(ledger.timeline().contains("dropped"));
+    }
+
+    #[test]
+    fn a_value_dropped_while_unwinding_is_still_recorded() {
+        let ledger = Ledger::new();
+        let inside = ledger.clone();
+        let result = thread::spawn(move || {
+            let _value = inside.track("unwound", String::from("x"));
+            panic!("unwinding past a tracked value");
+        }).join();
+        assert!(result.is_err());
+        assert_eq!(kinds(&ledger, 1), vec![EventKind::Created, EventKind::Dropped]);
+        assert!(ledger.live().is_empty());
+    }
+}
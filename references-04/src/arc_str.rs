@@ -0,0 +1,281 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, RangeBounds};
+use std::sync::Arc;
+
+use checked_slice::{CheckedSlice, SliceError};
+
+/*
+    A `&str` slice borrows from the `String` it points into, so it can't outlive it: storing one in a struct
+    drags a lifetime parameter along, and sending one to another thread needs the owner to live for `'static`.
+
+    `ArcStr` owns its text instead. It keeps the whole buffer behind an `Arc` together with the byte range it covers,
+    so cloning it or taking a sub-slice only bumps a reference count and adjusts two offsets; the text is never copied.
+    The buffer is freed when the last `ArcStr` pointing into it is dropped. Since `Arc<str>` is `Send + Sync`, so is `ArcStr`.
+*/
+#[derive(Clone)]
+pub struct ArcStr {
+    buffer: Arc<str>,
+    start: usize,
+    end: usize,
+}
+
+impl ArcStr {
+    pub fn new(text: &str) -> ArcStr {
+        ArcStr::from(Arc::<str>::from(text))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.buffer[self.start..self.end]
+    }
+
+    // Sub-slicing in O(1). Offsets are relative to this view, and errors are reported like `CheckedSlice::try_slice`.
+    pub fn try_substr<R: RangeBounds<usize>>(&self, range: R) -> Result<ArcStr, SliceError> {
+        let sub = self.as_str().try_slice(range)?;
+        Ok(self.slice_ref(sub).unwrap())
+    }
+
+    // Panics on the same inputs as `&s[range]` would.
+    pub fn substr<R: RangeBounds<usize>>(&self, range: R) -> ArcStr {
+        match self.try_substr(range) {
+            Ok(sub) => sub,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /*
+        Turns a `&str` that was borrowed from this view back into an owning `ArcStr`, for example the pieces produced by
+        `split_whitespace` or `lines`. Returns `None` if `sub` does not point into this view.
+    */
+    pub fn slice_ref(&self, sub: &str) -> Option<ArcStr> {
+        let own = self.as_str();
+        let own_start = own.as_ptr() as usize;
+        let sub_start = sub.as_ptr() as usize;
+
+        if sub_start < own_start || sub_start + sub.len() > own_start + own.len() {
+            return None;
+        }
+
+        let start = self.start + (sub_start - own_start);
+        Some(ArcStr {
+            buffer: Arc::clone(&self.buffer),
+            start,
+            end: start + sub.len(),
+        })
+    }
+
+    pub fn split_at(&self, mid: usize) -> (ArcStr, ArcStr) {
+        (self.substr(..mid), self.substr(mid..))
+    }
+
+    pub fn split_whitespace(&self) -> Vec<ArcStr> {
+        self.as_str().split_whitespace().map(|word| self.slice_ref(word).unwrap()).collect()
+    }
+
+    pub fn lines(&self) -> Vec<ArcStr> {
+        self.as_str().lines().map(|line| self.slice_ref(line).unwrap()).collect()
+    }
+
+    // Whether both views point into the same buffer, regardless of which part of it they cover.
+    pub fn shares_buffer(&self, other: &ArcStr) -> bool {
+        Arc::ptr_eq(&self.buffer, &other.buffer)
+    }
+
+    // Number of views (including this one) keeping the buffer alive.
+    pub fn buffer_refs(&self) -> usize {
+        Arc::strong_count(&self.buffer)
+    }
+
+    // Copies just this view into a buffer of its own, so a small view no longer keeps a large buffer alive.
+    pub fn detach(&self) -> ArcStr {
+        ArcStr::new(self.as_str())
+    }
+}
+
+impl From<Arc<str>> for ArcStr {
+    fn from(buffer: Arc<str>) -> ArcStr {
+        let end = buffer.len();
+        ArcStr { buffer, start: 0, end }
+    }
+}
+
+impl From<String> for ArcStr {
+    fn from(text: String) -> ArcStr {
+        ArcStr::from(Arc::<str>::from(text))
+    }
+}
+
+impl From<&str> for ArcStr {
+    fn from(text: &str) -> ArcStr {
+        ArcStr::new(text)
+    }
+}
+
+impl Deref for ArcStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for ArcStr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for ArcStr {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Default for ArcStr {
+    fn default() -> ArcStr {
+        ArcStr::new("")
+    }
+}
+
+// Comparison and hashing look only at the text, so equal views from different buffers are equal (and `Borrow<str>` holds).
+impl PartialEq for ArcStr {
+    fn eq(&self, other: &ArcStr) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for ArcStr {}
+
+impl PartialEq<str> for ArcStr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for ArcStr {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialOrd for ArcStr {
+    fn partial_cmp(&self, other: &ArcStr) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ArcStr {
+    fn cmp(&self, other: &ArcStr) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for ArcStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl fmt::Display for ArcStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Debug for ArcStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+    use std::thread;
+
+    fn hash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn views_share_one_buffer_until_detached() {
+        let shared = ArcStr::from(String::from("some string"));
+        let (head, tail) = shared.split_at(4);
+        assert_eq!((head.as_str(), tail.as_str()), ("some", " string"));
+        assert!(head.shares_buffer(&tail));
+        assert_eq!(shared.buffer_refs(), 3);
+
+        let detached = tail.detach();
+        assert_eq!(detached, tail);
+        assert!(!detached.shares_buffer(&shared));
+        assert_eq!(detached.buffer_refs(), 1);
+
+        drop(head);
+        drop(tail);
+        assert_eq!(shared.buffer_refs(), 1);
+    }
+
+    #[test]
+    fn substrings_are_relative_to_the_view() {
+        let text = ArcStr::new("안녕하세요 world");
+        let world = text.substr(16..);
+        assert_eq!(world, "world");
+        assert_eq!(world.substr(1..3), "or");
+        assert_eq!(world.try_substr(2..9), Err(SliceError::OutOfBounds { index: 9, len: 5 }));
+        assert_eq!(text.try_substr(..4), Err(SliceError::NotCharBoundary { index: 4, before: 3, after: 6 }));
+        assert_eq!(text.substr(3..6).substr(..), "녕");
+    }
+
+    #[test]
+    #[should_panic(expected = "byte index 4 is inside a character")]
+    fn substr_panics_like_indexing() {
+        ArcStr::new("안녕").substr(..4);
+    }
+
+    #[test]
+    fn slice_ref_only_accepts_text_from_the_view() {
+        let text = ArcStr::new("first line\nsecond line");
+        let second = text.lines().pop().unwrap();
+        assert_eq!(second, "second line");
+        assert!(second.slice_ref(&text[..5]).is_none());
+        assert!(second.slice_ref("second").is_none());
+        assert_eq!(text.slice_ref(&second[7..]).unwrap(), "line");
+
+        let words = second.split_whitespace();
+        assert_eq!(words, vec!["second", "line"]);
+        assert!(words.iter().all(|word| word.shares_buffer(&text)));
+    }
+
+    #[test]
+    fn equality_and_hashing_ignore_the_buffer() {
+        let one = ArcStr::new("abc abc");
+        let (left, right) = (one.substr(..3), one.substr(4..));
+        let other = ArcStr::new("abc");
+        assert_eq!(left, right);
+        assert_eq!(left, other);
+        assert_eq!(hash_of(&left), hash_of(&other));
+        assert_eq!(hash_of(&left), hash_of("abc"));
+        assert!(left < one.substr(1..));
+
+        let mut set = HashSet::new();
+        set.insert(left);
+        assert!(!set.insert(right));
+        assert!(set.contains("abc"));
+        assert_eq!(ArcStr::default(), "");
+    }
+
+    #[test]
+    fn views_can_move_to_another_thread() {
+        let shared = ArcStr::new("hello from main");
+        let tail = shared.substr(6..);
+        let handle = thread::spawn(move || format!("{}!", tail.substr(..4)));
+        assert_eq!(handle.join().unwrap(), "from!");
+        assert_eq!(shared.buffer_refs(), 1);
+    }
+}
@@ -0,0 +1,235 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+/*
+    `&s[a..b]` panics when `b` is past the end of the string or when either offset lands inside a multi-byte UTF-8 character.
+    That is fine for offsets we computed ourselves, but offsets that come from a user or from another system need to be checked.
+    `CheckedSlice` adds non-panicking versions of string slicing to `str` (and, through deref, to `String`).
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SliceError {
+    // `index` is past the end of a string that is `len` bytes long.
+    OutOfBounds { index: usize, len: usize },
+    // The range starts after it ends.
+    Inverted { start: usize, end: usize },
+    // `index` falls inside a character; `before` and `after` are the nearest character boundaries around it.
+    NotCharBoundary { index: usize, before: usize, after: usize },
+    // A bound can't be turned into an offset without overflowing, as with `..=usize::MAX`.
+    Overflow,
+}
+
+impl fmt::Display for SliceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SliceError::OutOfBounds { index, len } => {
+                write!(f, "byte index {} is out of bounds of a string of length {}", index, len)
+            }
+            SliceError::Inverted { start, end } => write!(f, "range starts at {} but ends at {}", start, end),
+            SliceError::NotCharBoundary { index, before, after } => write!(
+                f,
+                "byte index {} is inside a character; the nearest boundaries are {} and {}",
+                index, before, after
+            ),
+            SliceError::Overflow => write!(f, "the range reaches past the largest possible index"),
+        }
+    }
+}
+
+impl Error for SliceError {}
+
+// How an offset in the middle of a character is moved onto a boundary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Snap {
+    // Shrink the range so it only covers characters that lie entirely inside it.
+    Inward,
+    // Grow the range so it covers every character it touches.
+    Outward,
+}
+
+pub trait CheckedSlice {
+    fn try_slice<R: RangeBounds<usize>>(&self, range: R) -> Result<&str, SliceError>;
+
+    // Moves out-of-range offsets to the nearest end and mid-character offsets inward. Never fails.
+    fn slice_clamped<R: RangeBounds<usize>>(&self, range: R) -> &str;
+
+    // Like `try_slice`, but mid-character offsets are moved onto a boundary instead of being rejected.
+    fn slice_snapped<R: RangeBounds<usize>>(&self, range: R, snap: Snap) -> Result<&str, SliceError>;
+
+    fn floor_boundary(&self, index: usize) -> usize;
+
+    fn ceil_boundary(&self, index: usize) -> usize;
+}
+
+// Turns `range` into half-open byte offsets; `None` for a bound that overflows.
+fn offsets<R: RangeBounds<usize>>(range: &R, len: usize) -> (Option<usize>, Option<usize>) {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => Some(start),
+        Bound::Excluded(&start) => start.checked_add(1),
+        Bound::Unbounded => Some(0),
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.checked_add(1),
+        Bound::Excluded(&end) => Some(end),
+        Bound::Unbounded => Some(len),
+    };
+    (start, end)
+}
+
+fn resolve<R: RangeBounds<usize>>(range: &R, len: usize) -> Result<(usize, usize), SliceError> {
+    match offsets(range, len) {
+        (Some(start), Some(end)) => Ok((start, end)),
+        _ => Err(SliceError::Overflow),
+    }
+}
+
+fn check_bounds(start: usize, end: usize, len: usize) -> Result<(), SliceError> {
+    if start > len {
+        return Err(SliceError::OutOfBounds { index: start, len });
+    }
+    if end > len {
+        return Err(SliceError::OutOfBounds { index: end, len });
+    }
+    if start > end {
+        return Err(SliceError::Inverted { start, end });
+    }
+    Ok(())
+}
+
+impl CheckedSlice for str {
+    fn try_slice<R: RangeBounds<usize>>(&self, range: R) -> Result<&str, SliceError> {
+        let (start, end) = resolve(&range, self.len())?;
+        check_bounds(start, end, self.len())?;
+
+        for &index in &[start, end] {
+            if !self.is_char_boundary(index) {
+                return Err(SliceError::NotCharBoundary {
+                    index,
+                    before: self.floor_boundary(index),
+                    after: self.ceil_boundary(index),
+                });
+            }
+        }
+
+        Ok(&self[start..end])
+    }
+
+    fn slice_clamped<R: RangeBounds<usize>>(&self, range: R) -> &str {
+        // A bound that overflows is past the end anyway, so it clamps like any other.
+        let (start, end) = offsets(&range, self.len());
+        let (start, end) = (start.unwrap_or(usize::MAX), end.unwrap_or(usize::MAX));
+        let end = end.min(self.len());
+        let start = start.min(end);
+
+        let end = self.floor_boundary(end);
+        let start = self.ceil_boundary(start).min(end);
+        &self[start..end]
+    }
+
+    fn slice_snapped<R: RangeBounds<usize>>(&self, range: R, snap: Snap) -> Result<&str, SliceError> {
+        let (start, end) = resolve(&range, self.len())?;
+        check_bounds(start, end, self.len())?;
+
+        let (start, end) = match snap {
+            Snap::Inward => {
+                let end = self.floor_boundary(end);
+                (self.ceil_boundary(start).min(end), end)
+            }
+            Snap::Outward => (self.floor_boundary(start), self.ceil_boundary(end)),
+        };
+        Ok(&self[start..end])
+    }
+
+    // The largest character boundary at or before `index` (the length if `index` is past the end).
+    fn floor_boundary(&self, index: usize) -> usize {
+        if index >= self.len() {
+            return self.len();
+        }
+        (0..=index).rev().find(|&i| self.is_char_boundary(i)).unwrap_or(0)
+    }
+
+    // The smallest character boundary at or after `index` (the length if `index` is past the end).
+    fn ceil_boundary(&self, index: usize) -> usize {
+        if index >= self.len() {
+            return self.len();
+        }
+        (index..self.len()).find(|&i| self.is_char_boundary(i)).unwrap_or(self.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every character of "안녕하세요" is three bytes long.
+    const HANGUL: &str = "안녕하세요";
+
+    #[test]
+    fn slices_on_boundaries_like_indexing_does() {
+        let text = "string literal";
+        assert_eq!(text.try_slice(..6), Ok("string"));
+        assert_eq!(text.try_slice(7..), Ok("literal"));
+        assert_eq!(text.try_slice(..), Ok(text));
+        assert_eq!(text.try_slice(14..14), Ok(""));
+        assert_eq!(HANGUL.try_slice(3..=5), Ok("녕"));
+        assert_eq!(HANGUL.try_slice((Bound::Excluded(2), Bound::Excluded(6))), Ok("녕"));
+    }
+
+    #[test]
+    fn out_of_range_and_inverted_ranges_are_errors() {
+        let text = "string literal";
+        assert_eq!(text.try_slice(7..99), Err(SliceError::OutOfBounds { index: 99, len: 14 }));
+        assert_eq!(text.try_slice(15..), Err(SliceError::OutOfBounds { index: 15, len: 14 }));
+        assert_eq!(text.try_slice(..=14), Err(SliceError::OutOfBounds { index: 15, len: 14 }));
+        let (start, end) = (6, 2);
+        assert_eq!(text.try_slice(start..end), Err(SliceError::Inverted { start: 6, end: 2 }));
+        assert_eq!(
+            text.try_slice(7..99).unwrap_err().to_string(),
+            "byte index 99 is out of bounds of a string of length 14"
+        );
+    }
+
+    #[test]
+    fn offsets_inside_a_character_name_the_nearest_boundaries() {
+        assert_eq!(HANGUL.try_slice(..4), Err(SliceError::NotCharBoundary { index: 4, before: 3, after: 6 }));
+        assert_eq!(HANGUL.try_slice(1..3), Err(SliceError::NotCharBoundary { index: 1, before: 0, after: 3 }));
+        assert_eq!(HANGUL.try_slice(14..), Err(SliceError::NotCharBoundary { index: 14, before: 12, after: 15 }));
+        assert_eq!("é".try_slice(1..), Err(SliceError::NotCharBoundary { index: 1, before: 0, after: 2 }));
+    }
+
+    #[test]
+    fn bounds_past_usize_max_are_reported_as_overflow() {
+        assert_eq!("abc".try_slice(..=usize::MAX), Err(SliceError::Overflow));
+        assert_eq!("abc".try_slice((Bound::Excluded(usize::MAX), Bound::Unbounded)), Err(SliceError::Overflow));
+        assert_eq!("abc".slice_snapped(1..=usize::MAX, Snap::Outward), Err(SliceError::Overflow));
+        // `usize::MAX` itself is just out of bounds.
+        assert_eq!("abc".try_slice(..usize::MAX), Err(SliceError::OutOfBounds { index: usize::MAX, len: 3 }));
+        assert_eq!("abc".slice_clamped(1..=usize::MAX), "bc");
+    }
+
+    #[test]
+    fn clamping_never_fails() {
+        assert_eq!(HANGUL.slice_clamped(4..100), "하세요");
+        assert_eq!(HANGUL.slice_clamped(4..5), "");
+        assert_eq!(HANGUL.slice_clamped(50..), "");
+        let (start, end) = (9, 3);
+        assert_eq!(HANGUL.slice_clamped(start..end), "");
+    }
+
+    #[test]
+    fn snapping_moves_offsets_onto_boundaries() {
+        assert_eq!(HANGUL.slice_snapped(4..8, Snap::Inward), Ok(""));
+        assert_eq!(HANGUL.slice_snapped(4..8, Snap::Outward), Ok("녕하"));
+        assert_eq!(HANGUL.slice_snapped(2..10, Snap::Inward), Ok("녕하"));
+        assert_eq!(HANGUL.slice_snapped(3..6, Snap::Outward), Ok("녕"));
+        assert_eq!(HANGUL.slice_snapped(4..99, Snap::Outward), Err(SliceError::OutOfBounds { index: 99, len: 15 }));
+    }
+
+    #[test]
+    fn boundaries_round_down_and_up() {
+        assert_eq!((HANGUL.floor_boundary(4), HANGUL.ceil_boundary(4)), (3, 6));
+        assert_eq!((HANGUL.floor_boundary(6), HANGUL.ceil_boundary(6)), (6, 6));
+        assert_eq!((HANGUL.floor_boundary(99), HANGUL.ceil_boundary(99)), (15, 15));
+        assert_eq!(("".floor_boundary(0), "".ceil_boundary(0)), (0, 0));
+    }
+}
@@ -0,0 +1,480 @@
+use std::fmt;
+use std::iter::Chain;
+use std::str::{self, Chars};
+
+/*
+    A gap buffer keeps the text in one array with an unused "gap" sitting at the cursor:
+
+        [h e l l o _ _ _ _ w o r l d]
+                   ^ gap   ^
+                   start   end
+
+    Typing fills the gap from the left and deleting widens it, so edits at the cursor never shift the rest of the text.
+    Moving the cursor moves the gap, which only copies the characters the cursor passes over.
+
+    The text is stored as UTF-8 and the gap always sits on a character boundary, so the bytes on either side of it
+    are valid `str`s on their own. A line that crosses the gap is handed out as a `Line` made of those two halves.
+
+    Every method that edits the buffer takes `&mut self`, and every `Line` borrows the buffer immutably.
+    That is the one-mutable-or-many-immutable rule at work: the compiler rejects any edit while a `Line` view is still in use,
+    so a view can never point at text that has since moved.
+*/
+pub struct GapBuffer {
+    buffer: Vec<u8>,
+    gap_start: usize,
+    gap_end: usize,
+    // Byte offset (ignoring the gap) at which each line starts; always begins with 0.
+    line_starts: Vec<usize>,
+}
+
+const MIN_GAP: usize = 64;
+
+// Number of bytes in the UTF-8 sequence that starts with `lead`.
+fn utf8_width(lead: u8) -> usize {
+    match lead {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    }
+}
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+impl GapBuffer {
+    pub fn new() -> GapBuffer {
+        GapBuffer::with_capacity(MIN_GAP)
+    }
+
+    pub fn with_capacity(capacity: usize) -> GapBuffer {
+        GapBuffer {
+            buffer: vec![0; capacity],
+            gap_start: 0,
+            gap_end: capacity,
+            line_starts: vec![0],
+        }
+    }
+
+    // Length of the text in bytes.
+    pub fn len(&self) -> usize {
+        self.buffer.len() - (self.gap_end - self.gap_start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The cursor as a byte offset into the text.
+    pub fn cursor(&self) -> usize {
+        self.gap_start
+    }
+
+    fn before_gap(&self) -> &str {
+        // The gap only ever moves to character boundaries, so both sides always hold complete UTF-8 sequences.
+        unsafe { str::from_utf8_unchecked(&self.buffer[..self.gap_start]) }
+    }
+
+    fn after_gap(&self) -> &str {
+        unsafe { str::from_utf8_unchecked(&self.buffer[self.gap_end..]) }
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.len());
+        text.push_str(self.before_gap());
+        text.push_str(self.after_gap());
+        text
+    }
+
+    // Moves the gap so it starts at byte offset `position`, which must be a character boundary.
+    fn move_gap_to(&mut self, position: usize) {
+        if position < self.gap_start {
+            let count = self.gap_start - position;
+            self.buffer.copy_within(position..self.gap_start, self.gap_end - count);
+            self.gap_start = position;
+            self.gap_end -= count;
+        } else if position > self.gap_start {
+            let count = position - self.gap_start;
+            self.buffer.copy_within(self.gap_end..self.gap_end + count, self.gap_start);
+            self.gap_start += count;
+            self.gap_end += count;
+        }
+    }
+
+    fn ensure_gap(&mut self, needed: usize) {
+        let gap = self.gap_end - self.gap_start;
+        if gap >= needed {
+            return;
+        }
+
+        let grow_by = (needed - gap).max(self.buffer.len()).max(MIN_GAP);
+        let tail_len = self.buffer.len() - self.gap_end;
+        self.buffer.resize(self.buffer.len() + grow_by, 0);
+        let new_gap_end = self.buffer.len() - tail_len;
+        self.buffer.copy_within(self.gap_end..self.gap_end + tail_len, new_gap_end);
+        self.gap_end = new_gap_end;
+    }
+
+    pub fn move_left(&mut self) -> bool {
+        if self.gap_start == 0 {
+            return false;
+        }
+        let mut position = self.gap_start - 1;
+        while is_continuation(self.buffer[position]) {
+            position -= 1;
+        }
+        self.move_gap_to(position);
+        true
+    }
+
+    pub fn move_right(&mut self) -> bool {
+        if self.gap_end == self.buffer.len() {
+            return false;
+        }
+        let width = utf8_width(self.buffer[self.gap_end]);
+        let position = self.gap_start + width;
+        self.move_gap_to(position);
+        true
+    }
+
+    pub fn move_to_start(&mut self) {
+        self.move_gap_to(0);
+    }
+
+    pub fn move_to_end(&mut self) {
+        let end = self.len();
+        self.move_gap_to(end);
+    }
+
+    // The cursor as a zero-based (line, column) pair, with the column counted in characters.
+    pub fn cursor_position(&self) -> (usize, usize) {
+        let line = self.line_of(self.gap_start);
+        let column = self.before_gap()[self.line_starts[line]..].chars().count();
+        (line, column)
+    }
+
+    // Places the cursor at `column` characters into `line`, clamping both to what exists.
+    pub fn move_to(&mut self, line: usize, column: usize) {
+        let line = line.min(self.line_count() - 1);
+        let start = self.line_starts[line];
+        let offset: usize = self.line(line).unwrap().chars().take(column).map(char::len_utf8).sum();
+        self.move_gap_to(start + offset);
+    }
+
+    pub fn move_up(&mut self) -> bool {
+        let (line, column) = self.cursor_position();
+        if line == 0 {
+            return false;
+        }
+        self.move_to(line - 1, column);
+        true
+    }
+
+    pub fn move_down(&mut self) -> bool {
+        let (line, column) = self.cursor_position();
+        if line + 1 == self.line_count() {
+            return false;
+        }
+        self.move_to(line + 1, column);
+        true
+    }
+
+    pub fn insert_char(&mut self, ch: char) {
+        let mut encoded = [0; 4];
+        self.insert_str(ch.encode_utf8(&mut encoded));
+    }
+
+    // Inserts `text` at the cursor and leaves the cursor after it.
+    pub fn insert_str(&mut self, text: &str) {
+        self.ensure_gap(text.len());
+
+        let at = self.gap_start;
+        self.buffer[at..at + text.len()].copy_from_slice(text.as_bytes());
+        self.gap_start += text.len();
+
+        let first_shifted = self.line_starts.partition_point(|&start| start <= at);
+        for start in &mut self.line_starts[first_shifted..] {
+            *start += text.len();
+        }
+        let new_starts = text.match_indices('\n').map(|(i, _)| at + i + 1);
+        self.line_starts.splice(first_shifted..first_shifted, new_starts);
+    }
+
+    // Deletes the character before the cursor (backspace).
+    pub fn delete_backward(&mut self) -> Option<char> {
+        let end = self.gap_start;
+        if !self.move_left() {
+            return None;
+        }
+        let start = self.gap_start;
+        let deleted = self.after_gap().chars().next();
+        self.gap_end += end - start;
+        self.remove_line_starts(start, end);
+        deleted
+    }
+
+    // Deletes the character after the cursor (delete).
+    pub fn delete_forward(&mut self) -> Option<char> {
+        let deleted = self.after_gap().chars().next()?;
+        let start = self.gap_start;
+        self.gap_end += deleted.len_utf8();
+        self.remove_line_starts(start, start + deleted.len_utf8());
+        Some(deleted)
+    }
+
+    // Updates the line index after the bytes `start..end` were removed.
+    fn remove_line_starts(&mut self, start: usize, end: usize) {
+        let removed = end - start;
+        self.line_starts.retain(|&line_start| line_start <= start || line_start > end);
+        for line_start in &mut self.line_starts {
+            if *line_start > end {
+                *line_start -= removed;
+            }
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    fn line_of(&self, position: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= position) - 1
+    }
+
+    // A view of line `index` without its trailing newline.
+    pub fn line(&self, index: usize) -> Option<Line<'_>> {
+        let start = *self.line_starts.get(index)?;
+        let end = match self.line_starts.get(index + 1) {
+            Some(&next) => next - 1,
+            None => self.len(),
+        };
+        Some(self.range(start, end))
+    }
+
+    pub fn lines(&self) -> Lines<'_> {
+        Lines { buffer: self, next: 0 }
+    }
+
+    fn range(&self, start: usize, end: usize) -> Line<'_> {
+        let before = self.before_gap();
+        let after = self.after_gap();
+
+        if end <= self.gap_start {
+            Line { head: &before[start..end], tail: "" }
+        } else if start >= self.gap_start {
+            Line { head: &after[start - self.gap_start..end - self.gap_start], tail: "" }
+        } else {
+            Line { head: &before[start..], tail: &after[..end - self.gap_start] }
+        }
+    }
+}
+
+impl Default for GapBuffer {
+    fn default() -> GapBuffer {
+        GapBuffer::new()
+    }
+}
+
+impl From<&str> for GapBuffer {
+    fn from(text: &str) -> GapBuffer {
+        let mut buffer = GapBuffer::with_capacity(text.len() + MIN_GAP);
+        buffer.insert_str(text);
+        buffer
+    }
+}
+
+impl fmt::Display for GapBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.before_gap())?;
+        f.write_str(self.after_gap())
+    }
+}
+
+// A borrowed line of a `GapBuffer`. If the gap sits inside the line, its text is split across `head` and `tail`.
+#[derive(Clone, Copy, Debug)]
+pub struct Line<'a> {
+    head: &'a str,
+    tail: &'a str,
+}
+
+impl<'a> Line<'a> {
+    pub fn len(&self) -> usize {
+        self.head.len() + self.tail.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn chars(&self) -> Chain<Chars<'a>, Chars<'a>> {
+        self.head.chars().chain(self.tail.chars())
+    }
+
+    // The line as a single `&str`, if the gap does not split it.
+    pub fn as_str(&self) -> Option<&'a str> {
+        if self.tail.is_empty() {
+            Some(self.head)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> fmt::Display for Line<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.head)?;
+        f.write_str(self.tail)
+    }
+}
+
+impl<'a, 'b> PartialEq<&'b str> for Line<'a> {
+    fn eq(&self, other: &&str) -> bool {
+        self.len() == other.len() && other.starts_with(self.head) && other.ends_with(self.tail)
+    }
+}
+
+pub struct Lines<'a> {
+    buffer: &'a GapBuffer,
+    next: usize,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = Line<'a>;
+
+    fn next(&mut self) -> Option<Line<'a>> {
+        let line = self.buffer.line(self.next)?;
+        self.next += 1;
+        Some(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The lines as the buffer reports them must agree with splitting its text.
+    fn assert_lines(buffer: &GapBuffer, expected: &[&str]) {
+        let lines: Vec<String> = buffer.lines().map(|line| line.to_string()).collect();
+        assert_eq!(lines, expected);
+        assert_eq!(buffer.text().split('\n').collect::<Vec<_>>(), expected);
+        assert_eq!(buffer.line_count(), expected.len());
+    }
+
+    #[test]
+    fn inserts_and_deletes_on_both_sides_of_the_gap() {
+        let mut buffer = GapBuffer::from("hello world");
+        buffer.move_to(0, 5);
+        buffer.insert_str(",");
+        assert_eq!(buffer.text(), "hello, world");
+        assert_eq!(buffer.delete_forward(), Some(' '));
+        assert_eq!(buffer.delete_backward(), Some(','));
+        buffer.insert_char('\n');
+        assert_lines(&buffer, &["hello", "world"]);
+        assert_eq!(buffer.cursor_position(), (1, 0));
+
+        assert_eq!(buffer.delete_backward(), Some('\n'));
+        assert_lines(&buffer, &["helloworld"]);
+        assert_eq!(buffer.cursor(), 5);
+        assert_eq!(buffer.len(), 10);
+    }
+
+    #[test]
+    fn the_gap_grows_when_it_fills_up() {
+        let mut buffer = GapBuffer::with_capacity(1);
+        for i in 0..200 {
+            buffer.insert_str(&format!("{}\n", i));
+            if i % 7 == 0 {
+                buffer.move_left();
+            }
+        }
+        buffer.move_to_start();
+        buffer.insert_str("start\n");
+        let text = buffer.text();
+        assert_lines(&buffer, &text.split('\n').collect::<Vec<_>>());
+        assert!(text.starts_with("start\n0"));
+        assert_eq!(buffer.line_count(), 202);
+    }
+
+    #[test]
+    fn moving_past_either_end_does_nothing() {
+        let mut buffer = GapBuffer::from("ab\ncd");
+        buffer.move_to_start();
+        assert!(!buffer.move_left());
+        assert!(!buffer.move_up());
+        assert_eq!(buffer.delete_backward(), None);
+        assert_eq!(buffer.cursor(), 0);
+
+        buffer.move_to_end();
+        assert!(!buffer.move_right());
+        assert!(!buffer.move_down());
+        assert_eq!(buffer.delete_forward(), None);
+        assert_eq!(buffer.cursor_position(), (1, 2));
+
+        buffer.move_to(usize::MAX, usize::MAX);
+        assert_eq!(buffer.cursor_position(), (1, 2));
+        buffer.move_to(0, usize::MAX);
+        assert_eq!(buffer.cursor_position(), (0, 2));
+        assert_eq!(buffer.text(), "ab\ncd");
+    }
+
+    #[test]
+    fn up_and_down_keep_the_column_where_the_line_allows() {
+        let mut buffer = GapBuffer::from("long line\nab\nanother");
+        buffer.move_to(0, 7);
+        assert!(buffer.move_down());
+        assert_eq!(buffer.cursor_position(), (1, 2));
+        assert!(buffer.move_down());
+        assert_eq!(buffer.cursor_position(), (2, 2));
+        assert!(buffer.move_up() && buffer.move_up());
+        assert_eq!(buffer.cursor_position(), (0, 2));
+    }
+
+    #[test]
+    fn multibyte_characters_move_and_delete_whole() {
+        let mut buffer = GapBuffer::from("안녕 🦀é");
+        buffer.move_to_start();
+        assert!(buffer.move_right());
+        assert_eq!(buffer.cursor(), 3);
+        buffer.move_to_end();
+        assert!(buffer.move_left());
+        assert_eq!(buffer.cursor(), "안녕 🦀".len());
+        assert_eq!(buffer.delete_backward(), Some('🦀'));
+        assert_eq!(buffer.delete_forward(), Some('é'));
+        assert_eq!(buffer.text(), "안녕 ");
+
+        buffer.move_to(0, 1);
+        buffer.insert_char('ß');
+        assert_eq!(buffer.text(), "안ß녕 ");
+        assert_eq!(buffer.cursor_position(), (0, 2));
+        assert_eq!(buffer.cursor(), 5);
+    }
+
+    #[test]
+    fn a_line_split_by_the_gap_still_reads_as_one() {
+        let mut buffer = GapBuffer::from("first\n안녕하세요\n");
+        buffer.move_to(1, 2);
+        let line = buffer.line(1).unwrap();
+        assert_eq!(line.as_str(), None);
+        assert!(line == "안녕하세요");
+        assert!(line != "안녕하세");
+        assert_eq!(line.len(), 15);
+        assert_eq!(line.chars().rev().nth(1), Some('세'));
+        assert_eq!(line.to_string(), "안녕하세요");
+        assert!(buffer.line(2).unwrap().is_empty());
+        assert!(buffer.line(3).is_none());
+    }
+
+    #[test]
+    fn an_empty_buffer_has_one_empty_line() {
+        let mut buffer = GapBuffer::new();
+        assert!(buffer.is_empty());
+        assert_lines(&buffer, &[""]);
+        assert_eq!(buffer.cursor_position(), (0, 0));
+        buffer.insert_str("\n\n");
+        assert_lines(&buffer, &["", "", ""]);
+        buffer.move_left();
+        buffer.delete_forward();
+        assert_lines(&buffer, &["", ""]);
+    }
+}
@@ -1,3 +1,13 @@
+use std::thread;
+
+mod arc_str;
+mod checked_slice;
+mod gap_buffer;
+
+use arc_str::ArcStr;
+use checked_slice::{CheckedSlice, Snap};
+use gap_buffer::GapBuffer;
+
 fn main() {
     let mut s = String::from("some string");
 
@@ -17,4 +27,74 @@ fn main() {
     // The type of s here is &str: it’s a slice pointing to that specific point of the binary.
     // This is also why string literals are immutable; &str is an immutable reference.
     let sl = "string literal";
+
+    /*
+        Slicing with `[..]` panics if an offset is past the end or falls in the middle of a multi-byte character.
+        When offsets come from outside the program, the checked versions report what went wrong instead.
+    */
+    println!("{:?}", sl.try_slice(..6));
+    println!("{:?}", sl.try_slice(7..99));
+
+    let hangul = String::from("안녕하세요"); // every character here is three bytes long
+    match hangul.try_slice(..4) {
+        Ok(slice) => println!("{}", slice),
+        Err(error) => println!("error: {}", error),
+    }
+    println!("{:?}", hangul.slice_clamped(4..100));
+    println!("{:?}", hangul.slice_snapped(4..8, Snap::Inward));
+    println!("{:?}", hangul.slice_snapped(4..8, Snap::Outward));
+
+    /*
+        `string` above can't leave this function or cross into another thread, because it borrows from `s`.
+        An `ArcStr` owns a shared handle to its buffer instead, so its sub-slices can go wherever they like
+        while still sharing the same bytes.
+    */
+    let shared = ArcStr::from(s.clone());
+    let (head, tail) = shared.split_at(4);
+    let words = shared.split_whitespace();
+    println!("{:?} {:?} {:?}, {} views on one buffer", head, tail, words, shared.buffer_refs());
+
+    let handle = thread::spawn(move || format!("{} from another thread", tail.substr(..3)));
+    println!("{}", handle.join().unwrap());
+    println!("{:?}", head.try_substr(2..9));
+    println!("same buffer: {}, after detach: {}", head.shares_buffer(&words[0]), head.detach().shares_buffer(&shared));
+    println!("{:?}", ArcStr::new("first line\nsecond line").lines());
+
+    /*
+        The same rule keeps a text editor honest. Edits to a `GapBuffer` need `&mut`, while a `Line` view borrows it immutably,
+        so a view has to be finished with before the next edit:
+
+        ```
+        let first = buffer.line(0).unwrap();
+        buffer.insert_char('!'); // error[E0502]: cannot borrow `buffer` as mutable because it is also borrowed as immutable
+        println!("{}", first);
+        ```
+    */
+    let mut buffer = GapBuffer::from("fn main() {\n}\n");
+    buffer.move_to_start();
+    buffer.move_down();
+    buffer.insert_str("    printn!(\"안녕\");;\n");
+
+    // fix the typo: back up to the line above and step right past "    print"
+    buffer.move_up();
+    for _ in 0..9 {
+        buffer.move_right();
+    }
+    buffer.insert_char('l');
+
+    // drop the doubled semicolon, then the newline after the closing brace
+    buffer.move_to(1, usize::MAX);
+    buffer.delete_backward();
+    println!("last line empty: {}", buffer.line(buffer.line_count() - 1).unwrap().is_empty());
+    buffer.move_to_end();
+    buffer.move_left();
+    buffer.delete_forward();
+
+    for (number, line) in buffer.lines().enumerate() {
+        println!("{:>2} | {} ({} bytes)", number + 1, line, line.len());
+    }
+    let middle = buffer.line(1).unwrap();
+    println!("{} lines, cursor at {:?}, byte {}", buffer.line_count(), buffer.cursor_position(), buffer.cursor());
+    println!("{:?} {:?} {}", middle.as_str(), middle.chars().rev().nth(2), middle == "    println!(\"안녕\");");
+    println!("{:?} (empty: {})", buffer.text(), buffer.is_empty());
 }
@@ -0,0 +1,515 @@
+/*!
+    `#[derive(Builder)]` generates a builder for a struct with named fields:
+
+    ```
+    # #[macro_use] extern crate builder_derive;
+    #[derive(Builder)]
+    #[builder(validate = "check_profile", error = "String")]
+    struct Profile {
+        username: String,                      // required
+        bio: Option<String>,                   // optional, `None` unless set
+        #[builder(default)]
+        followers: u32,                        // `Default::default()` unless set
+        #[builder(default = "String::from(\"en\")")]
+        locale: String,                        // the given expression unless set
+    }
+    # fn check_profile(profile: &Profile) -> Result<(), String> {
+    #     if profile.username.is_empty() { Err(String::from("empty username")) } else { Ok(()) }
+    # }
+    # fn main() -> Result<(), String> {
+
+    let profile = Profile::builder().username(String::from("alice")).bio(String::from("hi")).build()?;
+    # assert_eq!((profile.bio.as_ref().map(String::as_str), profile.followers, profile.locale.as_str()), (Some("hi"), 0, "en"));
+    # let profile = Profile::builder().followers(3).locale(String::from("ko")).username(String::from("bob")).build()?;
+    # assert_eq!((profile.username.as_str(), profile.bio, profile.followers, profile.locale.as_str()), ("bob", None, 3, "ko"));
+    # assert_eq!(Profile::builder().username(String::new()).build().err(), Some(String::from("empty username")));
+    # Ok(())
+    # }
+    ```
+
+    Each field gets a setter named after it. The builder carries one type parameter per required field,
+    which starts out as `<Name>FieldMissing` and becomes `<Name>FieldSet` once that field's setter has been called.
+    `build` only exists when every parameter is `<Name>FieldSet`, so forgetting a required field is a compile error
+    rather than a panic, and so is setting one twice:
+
+    ```compile_fail,E0599
+    # #[macro_use] extern crate builder_derive;
+    #[derive(Builder)]
+    struct Point { x: i32, y: i32 }
+    # fn main() {
+    let point = Point::builder().x(1).build(); // `y` was never set
+    # }
+    ```
+
+    ```compile_fail,E0599
+    # #[macro_use] extern crate builder_derive;
+    #[derive(Builder)]
+    struct Point { x: i32, y: i32 }
+    # fn main() {
+    let point = Point::builder().x(1).y(2).x(3).build(); // `x` was already set
+    # }
+    ```
+
+    Without `validate`, `build` returns the struct. With it, `build` passes the finished struct to the named function,
+    a `fn(&Profile) -> Result<(), E>`, and returns `Result<Profile, E>`; `E` is given by `error` and defaults to `String`.
+
+    Only plain structs are supported: no generics, no tuple structs, no enums.
+
+    The macro has no dependencies besides `proc_macro`; it reads the token stream directly and emits the generated code as text.
+*/
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Spacing, TokenStream, TokenTree};
+
+#[proc_macro_derive(Builder, attributes(builder))]
+pub fn derive_builder(input: TokenStream) -> TokenStream {
+    let code = match parse_struct(input) {
+        Ok(parsed) => generate(&parsed),
+        Err(message) => format!("compile_error!({:?});", message),
+    };
+    code.parse().unwrap()
+}
+
+enum FieldKind {
+    Required,
+    // An `Option<T>` field; the setter takes the inner `T`.
+    Optional { inner: String },
+    // `None` means `Default::default()`.
+    Default(Option<String>),
+}
+
+struct Field {
+    name: String,
+    ty: String,
+    kind: FieldKind,
+}
+
+struct Input {
+    vis: String,
+    name: String,
+    fields: Vec<Field>,
+    validate: Option<String>,
+    error: String,
+}
+
+// One `key` or `key = "value"` inside `#[builder(...)]`.
+type Arg = (String, Option<String>);
+
+fn is_punct(token: Option<&TokenTree>, ch: char) -> bool {
+    match token {
+        Some(TokenTree::Punct(punct)) => punct.as_char() == ch,
+        _ => false,
+    }
+}
+
+fn is_ident(token: Option<&TokenTree>, name: &str) -> bool {
+    match token {
+        Some(TokenTree::Ident(ident)) => ident.to_string() == name,
+        _ => false,
+    }
+}
+
+// Reads the hex digits of a `\x` or `\u{...}` escape as the character they name.
+fn escaped_char(digits: &str) -> Option<char> {
+    let digits: String = digits.chars().filter(|&ch| ch != '_').collect();
+    if digits.is_empty() || digits.len() > 6 {
+        return None;
+    }
+    u32::from_str_radix(&digits, 16).ok().and_then(std::char::from_u32)
+}
+
+/*
+    Turns the source text of a string literal back into its value. Handles `"..."` with every escape Rust allows
+    (`\n \r \t \\ \0 \' \"`, `\x7F`, `\u{...}` and a `\` at the end of a line) and raw strings like `r#"..."#`.
+*/
+fn unquote(literal: &str) -> Result<String, String> {
+    if let Some(raw) = literal.strip_prefix('r') {
+        let hashes = raw.chars().take_while(|&ch| ch == '#').count();
+        return Ok(raw[1 + hashes..raw.len() - 1 - hashes].to_string());
+    }
+    if !literal.starts_with('"') {
+        return Err(format!("expected a string literal, found `{}`", literal));
+    }
+
+    let invalid = |escape: &str| Err(format!("invalid escape `\\{}` in {}", escape, literal));
+    let mut value = String::new();
+    let mut chars = literal[1..literal.len() - 1].chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            value.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some('t') => value.push('\t'),
+            Some('0') => value.push('\0'),
+            Some(quoted @ '\\') | Some(quoted @ '\'') | Some(quoted @ '"') => value.push(quoted),
+            Some('x') => {
+                let digits: String = chars.by_ref().take(2).collect();
+                match escaped_char(&digits) {
+                    Some(ch) if digits.len() == 2 && ch <= '\x7F' => value.push(ch),
+                    _ => return invalid(&format!("x{}", digits)),
+                }
+            }
+            Some('u') => {
+                let escape: String = chars.by_ref().take_while(|&ch| ch != '}').collect();
+                match escape.strip_prefix('{').and_then(escaped_char) {
+                    Some(ch) => value.push(ch),
+                    None => return invalid(&format!("u{}}}", escape)),
+                }
+            }
+            // A line continuation skips the newline and the indentation after it.
+            Some('\n') => {
+                while chars.peek().is_some_and(|ch| ch.is_whitespace()) {
+                    chars.next();
+                }
+            }
+            Some(other) => return invalid(&other.to_string()),
+            None => return invalid(""),
+        }
+    }
+    Ok(value)
+}
+
+fn parse_args(stream: TokenStream) -> Result<Vec<Arg>, String> {
+    let tokens: Vec<TokenTree> = stream.into_iter().collect();
+    let mut args = Vec::new();
+
+    for arg in tokens.split(|token| is_punct(Some(token), ',')) {
+        match arg {
+            [] => (),
+            [TokenTree::Ident(key)] => args.push((key.to_string(), None)),
+            [TokenTree::Ident(key), TokenTree::Punct(eq), TokenTree::Literal(value)] if eq.as_char() == '=' => {
+                args.push((key.to_string(), Some(unquote(&value.to_string())?)));
+            }
+            _ => {
+                let text: TokenStream = arg.iter().cloned().collect();
+                return Err(format!("unexpected `{}` in #[builder(...)]; expected `key` or `key = \"value\"`", text));
+            }
+        }
+    }
+
+    Ok(args)
+}
+
+// Skips the outer attributes at `tokens[*i..]` and returns the arguments of those that are `#[builder(...)]`.
+fn parse_attributes(tokens: &[TokenTree], i: &mut usize) -> Result<Vec<Arg>, String> {
+    let mut args = Vec::new();
+
+    while is_punct(tokens.get(*i), '#') {
+        let group = match tokens.get(*i + 1) {
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Bracket => group,
+            _ => return Err(String::from("malformed attribute")),
+        };
+        *i += 2;
+
+        let inner: Vec<TokenTree> = group.stream().into_iter().collect();
+        if !is_ident(inner.first(), "builder") {
+            continue;
+        }
+        match inner.get(1) {
+            Some(TokenTree::Group(arguments)) if arguments.delimiter() == Delimiter::Parenthesis => {
+                args.extend(parse_args(arguments.stream())?);
+            }
+            _ => return Err(String::from("expected #[builder(...)]")),
+        }
+    }
+
+    Ok(args)
+}
+
+// Skips `pub`, `pub(crate)`, `pub(super)` and so on, returning it as text (empty for private items).
+fn parse_visibility(tokens: &[TokenTree], i: &mut usize) -> String {
+    if !is_ident(tokens.get(*i), "pub") {
+        return String::new();
+    }
+    *i += 1;
+
+    match tokens.get(*i) {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+            *i += 1;
+            format!("pub{}", group)
+        }
+        _ => String::from("pub"),
+    }
+}
+
+// Reads a type up to the next `,` that is not nested inside `<...>`.
+fn parse_type(tokens: &[TokenTree], i: &mut usize) -> Vec<TokenTree> {
+    let mut depth = 0usize;
+    let mut ty = Vec::new();
+    let mut after_joint_minus = false;
+
+    while let Some(token) = tokens.get(*i) {
+        if let TokenTree::Punct(ref punct) = *token {
+            match punct.as_char() {
+                ',' if depth == 0 => break,
+                '<' => depth += 1,
+                // the `>` of `->` in a function type doesn't close anything
+                '>' if !after_joint_minus => depth = depth.saturating_sub(1),
+                _ => (),
+            }
+            after_joint_minus = punct.as_char() == '-' && punct.spacing() == Spacing::Joint;
+        } else {
+            after_joint_minus = false;
+        }
+        ty.push(token.clone());
+        *i += 1;
+    }
+
+    ty
+}
+
+// If `ty` is `Option<T>` (spelled with or without a `std::option::` path), returns `T`.
+fn option_inner(ty: &[TokenTree]) -> Option<String> {
+    let open = ty.iter().position(|token| is_punct(Some(token), '<'))?;
+    if open == 0 || !is_ident(ty.get(open - 1), "Option") || !is_punct(ty.last(), '>') {
+        return None;
+    }
+    let is_path = ty[..open].iter().all(|token| match *token {
+        TokenTree::Ident(_) => true,
+        TokenTree::Punct(ref punct) => punct.as_char() == ':',
+        _ => false,
+    });
+    if !is_path {
+        return None;
+    }
+
+    let inner: TokenStream = ty[open + 1..ty.len() - 1].iter().cloned().collect();
+    Some(inner.to_string())
+}
+
+fn parse_fields(stream: TokenStream) -> Result<Vec<Field>, String> {
+    let tokens: Vec<TokenTree> = stream.into_iter().collect();
+    let mut fields = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let args = parse_attributes(&tokens, &mut i)?;
+        parse_visibility(&tokens, &mut i);
+
+        let name = match tokens.get(i) {
+            Some(TokenTree::Ident(ident)) => ident.to_string(),
+            _ => return Err(String::from("expected a field name")),
+        };
+        if !is_punct(tokens.get(i + 1), ':') {
+            return Err(format!("expected `:` after field `{}`", name));
+        }
+        i += 2;
+
+        let ty_tokens = parse_type(&tokens, &mut i);
+        i += 1; // the comma, if there is one
+
+        let ty: TokenStream = ty_tokens.iter().cloned().collect();
+        let mut kind = match option_inner(&ty_tokens) {
+            Some(inner) => FieldKind::Optional { inner },
+            None => FieldKind::Required,
+        };
+        for (key, value) in args {
+            match key.as_str() {
+                "default" => kind = FieldKind::Default(value),
+                _ => return Err(format!("unknown field option `{}` on `{}`; expected `default`", key, name)),
+            }
+        }
+
+        fields.push(Field { name, ty: ty.to_string(), kind });
+    }
+
+    Ok(fields)
+}
+
+fn parse_struct(input: TokenStream) -> Result<Input, String> {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let mut i = 0;
+
+    let args = parse_attributes(&tokens, &mut i)?;
+    let vis = parse_visibility(&tokens, &mut i);
+
+    if !is_ident(tokens.get(i), "struct") {
+        return Err(String::from("#[derive(Builder)] only works on structs"));
+    }
+    let name = match tokens.get(i + 1) {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err(String::from("expected a struct name")),
+    };
+    if is_punct(tokens.get(i + 2), '<') {
+        return Err(String::from("#[derive(Builder)] does not support generic structs"));
+    }
+    let fields = match tokens.get(i + 2) {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => parse_fields(group.stream())?,
+        _ => return Err(String::from("#[derive(Builder)] only works on structs with named fields")),
+    };
+
+    let mut validate = None;
+    let mut error = String::from("String");
+    for (key, value) in args {
+        match (key.as_str(), value) {
+            ("validate", Some(path)) => validate = Some(path),
+            ("error", Some(ty)) => error = ty,
+            (key, _) => {
+                return Err(format!("unknown option `{}`; expected `validate = \"fn\"` or `error = \"Type\"`", key))
+            }
+        }
+    }
+
+    Ok(Input { vis, name, fields, validate, error })
+}
+
+fn angle(params: &[String]) -> String {
+    if params.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", params.join(", "))
+    }
+}
+
+fn generate(input: &Input) -> String {
+    let vis = &input.vis;
+    let name = &input.name;
+    let builder = format!("{}Builder", name);
+    let missing = format!("{}FieldMissing", name);
+    let set = format!("{}FieldSet", name);
+
+    let required: Vec<&Field> = input.fields.iter().filter(|field| matches!(field.kind, FieldKind::Required)).collect();
+    let params: Vec<String> = (0..required.len()).map(|k| format!("F{}", k)).collect();
+    let all = |marker: &str| angle(&vec![marker.to_string(); required.len()]);
+
+    let mut out = String::new();
+
+    // the typestate markers and the builder itself
+    out += &format!("{vis} struct {missing};\n{vis} struct {set};\n", vis = vis, missing = missing, set = set);
+
+    let storage: String = input.fields.iter().map(|field| match field.kind {
+        FieldKind::Optional { .. } => format!("{}: {},\n", field.name, field.ty),
+        _ => format!("{}: ::std::option::Option<{}>,\n", field.name, field.ty),
+    }).collect();
+    out += &format!(
+        "#[must_use]\n{vis} struct {builder}{params} {{\n{storage}__state: ::std::marker::PhantomData<({phantom})>,\n}}\n",
+        vis = vis,
+        builder = builder,
+        params = angle(&params),
+        storage = storage,
+        phantom = params.iter().map(|p| format!("{},", p)).collect::<String>(),
+    );
+
+    let empty: String = input.fields.iter().map(|field| format!("{}: ::std::option::Option::None,\n", field.name)).collect();
+    out += &format!(
+        "#[allow(dead_code)]\nimpl {name} {{\n{vis} fn builder() -> {builder}{missing} {{\n{builder} {{\n{empty}__state: ::std::marker::PhantomData,\n}}\n}}\n}}\n",
+        name = name,
+        vis = vis,
+        builder = builder,
+        missing = all(&missing),
+        empty = empty,
+    );
+
+    // setters for required fields flip their own marker from missing to set
+    for (k, field) in required.iter().enumerate() {
+        let others: Vec<String> = params.iter().enumerate().filter(|&(j, _)| j != k).map(|(_, p)| p.clone()).collect();
+        let with = |marker: &str| -> Vec<String> {
+            params.iter().enumerate().map(|(j, p)| if j == k { marker.to_string() } else { p.clone() }).collect()
+        };
+        let moved: String = input.fields.iter().map(|other| {
+            if other.name == field.name {
+                format!("{}: ::std::option::Option::Some(value),\n", other.name)
+            } else {
+                format!("{0}: self.{0},\n", other.name)
+            }
+        }).collect();
+
+        out += &format!(
+            "#[allow(dead_code)]\nimpl{others} {builder}{before} {{\n{vis} fn {field}(self, value: {ty}) -> {builder}{after} {{\n{builder} {{\n{moved}__state: ::std::marker::PhantomData,\n}}\n}}\n}}\n",
+            others = angle(&others),
+            builder = builder,
+            before = angle(&with(&missing)),
+            after = angle(&with(&set)),
+            vis = vis,
+            field = field.name,
+            ty = field.ty,
+            moved = moved,
+        );
+    }
+
+    // setters for optional and defaulted fields work in any state and can be called again to overwrite
+    let mut free_setters = String::new();
+    for field in &input.fields {
+        let ty = match field.kind {
+            FieldKind::Required => continue,
+            FieldKind::Optional { ref inner } => inner,
+            FieldKind::Default(_) => &field.ty,
+        };
+        free_setters += &format!(
+            "{vis} fn {field}(mut self, value: {ty}) -> Self {{\nself.{field} = ::std::option::Option::Some(value);\nself\n}}\n",
+            vis = vis,
+            field = field.name,
+            ty = ty,
+        );
+    }
+    if !free_setters.is_empty() {
+        out += &format!(
+            "#[allow(dead_code)]\nimpl{params} {builder}{params} {{\n{setters}}}\n",
+            params = angle(&params),
+            builder = builder,
+            setters = free_setters,
+        );
+    }
+
+    // `build`, only once every required field is set
+    let assembled: String = input.fields.iter().map(|field| match field.kind {
+        FieldKind::Required => format!("{0}: self.{0}.unwrap(),\n", field.name),
+        FieldKind::Optional { .. } => format!("{0}: self.{0},\n", field.name),
+        FieldKind::Default(None) => format!("{0}: self.{0}.unwrap_or_default(),\n", field.name),
+        FieldKind::Default(Some(ref expr)) => format!("{0}: self.{0}.unwrap_or_else(|| {1}),\n", field.name, expr),
+    }).collect();
+    let (returns, finish) = match input.validate {
+        None => (name.clone(), String::from("value")),
+        Some(ref validate) => (
+            format!("::std::result::Result<{}, {}>", name, input.error),
+            format!("{}(&value).map(|()| value)", validate),
+        ),
+    };
+    out += &format!(
+        "#[allow(dead_code)]\nimpl {builder}{set} {{\n{vis} fn build(self) -> {returns} {{\nlet value = {name} {{\n{assembled}}};\n{finish}\n}}\n}}\n",
+        builder = builder,
+        set = all(&set),
+        vis = vis,
+        returns = returns,
+        name = name,
+        assembled = assembled,
+        finish = finish,
+    );
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_and_raw_strings() {
+        assert_eq!(unquote(r#""validate_profile""#), Ok(String::from("validate_profile")));
+        assert_eq!(unquote(r#""""#), Ok(String::new()));
+        assert_eq!(unquote(r##"r#"String::from("en")"#"##), Ok(String::from(r#"String::from("en")"#)));
+        assert_eq!(unquote(r#"r"\n""#), Ok(String::from(r"\n")));
+    }
+
+    #[test]
+    fn every_escape_rust_allows() {
+        assert_eq!(unquote(r#""a\nb\tc\rd\0""#), Ok(String::from("a\nb\tc\rd\0")));
+        assert_eq!(unquote(r#""String::from(\"en\")""#), Ok(String::from("String::from(\"en\")")));
+        assert_eq!(unquote(r#""\\ \' \x41\x7F""#), Ok(String::from("\\ ' A\x7F")));
+        assert_eq!(unquote(r#""\u{48}\u{D55C}\u{1F_980}""#), Ok(String::from("H한🦀")));
+        assert_eq!(unquote("\"one \\\n    two\""), Ok(String::from("one two")));
+    }
+
+    #[test]
+    fn unsupported_escapes_and_literals_are_errors() {
+        for literal in &[r#""\q""#, r#""\x80""#, r#""\x4""#, r#""\u{D800}""#, r#""\u{110000}""#, r#""\u41""#] {
+            let error = unquote(literal).unwrap_err();
+            assert!(error.starts_with("invalid escape"), "{}: {}", literal, error);
+        }
+        assert_eq!(unquote("42"), Err(String::from("expected a string literal, found `42`")));
+        assert!(unquote(r#"b"bytes""#).is_err());
+    }
+}
@@ -0,0 +1,396 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/*
+    A parsed email address. Building one goes through `EmailAddress::parse`, so holding an `EmailAddress`
+    (rather than a `String`) means the text has already been checked.
+
+    The accepted syntax is the practical part of RFC 5322:
+    - the local part is a dot-atom (`first.last+tag`) or a quoted string (`"john doe"`, with `\` escapes)
+    - the domain is a dot-separated list of host name labels; labels may contain non-ASCII letters,
+      which are stored in their ASCII-compatible punycode form (`bücher.example` becomes `xn--bcher-kva.example`)
+    - the local part is at most 64 bytes, the domain 253 and the whole address 254, each label 63
+
+    Comments, folding whitespace and domain literals like `[192.0.2.1]` are rejected.
+*/
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct EmailAddress {
+    // `local@domain`, with the domain in lowercase ASCII (punycode) form
+    address: String,
+    at: usize,
+    // The domain as it was written, lowercased, for showing to people.
+    unicode_domain: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EmailErrorKind {
+    Empty,
+    MissingAt,
+    EmptyLocalPart,
+    InvalidLocalChar(char),
+    MisplacedDot,
+    UnterminatedQuote,
+    InvalidQuotedChar(char),
+    ExpectedAtAfterQuote,
+    EmptyDomain,
+    DomainLiteral,
+    EmptyLabel,
+    InvalidDomainChar(char),
+    MisplacedHyphen,
+    LabelTooLong,
+    LocalPartTooLong,
+    DomainTooLong,
+    AddressTooLong,
+}
+
+// What went wrong, and the byte offset into the input where it did.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmailError {
+    pub position: usize,
+    pub kind: EmailErrorKind,
+}
+
+impl fmt::Display for EmailError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.kind {
+            EmailErrorKind::Empty => String::from("address is empty"),
+            EmailErrorKind::MissingAt => String::from("missing '@'"),
+            EmailErrorKind::EmptyLocalPart => String::from("nothing before '@'"),
+            EmailErrorKind::InvalidLocalChar(ch) => format!("{:?} is not allowed before '@'", ch),
+            EmailErrorKind::MisplacedDot => String::from("dots may not start, end or repeat in the name"),
+            EmailErrorKind::UnterminatedQuote => String::from("quoted name is never closed"),
+            EmailErrorKind::InvalidQuotedChar(ch) => format!("{:?} is not allowed inside quotes", ch),
+            EmailErrorKind::ExpectedAtAfterQuote => String::from("expected '@' right after the closing quote"),
+            EmailErrorKind::EmptyDomain => String::from("nothing after '@'"),
+            EmailErrorKind::DomainLiteral => String::from("IP address domains are not supported"),
+            EmailErrorKind::EmptyLabel => String::from("domain has an empty part"),
+            EmailErrorKind::InvalidDomainChar(ch) => format!("{:?} is not allowed in a domain", ch),
+            EmailErrorKind::MisplacedHyphen => String::from("domain parts may not start or end with '-'"),
+            EmailErrorKind::LabelTooLong => String::from("domain part is longer than 63 characters"),
+            EmailErrorKind::LocalPartTooLong => String::from("name before '@' is longer than 64 characters"),
+            EmailErrorKind::DomainTooLong => String::from("domain is longer than 253 characters"),
+            EmailErrorKind::AddressTooLong => String::from("address is longer than 254 characters"),
+        };
+        write!(f, "{} (at position {})", what, self.position)
+    }
+}
+
+impl Error for EmailError {}
+
+const MAX_LOCAL_LEN: usize = 64;
+const MAX_DOMAIN_LEN: usize = 253;
+const MAX_ADDRESS_LEN: usize = 254;
+const MAX_LABEL_LEN: usize = 63;
+
+fn error<T>(position: usize, kind: EmailErrorKind) -> Result<T, EmailError> {
+    Err(EmailError { position, kind })
+}
+
+// Characters RFC 5322 calls `atext`: allowed unquoted in the local part.
+fn is_atext(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(ch)
+}
+
+// Printable ASCII except `"` and `\`, plus the space, which is allowed inside quotes.
+fn is_qtext(ch: char) -> bool {
+    ch == ' ' || (ch.is_ascii_graphic() && ch != '"' && ch != '\\')
+}
+
+impl EmailAddress {
+    pub fn parse(input: &str) -> Result<EmailAddress, EmailError> {
+        if input.is_empty() {
+            return error(0, EmailErrorKind::Empty);
+        }
+
+        let at = if input.starts_with('"') {
+            parse_quoted_local(input)?
+        } else {
+            parse_dot_atom_local(input)?
+        };
+        if at > MAX_LOCAL_LEN {
+            return error(MAX_LOCAL_LEN, EmailErrorKind::LocalPartTooLong);
+        }
+
+        let domain_start = at + 1;
+        let domain = &input[domain_start..];
+        if domain.is_empty() {
+            return error(domain_start, EmailErrorKind::EmptyDomain);
+        }
+        if domain.starts_with('[') {
+            return error(domain_start, EmailErrorKind::DomainLiteral);
+        }
+        // The ASCII form is never shorter than the number of characters, so this can be checked before encoding anything.
+        if domain.chars().count() > MAX_DOMAIN_LEN {
+            return error(domain_start, EmailErrorKind::DomainTooLong);
+        }
+
+        let mut ascii_labels = Vec::new();
+        let mut label_start = domain_start;
+        for label in domain.split('.') {
+            ascii_labels.push(parse_label(label, label_start)?);
+            label_start += label.len() + 1;
+        }
+
+        let ascii_domain = ascii_labels.join(".");
+        if ascii_domain.len() > MAX_DOMAIN_LEN {
+            return error(domain_start, EmailErrorKind::DomainTooLong);
+        }
+
+        let address = format!("{}@{}", &input[..at], ascii_domain);
+        if address.len() > MAX_ADDRESS_LEN {
+            return error(0, EmailErrorKind::AddressTooLong);
+        }
+
+        Ok(EmailAddress { address, at, unicode_domain: domain.to_lowercase() })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.address
+    }
+
+    pub fn local_part(&self) -> &str {
+        &self.address[..self.at]
+    }
+
+    // The domain in ASCII form, as it is sent over the wire.
+    pub fn domain(&self) -> &str {
+        &self.address[self.at + 1..]
+    }
+
+    // The domain as a person would write it, with any punycode labels shown in their original script.
+    pub fn unicode_domain(&self) -> &str {
+        &self.unicode_domain
+    }
+
+    pub fn is_internationalized(&self) -> bool {
+        self.domain() != self.unicode_domain
+    }
+}
+
+// Returns the byte offset of the `@` that ends the local part.
+fn parse_dot_atom_local(input: &str) -> Result<usize, EmailError> {
+    let mut previous_was_dot = true; // a leading dot is as wrong as a doubled one
+
+    for (index, ch) in input.char_indices() {
+        match ch {
+            '@' if index == 0 => return error(0, EmailErrorKind::EmptyLocalPart),
+            '@' if previous_was_dot => return error(index - 1, EmailErrorKind::MisplacedDot),
+            '@' => return Ok(index),
+            '.' if previous_was_dot => return error(index, EmailErrorKind::MisplacedDot),
+            '.' => previous_was_dot = true,
+            _ if is_atext(ch) => previous_was_dot = false,
+            _ => return error(index, EmailErrorKind::InvalidLocalChar(ch)),
+        }
+    }
+
+    error(input.len(), EmailErrorKind::MissingAt)
+}
+
+fn parse_quoted_local(input: &str) -> Result<usize, EmailError> {
+    let mut chars = input.char_indices().skip(1);
+
+    while let Some((index, ch)) = chars.next() {
+        match ch {
+            '"' => {
+                let at = index + 1;
+                return match input[at..].chars().next() {
+                    Some('@') => Ok(at),
+                    None => error(at, EmailErrorKind::MissingAt),
+                    Some(_) => error(at, EmailErrorKind::ExpectedAtAfterQuote),
+                };
+            }
+            // a quoted pair: backslash followed by any printable character or space
+            '\\' => match chars.next() {
+                Some((_, escaped)) if escaped == ' ' || escaped.is_ascii_graphic() => (),
+                Some((position, escaped)) => return error(position, EmailErrorKind::InvalidQuotedChar(escaped)),
+                None => return error(input.len(), EmailErrorKind::UnterminatedQuote),
+            },
+            _ if is_qtext(ch) => (),
+            _ => return error(index, EmailErrorKind::InvalidQuotedChar(ch)),
+        }
+    }
+
+    error(input.len(), EmailErrorKind::UnterminatedQuote)
+}
+
+// Checks one domain label starting at byte `start` of the input and returns its ASCII form.
+fn parse_label(label: &str, start: usize) -> Result<String, EmailError> {
+    if label.is_empty() {
+        return error(start, EmailErrorKind::EmptyLabel);
+    }
+    if label.starts_with('-') {
+        return error(start, EmailErrorKind::MisplacedHyphen);
+    }
+    if label.ends_with('-') {
+        return error(start + label.len() - 1, EmailErrorKind::MisplacedHyphen);
+    }
+    for (index, ch) in label.char_indices() {
+        if !(ch.is_alphanumeric() || ch == '-') {
+            return error(start + index, EmailErrorKind::InvalidDomainChar(ch));
+        }
+    }
+
+    let lower = label.to_lowercase();
+    if lower.chars().count() > MAX_LABEL_LEN {
+        return error(start, EmailErrorKind::LabelTooLong);
+    }
+    let ascii = if lower.is_ascii() {
+        lower
+    } else {
+        match punycode_encode(&lower) {
+            Some(encoded) => format!("xn--{}", encoded),
+            None => return error(start, EmailErrorKind::LabelTooLong),
+        }
+    };
+
+    if ascii.len() > MAX_LABEL_LEN {
+        return error(start, EmailErrorKind::LabelTooLong);
+    }
+    Ok(ascii)
+}
+
+/*
+    Punycode (RFC 3492) writes a Unicode string using only ASCII letters, digits and hyphens.
+    The ASCII characters of the input are copied as they are, followed by a '-' and an encoding of where
+    the remaining characters go, as a series of variable-length base-36 numbers.
+*/
+const BASE: u32 = 36;
+const T_MIN: u32 = 1;
+const T_MAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 128;
+
+fn adapt(mut delta: u32, num_points: u32, first_time: bool) -> u32 {
+    delta /= if first_time { DAMP } else { 2 };
+    delta += delta / num_points;
+
+    let mut k = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+    k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+}
+
+fn encode_digit(digit: u32) -> char {
+    if digit < 26 {
+        (b'a' + digit as u8) as char
+    } else {
+        (b'0' + (digit - 26) as u8) as char
+    }
+}
+
+// `None` if the running position overflows, which takes an input far longer than any valid label.
+fn punycode_encode(input: &str) -> Option<String> {
+    let code_points: Vec<u32> = input.chars().map(|ch| ch as u32).collect();
+    let mut output: String = input.chars().filter(char::is_ascii).collect();
+
+    let basic_count = output.len() as u32;
+    let mut handled = basic_count;
+    if basic_count > 0 {
+        output.push('-');
+    }
+
+    let mut n = INITIAL_N;
+    let mut delta = 0u32;
+    let mut bias = INITIAL_BIAS;
+
+    while (handled as usize) < code_points.len() {
+        let next = *code_points.iter().filter(|&&cp| cp >= n).min().unwrap();
+        delta = (next - n).checked_mul(handled + 1).and_then(|step| delta.checked_add(step))?;
+        n = next;
+
+        for &cp in &code_points {
+            if cp < n {
+                delta = delta.checked_add(1)?;
+            }
+            if cp == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let t = if k <= bias {
+                        T_MIN
+                    } else if k >= bias + T_MAX {
+                        T_MAX
+                    } else {
+                        k - bias
+                    };
+                    if q < t {
+                        break;
+                    }
+                    output.push(encode_digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+                output.push(encode_digit(q));
+                bias = adapt(delta, handled + 1, handled == basic_count);
+                delta = 0;
+                handled += 1;
+            }
+        }
+
+        delta = delta.checked_add(1)?;
+        n += 1;
+    }
+
+    Some(output)
+}
+
+impl FromStr for EmailAddress {
+    type Err = EmailError;
+
+    fn from_str(input: &str) -> Result<EmailAddress, EmailError> {
+        EmailAddress::parse(input)
+    }
+}
+
+impl fmt::Display for EmailAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.address)
+    }
+}
+
+impl fmt::Debug for EmailAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("EmailAddress").field(&self.address).finish()
+    }
+}
+
+impl AsRef<str> for EmailAddress {
+    fn as_ref(&self) -> &str {
+        &self.address
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn punycode_matches_rfc_examples() {
+        assert_eq!(punycode_encode("bücher").as_deref(), Some("bcher-kva"));
+        assert_eq!(punycode_encode("münchen").as_deref(), Some("mnchen-3ya"));
+        assert_eq!(EmailAddress::parse("a@Bücher.de").unwrap().domain(), "xn--bcher-kva.de");
+    }
+
+    #[test]
+    fn huge_internationalized_domain_is_rejected_without_overflow() {
+        let input = format!("x@{}\u{20000}.com", "a".repeat(40_000));
+        assert_eq!(EmailAddress::parse(&input).unwrap_err().kind, EmailErrorKind::DomainTooLong);
+
+        let label = format!("{}\u{20000}", "a".repeat(40_000));
+        assert_eq!(punycode_encode(&label), None);
+        assert_eq!(parse_label(&label, 2).unwrap_err().kind, EmailErrorKind::LabelTooLong);
+    }
+
+    #[test]
+    fn label_length_is_checked_on_the_encoded_form() {
+        let longest = format!("x@{}.com", "a".repeat(MAX_LABEL_LEN));
+        assert!(EmailAddress::parse(&longest).is_ok());
+        let too_long = format!("x@{}ü.com", "a".repeat(MAX_LABEL_LEN - 4));
+        assert_eq!(EmailAddress::parse(&too_long).unwrap_err().kind, EmailErrorKind::LabelTooLong);
+    }
+}
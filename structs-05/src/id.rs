@@ -0,0 +1,420 @@
+use std::error::Error;
+use std::fmt;
+use std::str::{self, FromStr};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/*
+    A `UserId` packs three fields into 64 bits, most significant first:
+
+        | 41 bits: milliseconds since EPOCH | 10 bits: node | 12 bits: sequence |
+
+    Because the timestamp comes first, sorting ids sorts them by creation time. The node tells apart generators running
+    at the same time in different processes, and the sequence tells apart ids made by one generator in the same millisecond.
+    41 bits of milliseconds last about 69 years from the epoch, 10 bits allow 1024 nodes, and 12 bits allow 4096 ids
+    per millisecond per node.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UserId(u64);
+
+const TIMESTAMP_BITS: u32 = 41;
+const NODE_BITS: u32 = 10;
+const SEQUENCE_BITS: u32 = 12;
+
+pub const MAX_TIMESTAMP: u64 = (1 << TIMESTAMP_BITS) - 1;
+pub const MAX_NODE: u16 = (1 << NODE_BITS) - 1;
+pub const MAX_SEQUENCE: u16 = (1 << SEQUENCE_BITS) - 1;
+
+// 2020-01-01T00:00:00Z, in milliseconds since the Unix epoch.
+pub const EPOCH: u64 = 1_577_836_800_000;
+
+impl UserId {
+    // `None` if a field doesn't fit in its bits, i.e. is past `MAX_TIMESTAMP`, `MAX_NODE` or `MAX_SEQUENCE`.
+    pub fn from_parts(timestamp: u64, node: u16, sequence: u16) -> Option<UserId> {
+        if timestamp > MAX_TIMESTAMP || node > MAX_NODE || sequence > MAX_SEQUENCE {
+            return None;
+        }
+        Some(UserId(timestamp << (NODE_BITS + SEQUENCE_BITS) | u64::from(node) << SEQUENCE_BITS | u64::from(sequence)))
+    }
+
+    pub fn from_u64(value: u64) -> UserId {
+        UserId(value)
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    // Milliseconds since `EPOCH`.
+    pub fn timestamp(&self) -> u64 {
+        self.0 >> (NODE_BITS + SEQUENCE_BITS)
+    }
+
+    pub fn node(&self) -> u16 {
+        (self.0 >> SEQUENCE_BITS) as u16 & MAX_NODE
+    }
+
+    pub fn sequence(&self) -> u16 {
+        self.0 as u16 & MAX_SEQUENCE
+    }
+
+    pub fn created_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(EPOCH + self.timestamp())
+    }
+}
+
+/*
+    The text form is Crockford's base32: 13 characters from `0-9` and `A-Z` minus `I`, `L`, `O` and `U`,
+    so an id can be read out loud or typed in without mixing up `1` and `l` or `0` and `O`.
+    It is fixed width, so ids sort the same way as text as they do as numbers.
+*/
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const TEXT_LEN: usize = 13;
+
+impl fmt::Display for UserId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut text = [0; TEXT_LEN];
+        for (i, digit) in text.iter_mut().rev().enumerate() {
+            *digit = ALPHABET[(self.0 >> (5 * i) & 31) as usize];
+        }
+        f.pad(str::from_utf8(&text).unwrap())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseIdError {
+    WrongLength(usize),
+    InvalidCharacter { position: usize, ch: char },
+    // 13 base32 digits hold 65 bits, so the first digit may be at most `F`.
+    Overflow,
+}
+
+impl fmt::Display for ParseIdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseIdError::WrongLength(len) => write!(f, "expected {} characters, found {}", TEXT_LEN, len),
+            ParseIdError::InvalidCharacter { position, ch } => {
+                write!(f, "{:?} at position {} is not a base32 digit", ch, position)
+            }
+            ParseIdError::Overflow => write!(f, "value does not fit in 64 bits"),
+        }
+    }
+}
+
+impl Error for ParseIdError {}
+
+fn decode_digit(ch: char) -> Option<u64> {
+    // Crockford's rules: case doesn't matter, and the letters left out of the alphabet read as the digits they resemble.
+    let ch = match ch.to_ascii_uppercase() {
+        'O' => '0',
+        'I' | 'L' => '1',
+        ch => ch,
+    };
+    ALPHABET.iter().position(|&digit| digit as char == ch).map(|value| value as u64)
+}
+
+impl FromStr for UserId {
+    type Err = ParseIdError;
+
+    fn from_str(text: &str) -> Result<UserId, ParseIdError> {
+        let count = text.chars().count();
+        if count != TEXT_LEN {
+            return Err(ParseIdError::WrongLength(count));
+        }
+
+        let mut value: u64 = 0;
+        for (position, ch) in text.chars().enumerate() {
+            let digit = decode_digit(ch).ok_or(ParseIdError::InvalidCharacter { position, ch })?;
+            if position == 0 && digit > 15 {
+                return Err(ParseIdError::Overflow);
+            }
+            value = value << 5 | digit;
+        }
+        Ok(UserId(value))
+    }
+}
+
+// Where a generator gets the current time from, in milliseconds since the Unix epoch.
+pub trait Clock {
+    fn now_millis(&self) -> u64;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        // A system clock set before 1970 reads as the Unix epoch, which `next_id` then reports as being before `EPOCH`.
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|since| since.as_millis() as u64).unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IdError {
+    NodeOutOfRange(u16),
+    // The clock went back further than the generator is willing to paper over.
+    ClockRolledBack { by: Duration },
+    BeforeEpoch,
+    // The 41-bit timestamp has run out, some time in 2089.
+    TimestampOverflow,
+}
+
+impl fmt::Display for IdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IdError::NodeOutOfRange(node) => write!(f, "node {} is out of range (at most {})", node, MAX_NODE),
+            IdError::ClockRolledBack { by } => write!(f, "clock moved backwards by {} ms", by.as_millis()),
+            IdError::BeforeEpoch => write!(f, "clock reads a time before the id epoch"),
+            IdError::TimestampOverflow => write!(f, "id timestamp space is exhausted"),
+        }
+    }
+}
+
+impl Error for IdError {}
+
+// The last id handed out, as its timestamp and sequence.
+#[derive(Debug)]
+struct State {
+    timestamp: u64,
+    sequence: u16,
+}
+
+/*
+    Hands out ids that are unique per node and strictly increasing, from any number of threads: `next_id` takes `&self`,
+    so one generator can be shared behind an `Arc`, and the state it needs is behind a `Mutex`.
+
+    Two things can get in the way of a plain "timestamp plus counter":
+
+    - The clock goes backwards, e.g. when NTP corrects it. Stamping ids with the earlier time would break the ordering
+      and could repeat ids already handed out, so the generator keeps using the last timestamp it issued as long as
+      the clock is behind it. Rollbacks larger than `max_rollback` are reported as errors instead, since a clock that
+      far off is more likely broken than corrected.
+    - All 4096 sequence numbers of a millisecond are used up. If the clock is keeping time, the generator waits for
+      the next millisecond. If it is behind after a rollback, waiting could take as long as the rollback, so the
+      generator moves its own timestamp one millisecond ahead instead.
+*/
+#[derive(Debug)]
+pub struct IdGenerator<C: Clock = SystemClock> {
+    node: u16,
+    clock: C,
+    max_rollback: Duration,
+    state: Mutex<State>,
+}
+
+impl IdGenerator {
+    pub fn new(node: u16) -> Result<IdGenerator, IdError> {
+        IdGenerator::with_clock(node, SystemClock)
+    }
+}
+
+impl Default for IdGenerator {
+    fn default() -> IdGenerator {
+        IdGenerator::new(0).unwrap()
+    }
+}
+
+impl<C: Clock> IdGenerator<C> {
+    pub fn with_clock(node: u16, clock: C) -> Result<IdGenerator<C>, IdError> {
+        if node > MAX_NODE {
+            return Err(IdError::NodeOutOfRange(node));
+        }
+        Ok(IdGenerator {
+            node,
+            clock,
+            max_rollback: Duration::from_secs(5),
+            state: Mutex::new(State { timestamp: 0, sequence: 0 }),
+        })
+    }
+
+    pub fn max_rollback(mut self, max_rollback: Duration) -> IdGenerator<C> {
+        self.max_rollback = max_rollback;
+        self
+    }
+
+    pub fn node(&self) -> u16 {
+        self.node
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    fn now(&self) -> Result<u64, IdError> {
+        let now = self.clock.now_millis().checked_sub(EPOCH).ok_or(IdError::BeforeEpoch)?;
+        if now > MAX_TIMESTAMP {
+            return Err(IdError::TimestampOverflow);
+        }
+        Ok(now)
+    }
+
+    pub fn next_id(&self) -> Result<UserId, IdError> {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut now = self.now()?;
+
+        if now < state.timestamp {
+            let behind = Duration::from_millis(state.timestamp - now);
+            if behind > self.max_rollback {
+                return Err(IdError::ClockRolledBack { by: behind });
+            }
+        }
+
+        if now > state.timestamp {
+            state.timestamp = now;
+            state.sequence = 0;
+        } else if state.sequence < MAX_SEQUENCE {
+            state.sequence += 1;
+        } else {
+            while now == state.timestamp {
+                thread::yield_now();
+                now = self.now()?;
+            }
+            // `now` is either the next millisecond, or still behind after a rollback and `timestamp + 1` runs ahead of it.
+            state.timestamp = now.max(state.timestamp + 1);
+            state.sequence = 0;
+        }
+
+        // The node was checked by `with_clock` and the sequence stays in range, so only the timestamp can be too large.
+        UserId::from_parts(state.timestamp, self.node, state.sequence).ok_or(IdError::TimestampOverflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    // A clock that only moves when it is told to.
+    struct ManualClock(AtomicU64);
+
+    impl ManualClock {
+        fn set(&self, millis: u64) {
+            self.0.store(millis, Ordering::SeqCst);
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now_millis(&self) -> u64 {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    const START: u64 = EPOCH + 1_000_000;
+
+    fn manual(node: u16) -> IdGenerator<ManualClock> {
+        IdGenerator::with_clock(node, ManualClock(AtomicU64::new(START))).unwrap().max_rollback(Duration::from_secs(5))
+    }
+
+    #[test]
+    fn parts_round_trip_and_out_of_range_parts_are_refused() {
+        let id = UserId::from_parts(MAX_TIMESTAMP, MAX_NODE, MAX_SEQUENCE).unwrap();
+        assert_eq!((id.timestamp(), id.node(), id.sequence()), (MAX_TIMESTAMP, MAX_NODE, MAX_SEQUENCE));
+        assert_eq!(id.as_u64(), u64::MAX >> 1);
+        let id = UserId::from_parts(5, 3, 2).unwrap();
+        assert_eq!((id.timestamp(), id.node(), id.sequence()), (5, 3, 2));
+        assert_eq!(id.created_at(), UNIX_EPOCH + Duration::from_millis(EPOCH + 5));
+
+        assert_eq!(UserId::from_parts(MAX_TIMESTAMP + 1, 0, 0), None);
+        assert_eq!(UserId::from_parts(0, MAX_NODE + 1, 0), None);
+        assert_eq!(UserId::from_parts(0, 0, MAX_SEQUENCE + 1), None);
+    }
+
+    #[test]
+    fn text_form_round_trips_and_sorts_like_the_number() {
+        let ids = [UserId::from_u64(0), UserId::from_parts(1, 2, 3).unwrap(), UserId::from_u64(u64::MAX)];
+        for id in &ids {
+            assert_eq!(id.to_string().len(), TEXT_LEN);
+            assert_eq!(id.to_string().parse(), Ok(*id));
+            assert_eq!(id.to_string().to_lowercase().parse(), Ok(*id));
+        }
+        assert_eq!(ids[2].to_string(), "FZZZZZZZZZZZZ");
+        assert!(ids[0].to_string() < ids[1].to_string() && ids[1].to_string() < ids[2].to_string());
+        assert_eq!("0000000000OIL".parse(), Ok(UserId::from_u64(0b00001_00001)));
+
+        assert_eq!("0123".parse::<UserId>(), Err(ParseIdError::WrongLength(4)));
+        assert_eq!("0123456789ABU".parse::<UserId>(), Err(ParseIdError::InvalidCharacter { position: 12, ch: 'U' }));
+        assert_eq!("ZZZZZZZZZZZZZ".parse::<UserId>(), Err(ParseIdError::Overflow));
+    }
+
+    #[test]
+    fn nodes_past_the_limit_are_refused() {
+        assert_eq!(IdGenerator::new(MAX_NODE + 1).err(), Some(IdError::NodeOutOfRange(MAX_NODE + 1)));
+        assert_eq!(IdGenerator::new(MAX_NODE).unwrap().node(), MAX_NODE);
+    }
+
+    #[test]
+    fn ids_from_several_threads_are_unique_and_increasing() {
+        let shared = Arc::new(IdGenerator::new(7).unwrap());
+        let handles: Vec<_> = (0..4).map(|_| {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || (0..20_000).map(|_| shared.next_id().unwrap()).collect::<Vec<_>>())
+        }).collect();
+
+        let mut all = HashSet::new();
+        for handle in handles {
+            let batch = handle.join().unwrap();
+            assert!(batch.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(batch.iter().all(|id| id.node() == 7));
+            all.extend(batch);
+        }
+        assert_eq!(all.len(), 80_000);
+    }
+
+    #[test]
+    fn a_small_clock_rollback_keeps_counting_from_the_last_timestamp() {
+        let ids = manual(3);
+        let before = ids.next_id().unwrap();
+        assert_eq!((before.timestamp(), before.sequence()), (1_000_000, 0));
+
+        ids.clock().set(START - 2_000);
+        let after = ids.next_id().unwrap();
+        assert_eq!((after.timestamp(), after.sequence()), (1_000_000, 1));
+
+        // Once the clock catches up again, the timestamp follows it.
+        ids.clock().set(START + 1);
+        assert_eq!(ids.next_id().unwrap().timestamp(), 1_000_001);
+    }
+
+    #[test]
+    fn a_large_rollback_or_a_clock_before_the_epoch_is_an_error() {
+        let ids = manual(3);
+        ids.next_id().unwrap();
+        ids.clock().set(START - 60_000);
+        assert_eq!(ids.next_id(), Err(IdError::ClockRolledBack { by: Duration::from_secs(60) }));
+        ids.clock().set(EPOCH - 1);
+        assert_eq!(ids.next_id(), Err(IdError::BeforeEpoch));
+        ids.clock().set(EPOCH + MAX_TIMESTAMP + 1);
+        assert_eq!(ids.next_id(), Err(IdError::TimestampOverflow));
+    }
+
+    #[test]
+    fn running_out_of_sequence_numbers_behind_the_clock_moves_a_millisecond_ahead() {
+        let ids = manual(1);
+        ids.next_id().unwrap();
+        ids.clock().set(START - 2_000);
+
+        // Sequence 0 went to the first id; the rest of the millisecond, 1 through MAX_SEQUENCE, goes to these.
+        let mut last = None;
+        for sequence in 1..=MAX_SEQUENCE {
+            let id = ids.next_id().unwrap();
+            assert_eq!((id.timestamp(), id.sequence()), (1_000_000, sequence));
+            last = Some(id);
+        }
+        let next = ids.next_id().unwrap();
+        assert_eq!((next.timestamp(), next.sequence()), (1_000_001, 0));
+        assert!(next > last.unwrap());
+    }
+
+    #[test]
+    fn running_out_of_the_timestamp_space_is_an_error() {
+        let ids = IdGenerator::with_clock(0, ManualClock(AtomicU64::new(EPOCH + MAX_TIMESTAMP))).unwrap();
+        for _ in 0..=MAX_SEQUENCE {
+            assert_eq!(ids.next_id().unwrap().timestamp(), MAX_TIMESTAMP);
+        }
+        // Moving ahead would go past the largest timestamp, and waiting for the clock can't help either.
+        ids.clock().set(EPOCH + MAX_TIMESTAMP - 1);
+        assert_eq!(ids.next_id(), Err(IdError::TimestampOverflow));
+    }
+}
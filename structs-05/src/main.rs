@@ -1,3 +1,18 @@
+#[macro_use]
+extern crate builder_derive;
+
+mod email;
+mod id;
+mod registry;
+mod vector3;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use email::EmailAddress;
+use id::{Clock, IdGenerator, UserId};
+use registry::{UserRegistry, UserUpdate};
+
 /*
     A struct, or structure, is a custom data type that lets you name and package together multiple related values that make up a meaningful group.
     If you’re familiar with an object-oriented language, a struct is like an object’s data attributes.
@@ -8,10 +23,11 @@
     Unlike with tuples, you’ll name each piece of data so it’s clear what the values mean. As a result of these names,
     structs are more flexible than tuples: you don’t have to rely on the order of the data to specify or access the values of an instance.
 */
+#[derive(Builder, Clone, Debug, PartialEq)]
 struct User {
     username: String,
-    email: String,
-    id: usize
+    email: EmailAddress,
+    id: UserId
 }
 
 /*
@@ -24,9 +40,10 @@ struct User {
 
     Each struct you define is its own type, even though the fields within the struct have the same types.
 */
-struct Vector3(i32, i32, i32);
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Vector3<T>(T, T, T);
 
-fn build_user(email: String, username: String, id: usize) -> User {
+fn build_user(email: EmailAddress, username: String, id: UserId) -> User {
     User {
         email,
         username,
@@ -34,15 +51,154 @@ fn build_user(email: String, username: String, id: usize) -> User {
     }
 }
 
+/*
+    `build_user` takes its arguments by position, and since `email` and `username` are both strings in most code that calls it,
+    nothing catches them being passed the wrong way round. `#[derive(Builder)]` generates a builder with one named setter per field.
+    Fields are required unless they are an `Option` or marked `#[builder(default)]`, and `build` only compiles once every required
+    field has been set. An optional validation function runs on the finished value.
+*/
+#[derive(Builder, Debug)]
+#[builder(validate = "validate_profile")]
+struct Profile {
+    username: String,
+    display_name: Option<String>,
+    #[builder(default = "String::from(\"en\")")]
+    locale: String,
+    #[builder(default)]
+    followers: u32,
+}
+
+fn validate_profile(profile: &Profile) -> Result<(), String> {
+    match profile.display_name {
+        Some(ref name) if name.trim().is_empty() => Err(format!("display name of {} is blank", profile.username)),
+        _ => Ok(()),
+    }
+}
+
+// A clock that only moves when told to, for showing what the id generator does when time misbehaves.
+struct ManualClock(AtomicU64);
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
 fn main() {
+    let ids = IdGenerator::new(1).unwrap();
     let mut user1 = User {
         username: String::from("someusername123"),
-        email: String::from("someone@example.com"),
-        id: 1
+        email: "someone@example.com".parse().unwrap(),
+        id: ids.next_id().unwrap()
     };
 
     // To get a specific value from a struct, we can use dot notation.
-    user1.email = String::from("anotheremail@example.com");
+    user1.email = "anotheremail@example.com".parse().unwrap();
 
     let origin = Vector3(0, 0, 0);
+    println!("origin: ({}, {}, {})", origin.0, origin.1, origin.2);
+
+    /*
+        A tuple struct can still get behaviour of its own. With operator overloading, `Vector3` reads like the math it stands for,
+        and being generic over its component type lets the same code serve integer grids and floating-point physics.
+    */
+    let x_axis = Vector3(1, 0, 0);
+    let y_axis = Vector3::new(0, 1, 0);
+    let mut grid = x_axis.cross(&y_axis) * 3 - origin;
+    grid[0] += 2;
+    println!("{} · {} = {}, -grid = {}, 2 * grid = {}", grid, x_axis, grid.dot(&x_axis), -grid, 2 * grid);
+
+    let gravity = Vector3(0.0, -9.81, 0.0);
+    let mut position = Vector3::<f64>::zero();
+    let mut velocity: Vector3<f64> = "(3.0, 12.0, 4.0)".parse().unwrap();
+    let dt = 0.1;
+    while position.y() >= 0.0 {
+        velocity += gravity * dt;
+        position += velocity * dt;
+    }
+    velocity *= 0.5;
+    println!("landed near ({:.2}, {:.2}) after {:.2} m", position.x(), position.z(), position.distance(&Vector3::zero()));
+    println!("{:.3} heading {:.3}", velocity.length(), velocity.normalize().unwrap());
+    println!("{:.2}", Vector3(0.0, 0.0, 0.0).lerp(&Vector3(2.0, 4.0, 8.0), 0.25));
+    println!("{:?} {:?}", Vector3::<f32>::zero().normalize(), "1 2".parse::<Vector3<i32>>());
+    println!("{:?} {}", "1, x, 3".parse::<Vector3<i32>>(), Vector3(1u8, 2, 3).length_squared());
+    println!("{}", Vector3(-1, 0, 1).map(|c: i32| c.abs()));
+
+    /*
+        Nothing stops `build_user` from creating two users with the same username or an email without an `@`.
+        A registry owns all the users and checks every change against the others before accepting it.
+    */
+    let mut registry = UserRegistry::new();
+    let alice = registry.register("alice", "alice@example.com").unwrap().id;
+    let bob = registry.register("bob", "bob@example.com").unwrap().id;
+
+    for (username, email) in &[("Alice", "alice2@example.com"), ("carol", "BOB@example.com"), ("x", "x@example.com"), ("dave", "dave")] {
+        if let Err(error) = registry.register(username, email) {
+            println!("rejected: {}", error);
+        }
+    }
+
+    let rename = UserUpdate { username: Some(String::from("Alice")), ..UserUpdate::default() };
+    println!("{:?}", registry.update(alice, rename));
+    let steal = UserUpdate { email: Some(String::from("Alice@Example.com")), ..UserUpdate::default() };
+    println!("{:?}", registry.update(bob, steal));
+
+    println!("{:?}", registry.find_by_username("ALICE").map(|user| user.id));
+    println!("{:?}", registry.find_by_email("bob@EXAMPLE.com").map(|user| &user.username));
+    println!("{:?}", registry.remove(bob));
+    println!("{:?}", registry.get(bob));
+    println!("{:?}", registry.remove(bob));
+    println!("{} user(s): {:?}", registry.len(), registry.iter().collect::<Vec<_>>());
+    println!("empty: {}", registry.is_empty());
+
+    /*
+        Giving `email` its own type instead of `String` means a `User` can't hold an address that was never checked:
+        the only way to get an `EmailAddress` is to parse one, and parsing says exactly where a bad address goes wrong.
+    */
+    for input in &["someone@example.com", "\"john doe\"@example.com", "user@bücher.example", "user@한국.kr", "a..b@example.com", "user@exa_mple.com", "user@-example.com"] {
+        match input.parse::<EmailAddress>() {
+            Ok(email) => println!("{} -> {} (local {:?}, domain {:?}, shown as {:?}, international: {})",
+                                  input, email, email.local_part(), email.domain(), email.unicode_domain(), email.is_internationalized()),
+            Err(error) => println!("{} -> {}", input, error),
+        }
+    }
+    println!("{:?}", registry.find_by_email("alice@EXAMPLE.com").map(|user| user.email.as_ref().len()));
+
+    // The setters can come in any order. Leaving one out, e.g. `.id(...)`, makes `build` a compile error instead of a runtime one.
+    let user2 = User::builder()
+        .id(ids.next_id().unwrap())
+        .username(String::from("user2"))
+        .email("user2@example.com".parse().unwrap())
+        .build();
+    println!("{:?}", user2);
+
+    let profile = Profile::builder().username(String::from("alice")).followers(10).build().unwrap();
+    println!("{} ({:?}, {}, {} followers)", profile.username, profile.display_name, profile.locale, profile.followers);
+    println!("{:?}", Profile::builder().display_name(String::from(" ")).username(String::from("bob")).locale(String::from("ko")).build());
+
+    /*
+        Counting ids up by hand only works while one piece of code hands them all out. A generated id carries the time
+        it was made, the node that made it and a per-millisecond sequence number, so generators on different threads or
+        machines never collide, and sorting ids sorts them by age.
+    */
+    println!("user1 {} = {:#x}: node {}, sequence {}, {:?} ms after the epoch",
+             user1.id, user1.id.as_u64(), user1.id.node(), user1.id.sequence(), user1.id.timestamp());
+    println!("created at {:?}, parsed back: {:?}", user1.id.created_at(), user1.id.to_string().to_lowercase().parse::<UserId>() == Ok(user1.id));
+    println!("{:?} {:?}", "0123456789ABU".parse::<UserId>(), "ZZZZZZZZZZZZZ".parse::<UserId>().map_err(|error| error.to_string()));
+    println!("{:?} {}", IdGenerator::new(2000).map(|ids| ids.node()), UserId::from_u64(u64::MAX));
+    println!("{:?} {:?}", UserId::from_parts(1, 2, 3).map(|id| id.to_string()), UserId::from_parts(0, 0, id::MAX_SEQUENCE + 1));
+
+    // The clock jumps back 2 seconds: ids keep counting on from the last timestamp. Beyond `max_rollback`, that's an error.
+    let start = id::EPOCH + 1_000_000;
+    let clock = ManualClock(AtomicU64::new(start));
+    let manual = IdGenerator::with_clock(3, clock).unwrap().max_rollback(Duration::from_secs(5));
+    let before = manual.next_id().unwrap();
+    manual.clock().0.store(start - 2_000, Ordering::SeqCst);
+    let after = manual.next_id().unwrap();
+    println!("rollback: {} then {} (timestamp {} -> {}, increasing: {})", before, after, before.timestamp(), after.timestamp(), after > before);
+
+    manual.clock().0.store(start - 60_000, Ordering::SeqCst);
+    println!("{:?}", manual.next_id().map_err(|error| error.to_string()));
+    manual.clock().0.store(id::EPOCH - 1, Ordering::SeqCst);
+    println!("{:?}", manual.next_id());
 }
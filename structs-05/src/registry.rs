@@ -0,0 +1,297 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+
+use email::{EmailAddress, EmailError};
+use id::{IdError, IdGenerator, UserId};
+use {build_user, User};
+
+/*
+    `build_user` happily builds a `User` out of any username and any id. `UserRegistry` is the one place users are created:
+    it hands out ids itself, checks usernames and emails before accepting them, and keeps both unique.
+    Uniqueness ignores case, so "Alice" and "alice" count as the same username.
+
+    Every way a request can be refused has its own `RegistryError` variant, so callers can react to the reason
+    instead of parsing a message.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegistryError {
+    InvalidUsername { username: String, reason: &'static str },
+    InvalidEmail { email: String, error: EmailError },
+    DuplicateUsername { username: String, taken_by: UserId },
+    DuplicateEmail { email: String, taken_by: UserId },
+    NotFound { id: UserId },
+    Id(IdError),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RegistryError::InvalidUsername { ref username, reason } => {
+                write!(f, "invalid username {:?}: {}", username, reason)
+            }
+            RegistryError::InvalidEmail { ref email, ref error } => write!(f, "invalid email {:?}: {}", email, error),
+            RegistryError::DuplicateUsername { ref username, taken_by } => {
+                write!(f, "username {:?} is already taken by user {}", username, taken_by)
+            }
+            RegistryError::DuplicateEmail { ref email, taken_by } => {
+                write!(f, "email {:?} is already registered to user {}", email, taken_by)
+            }
+            RegistryError::NotFound { id } => write!(f, "no user with id {}", id),
+            RegistryError::Id(ref error) => write!(f, "could not allocate an id: {}", error),
+        }
+    }
+}
+
+impl Error for RegistryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            RegistryError::InvalidEmail { ref error, .. } => Some(error),
+            RegistryError::Id(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+const USERNAME_MIN_LEN: usize = 3;
+const USERNAME_MAX_LEN: usize = 32;
+
+fn validate_username(username: &str) -> Result<(), RegistryError> {
+    let invalid = |reason| {
+        Err(RegistryError::InvalidUsername { username: username.to_string(), reason })
+    };
+
+    let len = username.chars().count();
+    if len < USERNAME_MIN_LEN {
+        return invalid("must be at least 3 characters long");
+    }
+    if len > USERNAME_MAX_LEN {
+        return invalid("must be at most 32 characters long");
+    }
+    if !username.chars().all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '-' || ch == '.') {
+        return invalid("may only contain letters, digits, '_', '-' and '.'");
+    }
+    if !username.chars().next().unwrap().is_alphanumeric() {
+        return invalid("must start with a letter or digit");
+    }
+    Ok(())
+}
+
+fn parse_email(email: &str) -> Result<EmailAddress, RegistryError> {
+    EmailAddress::parse(email).map_err(|error| RegistryError::InvalidEmail { email: email.to_string(), error })
+}
+
+// Usernames and emails are compared case-insensitively, so the indexes are keyed by their lowercase form.
+fn key(value: &str) -> String {
+    value.to_lowercase()
+}
+
+// Fields left as `None` keep their current value.
+#[derive(Clone, Debug, Default)]
+pub struct UserUpdate {
+    pub username: Option<String>,
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct UserRegistry {
+    ids: IdGenerator,
+    users: BTreeMap<UserId, User>,
+    by_username: HashMap<String, UserId>,
+    by_email: HashMap<String, UserId>,
+}
+
+impl UserRegistry {
+    pub fn new() -> UserRegistry {
+        UserRegistry::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.users.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+
+    // Validates both fields, checks they are free, and stores the user under a newly allocated id.
+    pub fn register(&mut self, username: &str, email: &str) -> Result<&User, RegistryError> {
+        validate_username(username)?;
+        let email = parse_email(email)?;
+        self.check_available(username, email.as_str(), None)?;
+
+        // Ids are never handed out twice, even after the user holding one is removed.
+        let id = self.ids.next_id().map_err(RegistryError::Id)?;
+
+        self.by_username.insert(key(username), id);
+        self.by_email.insert(key(email.as_str()), id);
+        self.users.insert(id, build_user(email, username.to_string(), id));
+        Ok(&self.users[&id])
+    }
+
+    // Either every requested change is applied or, on error, none of them is.
+    pub fn update(&mut self, id: UserId, update: UserUpdate) -> Result<&User, RegistryError> {
+        let (username, email) = {
+            let current = self.get(id).ok_or(RegistryError::NotFound { id })?;
+            (
+                update.username.unwrap_or_else(|| current.username.clone()),
+                match update.email {
+                    Some(email) => parse_email(&email)?,
+                    None => current.email.clone(),
+                },
+            )
+        };
+
+        validate_username(&username)?;
+        self.check_available(&username, email.as_str(), Some(id))?;
+
+        let user = self.users.get_mut(&id).unwrap();
+        if user.username != username {
+            self.by_username.remove(&key(&user.username));
+            self.by_username.insert(key(&username), id);
+            user.username = username;
+        }
+        if user.email != email {
+            self.by_email.remove(&key(user.email.as_str()));
+            self.by_email.insert(key(email.as_str()), id);
+            user.email = email;
+        }
+        Ok(user)
+    }
+
+    pub fn remove(&mut self, id: UserId) -> Result<User, RegistryError> {
+        let user = self.users.remove(&id).ok_or(RegistryError::NotFound { id })?;
+        self.by_username.remove(&key(&user.username));
+        self.by_email.remove(&key(user.email.as_str()));
+        Ok(user)
+    }
+
+    pub fn get(&self, id: UserId) -> Option<&User> {
+        self.users.get(&id)
+    }
+
+    pub fn find_by_username(&self, username: &str) -> Option<&User> {
+        self.by_username.get(&key(username)).and_then(|id| self.users.get(id))
+    }
+
+    // Accepts the address in any spelling that parses to the same address, e.g. with a Unicode or uppercase domain.
+    pub fn find_by_email(&self, email: &str) -> Option<&User> {
+        let email = EmailAddress::parse(email).ok()?;
+        self.by_email.get(&key(email.as_str())).and_then(|id| self.users.get(id))
+    }
+
+    // All users in id order, which is the order they registered in.
+    pub fn iter(&self) -> impl Iterator<Item = &User> {
+        self.users.values()
+    }
+
+    // `exclude` is the user being updated, who may of course keep their own username and email.
+    fn check_available(&self, username: &str, email: &str, exclude: Option<UserId>) -> Result<(), RegistryError> {
+        if let Some(&taken_by) = self.by_username.get(&key(username)) {
+            if Some(taken_by) != exclude {
+                return Err(RegistryError::DuplicateUsername { username: username.to_string(), taken_by });
+            }
+        }
+        if let Some(&taken_by) = self.by_email.get(&key(email)) {
+            if Some(taken_by) != exclude {
+                return Err(RegistryError::DuplicateEmail { email: email.to_string(), taken_by });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> (UserRegistry, UserId, UserId) {
+        let mut registry = UserRegistry::new();
+        let alice = registry.register("alice", "alice@example.com").unwrap().id;
+        let bob = registry.register("bob", "bob@example.com").unwrap().id;
+        (registry, alice, bob)
+    }
+
+    fn rename(username: &str) -> UserUpdate {
+        UserUpdate { username: Some(username.to_string()), email: None }
+    }
+
+    #[test]
+    fn duplicates_are_rejected_ignoring_case() {
+        let (mut registry, alice, bob) = registry();
+        assert!(alice < bob);
+        assert_eq!(
+            registry.register("ALICE", "other@example.com"),
+            Err(RegistryError::DuplicateUsername { username: "ALICE".to_string(), taken_by: alice })
+        );
+        assert_eq!(
+            registry.register("carol", "Bob@Example.COM"),
+            Err(RegistryError::DuplicateEmail { email: "Bob@example.com".to_string(), taken_by: bob })
+        );
+        assert_eq!(registry.len(), 2);
+        assert!(registry.find_by_username("carol").is_none());
+    }
+
+    #[test]
+    fn invalid_fields_are_rejected() {
+        let mut registry = UserRegistry::new();
+        for username in &["al", "-alice", "al ice", &"a".repeat(33)] {
+            match registry.register(username, "someone@example.com") {
+                Err(RegistryError::InvalidUsername { username: ref rejected, .. }) => assert_eq!(rejected, username),
+                other => panic!("{:?}: {:?}", username, other),
+            }
+        }
+        match registry.register("alice", "not an email") {
+            Err(ref error @ RegistryError::InvalidEmail { .. }) => assert!(error.source().is_some()),
+            other => panic!("{:?}", other),
+        }
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn a_failed_update_changes_nothing() {
+        let (mut registry, alice, bob) = registry();
+        let steal = UserUpdate { username: Some("alicia".to_string()), email: Some("BOB@example.com".to_string()) };
+        assert_eq!(
+            registry.update(alice, steal),
+            Err(RegistryError::DuplicateEmail { email: "BOB@example.com".to_string(), taken_by: bob })
+        );
+        let bad_email = UserUpdate { username: Some("alicia".to_string()), email: Some("@example.com".to_string()) };
+        assert!(registry.update(alice, bad_email).is_err());
+        assert!(registry.update(alice, rename("x")).is_err());
+
+        let unchanged = registry.get(alice).unwrap();
+        assert_eq!((unchanged.username.as_str(), unchanged.email.as_str()), ("alice", "alice@example.com"));
+        assert!(registry.find_by_username("alicia").is_none());
+        assert_eq!(registry.find_by_username("alice").unwrap().id, alice);
+    }
+
+    #[test]
+    fn lookups_follow_a_rename() {
+        let (mut registry, alice, _) = registry();
+        let update = UserUpdate { username: Some("Alicia".to_string()), email: Some("alicia@example.com".to_string()) };
+        assert_eq!(registry.update(alice, update).unwrap().username, "Alicia");
+
+        assert!(registry.find_by_username("alice").is_none());
+        assert!(registry.find_by_email("alice@example.com").is_none());
+        assert_eq!(registry.find_by_username("ALICIA").unwrap().id, alice);
+        assert_eq!(registry.find_by_email("alicia@EXAMPLE.com").unwrap().id, alice);
+        // The old name is free again, and a user may change the case of their own.
+        assert!(registry.register("alice", "new@example.com").is_ok());
+        assert_eq!(registry.update(alice, rename("ALICIA")).unwrap().username, "ALICIA");
+    }
+
+    #[test]
+    fn removed_users_free_their_names_but_not_their_ids() {
+        let (mut registry, alice, bob) = registry();
+        assert_eq!(registry.remove(bob).unwrap().username, "bob");
+        assert_eq!(registry.remove(bob), Err(RegistryError::NotFound { id: bob }));
+        assert_eq!(registry.update(bob, rename("bobby")), Err(RegistryError::NotFound { id: bob }));
+        assert!(registry.get(bob).is_none());
+
+        let again = registry.register("bob", "bob@example.com").unwrap().id;
+        assert!(again != bob);
+        let ids: Vec<UserId> = registry.iter().map(|user| user.id).collect();
+        assert_eq!(ids, vec![alice, again]);
+    }
+}
@@ -0,0 +1,357 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+use Vector3;
+
+/*
+    The arithmetic a component type needs for `Vector3` to do vector math with it.
+    It is implemented for the built-in integer and floating-point types below; `Vector3<u32>` still works,
+    it just has no `Neg`, because `u32` doesn't.
+*/
+pub trait Number:
+    Copy + PartialEq + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self>
+{
+    fn zero() -> Self;
+}
+
+// Lengths and unit vectors involve square roots, which only make sense for floating-point components.
+pub trait Float: Number {
+    fn sqrt(self) -> Self;
+}
+
+macro_rules! impl_number {
+    ($zero:expr; $($t:ty)*) => {$(
+        impl Number for $t {
+            fn zero() -> $t {
+                $zero
+            }
+        }
+
+        // `2 * v` as well as `v * 2`; the scalar has to be a concrete type for the compiler to accept this impl.
+        impl Mul<Vector3<$t>> for $t {
+            type Output = Vector3<$t>;
+
+            fn mul(self, vector: Vector3<$t>) -> Vector3<$t> {
+                vector * self
+            }
+        }
+    )*};
+}
+
+impl_number!(0; i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+impl_number!(0.0; f32 f64);
+
+impl Float for f32 {
+    fn sqrt(self) -> f32 {
+        f32::sqrt(self)
+    }
+}
+
+impl Float for f64 {
+    fn sqrt(self) -> f64 {
+        f64::sqrt(self)
+    }
+}
+
+impl<T: Number> Vector3<T> {
+    pub fn new(x: T, y: T, z: T) -> Vector3<T> {
+        Vector3(x, y, z)
+    }
+
+    pub fn zero() -> Vector3<T> {
+        Vector3(T::zero(), T::zero(), T::zero())
+    }
+
+    pub fn x(&self) -> T {
+        self.0
+    }
+
+    pub fn y(&self) -> T {
+        self.1
+    }
+
+    pub fn z(&self) -> T {
+        self.2
+    }
+
+    pub fn dot(&self, other: &Vector3<T>) -> T {
+        self.0 * other.0 + self.1 * other.1 + self.2 * other.2
+    }
+
+    // The vector perpendicular to both, following the right-hand rule.
+    pub fn cross(&self, other: &Vector3<T>) -> Vector3<T> {
+        Vector3(
+            self.1 * other.2 - self.2 * other.1,
+            self.2 * other.0 - self.0 * other.2,
+            self.0 * other.1 - self.1 * other.0,
+        )
+    }
+
+    // Cheaper than `length` and enough for comparing lengths, since squaring preserves their order.
+    pub fn length_squared(&self) -> T {
+        self.dot(self)
+    }
+
+    pub fn map<U, F: Fn(T) -> U>(&self, f: F) -> Vector3<U> {
+        Vector3(f(self.0), f(self.1), f(self.2))
+    }
+}
+
+impl<T: Float> Vector3<T> {
+    pub fn length(&self) -> T {
+        self.length_squared().sqrt()
+    }
+
+    pub fn distance(&self, other: &Vector3<T>) -> T {
+        (*self - *other).length()
+    }
+
+    // The vector with the same direction and a length of 1, or `None` for the zero vector, which has no direction.
+    pub fn normalize(&self) -> Option<Vector3<T>> {
+        let length = self.length();
+        if length == T::zero() {
+            None
+        } else {
+            Some(*self / length)
+        }
+    }
+
+    // The point `t` of the way from `self` to `other`; `t = 0` gives `self` and `t = 1` gives `other`.
+    pub fn lerp(&self, other: &Vector3<T>, t: T) -> Vector3<T> {
+        *self + (*other - *self) * t
+    }
+}
+
+impl<T: Number> Add for Vector3<T> {
+    type Output = Vector3<T>;
+
+    fn add(self, other: Vector3<T>) -> Vector3<T> {
+        Vector3(self.0 + other.0, self.1 + other.1, self.2 + other.2)
+    }
+}
+
+impl<T: Number> Sub for Vector3<T> {
+    type Output = Vector3<T>;
+
+    fn sub(self, other: Vector3<T>) -> Vector3<T> {
+        Vector3(self.0 - other.0, self.1 - other.1, self.2 - other.2)
+    }
+}
+
+impl<T: Number + Neg<Output = T>> Neg for Vector3<T> {
+    type Output = Vector3<T>;
+
+    fn neg(self) -> Vector3<T> {
+        Vector3(-self.0, -self.1, -self.2)
+    }
+}
+
+impl<T: Number> Mul<T> for Vector3<T> {
+    type Output = Vector3<T>;
+
+    fn mul(self, scalar: T) -> Vector3<T> {
+        Vector3(self.0 * scalar, self.1 * scalar, self.2 * scalar)
+    }
+}
+
+impl<T: Number> Div<T> for Vector3<T> {
+    type Output = Vector3<T>;
+
+    fn div(self, scalar: T) -> Vector3<T> {
+        Vector3(self.0 / scalar, self.1 / scalar, self.2 / scalar)
+    }
+}
+
+impl<T: Number> AddAssign for Vector3<T> {
+    fn add_assign(&mut self, other: Vector3<T>) {
+        *self = *self + other;
+    }
+}
+
+impl<T: Number> SubAssign for Vector3<T> {
+    fn sub_assign(&mut self, other: Vector3<T>) {
+        *self = *self - other;
+    }
+}
+
+impl<T: Number> MulAssign<T> for Vector3<T> {
+    fn mul_assign(&mut self, scalar: T) {
+        *self = *self * scalar;
+    }
+}
+
+// `v[0]`, `v[1]` and `v[2]` are x, y and z; any other index panics, like indexing past the end of an array.
+impl<T> Index<usize> for Vector3<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        match index {
+            0 => &self.0,
+            1 => &self.1,
+            2 => &self.2,
+            _ => panic!("index out of bounds: a Vector3 has 3 components but the index is {}", index),
+        }
+    }
+}
+
+impl<T> IndexMut<usize> for Vector3<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        match index {
+            0 => &mut self.0,
+            1 => &mut self.1,
+            2 => &mut self.2,
+            _ => panic!("index out of bounds: a Vector3 has 3 components but the index is {}", index),
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Vector3<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Forward the formatter so precision flags like `{:.2}` apply to every component.
+        f.write_str("(")?;
+        self.0.fmt(f)?;
+        f.write_str(", ")?;
+        self.1.fmt(f)?;
+        f.write_str(", ")?;
+        self.2.fmt(f)?;
+        f.write_str(")")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseVectorError {
+    WrongComponentCount(usize),
+    InvalidComponent { index: usize, text: String },
+}
+
+impl fmt::Display for ParseVectorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseVectorError::WrongComponentCount(count) => write!(f, "expected 3 components, found {}", count),
+            ParseVectorError::InvalidComponent { index, ref text } => {
+                write!(f, "component {} ({:?}) is not a valid number", index, text)
+            }
+        }
+    }
+}
+
+impl Error for ParseVectorError {}
+
+// Accepts what `Display` prints, `(1, 2, 3)`, as well as the bare `1, 2, 3` and `1 2 3`.
+impl<T: FromStr> FromStr for Vector3<T> {
+    type Err = ParseVectorError;
+
+    fn from_str(text: &str) -> Result<Vector3<T>, ParseVectorError> {
+        let text = text.trim();
+        let inner = if text.starts_with('(') && text.ends_with(')') {
+            &text[1..text.len() - 1]
+        } else {
+            text
+        };
+
+        let parts: Vec<&str> = if inner.contains(',') {
+            inner.split(',').map(str::trim).collect()
+        } else {
+            inner.split_whitespace().collect()
+        };
+        if parts.len() != 3 {
+            return Err(ParseVectorError::WrongComponentCount(parts.len()));
+        }
+
+        let parse = |index: usize| {
+            parts[index]
+                .parse::<T>()
+                .map_err(|_| ParseVectorError::InvalidComponent { index, text: parts[index].to_string() })
+        };
+        Ok(Vector3(parse(0)?, parse(1)?, parse(2)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operators_work_for_integer_and_float_components() {
+        let a = Vector3(1, 2, 3);
+        let b = Vector3::new(4, 5, 6);
+        assert_eq!(a + b, Vector3(5, 7, 9));
+        assert_eq!(b - a, Vector3(3, 3, 3));
+        assert_eq!(-a, Vector3(-1, -2, -3));
+        assert_eq!(a * 2, Vector3(2, 4, 6));
+        assert_eq!(2 * a, a * 2);
+        assert_eq!(b / 2, Vector3(2, 2, 3));
+
+        let mut v = Vector3(1.5f32, 0.0, -2.0);
+        v += Vector3(0.5, 1.0, 1.0);
+        v -= Vector3(1.0, 0.0, 0.0);
+        v *= 2.0;
+        assert_eq!(v, Vector3(2.0, 2.0, -2.0));
+        assert_eq!(0.5 * v, Vector3(1.0, 1.0, -1.0));
+
+        // Unsigned components get everything but negation.
+        assert_eq!(Vector3(1u8, 2, 3) + Vector3(1, 1, 1), Vector3(2, 3, 4));
+        assert_eq!(Vector3(1u8, 2, 3).length_squared(), 14);
+    }
+
+    #[test]
+    fn dot_and_cross_products() {
+        let (x, y, z) = (Vector3(1, 0, 0), Vector3(0, 1, 0), Vector3(0, 0, 1));
+        assert_eq!(x.cross(&y), z);
+        assert_eq!(y.cross(&z), x);
+        assert_eq!(y.cross(&x), -z);
+        assert_eq!(x.dot(&y), 0);
+        assert_eq!(Vector3(1, 2, 3).dot(&Vector3(4, -5, 6)), 12);
+
+        let (a, b) = (Vector3(2, -3, 7), Vector3(-1, 4, 5));
+        let cross = a.cross(&b);
+        assert_eq!((cross.dot(&a), cross.dot(&b)), (0, 0));
+        assert_eq!(a.cross(&a), Vector3::zero());
+    }
+
+    #[test]
+    fn lengths_and_normalization() {
+        let v = Vector3(3.0f64, 4.0, 12.0);
+        assert_eq!(v.length(), 13.0);
+        assert_eq!(v.distance(&Vector3::zero()), 13.0);
+        let unit = v.normalize().unwrap();
+        assert!((unit.length() - 1.0).abs() < 1e-12);
+        assert_eq!(unit * 13.0, v);
+
+        assert_eq!(Vector3::<f64>::zero().normalize(), None);
+        assert_eq!(Vector3(0.0f32, -0.0, 0.0).normalize(), None);
+        assert_eq!(Vector3(0.0, 0.0, 0.0).lerp(&Vector3(2.0, 4.0, 8.0), 0.25), Vector3(0.5, 1.0, 2.0));
+    }
+
+    #[test]
+    fn indexing_and_map() {
+        let mut v = Vector3(7, 8, 9);
+        assert_eq!((v[0], v[1], v[2]), (v.x(), v.y(), v.z()));
+        v[1] = -8;
+        assert_eq!(v.map(|c: i32| c.abs()), Vector3(7, 8, 9));
+        assert_eq!(v.map(|c| c as f64 / 2.0), Vector3(3.5, -4.0, 4.5));
+    }
+
+    #[test]
+    #[should_panic(expected = "the index is 3")]
+    fn indexing_past_z_panics() {
+        let _ = Vector3(1, 2, 3)[3];
+    }
+
+    #[test]
+    fn parses_what_display_prints() {
+        let v = Vector3(1.5, -2.0, 0.25);
+        assert_eq!(v.to_string(), "(1.5, -2, 0.25)");
+        assert_eq!(format!("{:.2}", v), "(1.50, -2.00, 0.25)");
+        assert_eq!(v.to_string().parse::<Vector3<f64>>(), Ok(v));
+        assert_eq!("1, 2, 3".parse::<Vector3<i32>>(), Ok(Vector3(1, 2, 3)));
+        assert_eq!(" 1 2 3 ".parse::<Vector3<i32>>(), Ok(Vector3(1, 2, 3)));
+        assert_eq!("1 2".parse::<Vector3<i32>>(), Err(ParseVectorError::WrongComponentCount(2)));
+        assert_eq!(
+            "1, x, 3".parse::<Vector3<i32>>(),
+            Err(ParseVectorError::InvalidComponent { index: 1, text: "x".to_string() })
+        );
+    }
+}